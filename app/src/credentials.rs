@@ -0,0 +1,341 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encrypted API key store.
+//!
+//! Keys are kept per provider in a single AES-256-GCM encrypted file
+//! under the app config directory. The encryption key is derived from
+//! the machine id, so a copied file is useless on another machine.
+//! Where there is no machine id (containers, some sandboxes), a random
+//! per-install secret kept next to the store stands in for it. Key
+//! material is never logged and only leaves Rust through
+//! [`get_api_key`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+/// File name inside the app config directory.
+const STORE_FILE: &str = "credentials.bin";
+/// Per-install secret used when there is no machine id.
+const SECRET_FILE: &str = "install.key";
+const SECRET_LEN: usize = 32;
+
+/// File header: magic bytes followed by a format version.
+const MAGIC: &[u8; 4] = b"OMIK";
+const VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+/// Domain separator mixed into the key derivation.
+const KEY_CONTEXT: &[u8] = b"ocrmyimg/credentials/v1";
+
+/// Errors surfaced to the frontend by the credential commands.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum CredentialError {
+    /// The provider name is empty or contains unsupported characters.
+    InvalidProvider(String),
+    /// Neither a machine id nor a per-install secret is available to
+    /// bind the key to.
+    MachineId(String),
+    /// Reading or writing the store file failed.
+    Io(String),
+    /// The store exists but cannot be decrypted or parsed.
+    Corrupt(String),
+}
+
+impl fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidProvider(msg) => write!(f, "invalid provider: {msg}"),
+            Self::MachineId(msg) => write!(f, "machine id unavailable: {msg}"),
+            Self::Io(msg) => write!(f, "credential store I/O failed: {msg}"),
+            Self::Corrupt(msg) => write!(f, "credential store is corrupt: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+impl From<std::io::Error> for CredentialError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+/// Machine-bound encrypted key/value store for API keys.
+pub struct CredentialStore {
+    path: PathBuf,
+    /// What the encryption key is bound to, or why there is nothing;
+    /// the app still starts without it, only the key commands fail.
+    secret: Result<Vec<u8>, CredentialError>,
+    lock: Mutex<()>,
+}
+
+impl fmt::Debug for CredentialStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CredentialStore")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CredentialStore {
+    /// Open the store in `config_dir`, creating nothing until the first
+    /// write other than the per-install secret, when one is needed.
+    pub fn open(config_dir: &Path) -> Self {
+        let secret = machine_id().or_else(|e| {
+            log::warn!("{e}; binding credentials to this install instead");
+            install_secret(&config_dir.join(SECRET_FILE))
+        });
+        if let Err(e) = &secret {
+            log::warn!("Credential store unavailable: {e}");
+        }
+        Self {
+            path: config_dir.join(STORE_FILE),
+            secret,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Get the key stored for `provider`, if any.
+    pub fn get(&self, provider: &str) -> Result<Option<String>, CredentialError> {
+        validate_provider(provider)?;
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.remove(provider))
+    }
+
+    /// Store `key` for `provider`, replacing any previous value.
+    pub fn set(&self, provider: &str, key: &str) -> Result<(), CredentialError> {
+        validate_provider(provider)?;
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.load()?;
+        entries.insert(provider.to_string(), key.trim().to_string());
+        self.save(&entries)
+    }
+
+    /// Remove the key for `provider`. Missing keys are not an error.
+    pub fn delete(&self, provider: &str) -> Result<(), CredentialError> {
+        validate_provider(provider)?;
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.load()?;
+        if entries.remove(provider).is_some() {
+            self.save(&entries)?;
+        }
+        Ok(())
+    }
+
+    /// Read and decrypt all entries. A missing file is an empty store.
+    fn load(&self) -> Result<BTreeMap<String, String>, CredentialError> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+            return Err(CredentialError::Corrupt("unrecognized header".into()));
+        }
+        if data[MAGIC.len()] != VERSION {
+            return Err(CredentialError::Corrupt(format!(
+                "unsupported version {}",
+                data[MAGIC.len()]
+            )));
+        }
+
+        let salt = &data[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+        let nonce = Nonce::from_slice(&data[HEADER_LEN - NONCE_LEN..HEADER_LEN]);
+        let plaintext = self
+            .cipher(salt)?
+            .decrypt(nonce, &data[HEADER_LEN..])
+            .map_err(|_| CredentialError::Corrupt("decryption failed".into()))?;
+
+        serde_json::from_slice(&plaintext).map_err(|e| CredentialError::Corrupt(e.to_string()))
+    }
+
+    /// Encrypt and atomically replace the store file.
+    fn save(&self, entries: &BTreeMap<String, String>) -> Result<(), CredentialError> {
+        let plaintext =
+            serde_json::to_vec(entries).map_err(|e| CredentialError::Io(e.to_string()))?;
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher(&salt)?
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| CredentialError::Io("encryption failed".into()))?;
+
+        let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        write_private(&tmp, &data)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Build the cipher for a given salt.
+    fn cipher(&self, salt: &[u8]) -> Result<Aes256Gcm, CredentialError> {
+        let secret = self.secret.as_ref().map_err(Clone::clone)?;
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_CONTEXT);
+        hasher.update(salt);
+        hasher.update(secret);
+        let key = hasher.finalize();
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+}
+
+/// Write a file readable only by the current user.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(data)?;
+        file.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, data)
+    }
+}
+
+/// Read the per-install secret at `path`, creating it on first use.
+fn install_secret(path: &Path) -> Result<Vec<u8>, CredentialError> {
+    match fs::read(path) {
+        Ok(secret) if secret.len() == SECRET_LEN => return Ok(secret),
+        Ok(_) => return Err(CredentialError::Corrupt("malformed install secret".into())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(CredentialError::MachineId(e.to_string())),
+    }
+    let mut secret = vec![0u8; SECRET_LEN];
+    OsRng.fill_bytes(&mut secret);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| CredentialError::MachineId(e.to_string()))?;
+    }
+    write_private(path, &secret).map_err(|e| CredentialError::MachineId(e.to_string()))?;
+    Ok(secret)
+}
+
+/// Accept short lowercase identifiers such as `imgbb`.
+fn validate_provider(provider: &str) -> Result<(), CredentialError> {
+    let valid = !provider.is_empty()
+        && provider.len() <= 32
+        && provider
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(CredentialError::InvalidProvider(provider.to_string()))
+    }
+}
+
+// --- Platform Specific Machine Ids ---
+
+#[cfg(target_os = "linux")]
+fn machine_id() -> Result<Vec<u8>, CredentialError> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+        .map(String::into_bytes)
+        .ok_or_else(|| CredentialError::MachineId("no /etc/machine-id".into()))
+}
+
+#[cfg(target_os = "macos")]
+fn machine_id() -> Result<Vec<u8>, CredentialError> {
+    use std::process::Command;
+
+    let output = Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("IOPlatformUUID"))
+        .and_then(|line| line.split('"').nth(3))
+        .map(|id| id.as_bytes().to_vec())
+        .ok_or_else(|| CredentialError::MachineId("no IOPlatformUUID".into()))
+}
+
+#[cfg(target_os = "windows")]
+fn machine_id() -> Result<Vec<u8>, CredentialError> {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let output = Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(|id| id.as_bytes().to_vec())
+        .ok_or_else(|| CredentialError::MachineId("no MachineGuid".into()))
+}
+
+// --- Commands ---
+
+/// Return the stored key for `provider`, or an empty string if none.
+#[tauri::command]
+pub fn get_api_key(
+    store: State<'_, CredentialStore>,
+    provider: String,
+) -> Result<String, CredentialError> {
+    Ok(store.get(&provider)?.unwrap_or_default())
+}
+
+/// Store the key for `provider`.
+#[tauri::command]
+pub fn set_api_key(
+    store: State<'_, CredentialStore>,
+    provider: String,
+    key: String,
+) -> Result<(), CredentialError> {
+    store.set(&provider, &key)
+}
+
+/// Forget the key for `provider`.
+#[tauri::command]
+pub fn delete_api_key(
+    store: State<'_, CredentialStore>,
+    provider: String,
+) -> Result<(), CredentialError> {
+    store.delete(&provider)
+}
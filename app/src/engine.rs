@@ -0,0 +1,332 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Warm OCR engine supervisor.
+//!
+//! Keeps one `ocr-engine --serve` process alive and talks to it over
+//! line-delimited JSON-RPC. Requests are queued and handled one at a
+//! time; the process is restarted when it dies and asked to shut down
+//! when the app exits.
+
+use std::time::{Duration, Instant};
+
+use ocr_model::Page;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::async_runtime::Receiver;
+use tauri::AppHandle;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::ocr::OcrError;
+
+/// Sidecar name as declared in `bundle.externalBin`.
+const SIDECAR: &str = "ocr-engine";
+
+/// Upper bound for a single request, including model initialization.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long a clean shutdown may take before the process is killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Crash restarts allowed within [`RESTART_WINDOW`] before giving up
+/// on eager restarts and waiting for the next request instead.
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// JSON-RPC error codes used by the engine server.
+const IMAGE_NOT_FOUND: i64 = -32001;
+const ENGINE_INIT: i64 = -32002;
+
+/// Messages accepted by the supervisor task.
+enum Message {
+    Ocr {
+        path: String,
+        reply: oneshot::Sender<Result<Page, OcrError>>,
+    },
+    Shutdown {
+        done: oneshot::Sender<()>,
+    },
+}
+
+/// Handle to the supervisor task, stored in Tauri state.
+pub struct EngineSupervisor {
+    tx: mpsc::Sender<Message>,
+}
+
+impl EngineSupervisor {
+    /// Start the supervisor and warm up the engine in the background.
+    pub fn start(app: AppHandle) -> Self {
+        let (tx, rx) = mpsc::channel(32);
+        tauri::async_runtime::spawn(supervise(app, rx));
+        Self { tx }
+    }
+
+    /// Queue an OCR request and wait for its result.
+    pub async fn ocr(&self, path: String) -> Result<Page, OcrError> {
+        let (reply, rx) = oneshot::channel();
+        self.tx
+            .send(Message::Ocr { path, reply })
+            .await
+            .map_err(|_| OcrError::EngineFailed("engine supervisor stopped".into()))?;
+        rx.await
+            .map_err(|_| OcrError::EngineFailed("engine supervisor stopped".into()))?
+    }
+
+    /// Queue several OCR requests at once and wait for all results, in
+    /// the order of `paths`. Everything is queued before the first reply
+    /// is awaited, so the engine never sits idle between requests.
+    pub async fn ocr_many(&self, paths: Vec<String>) -> Result<Vec<Page>, OcrError> {
+        let stopped = || OcrError::EngineFailed("engine supervisor stopped".into());
+        let mut replies = Vec::with_capacity(paths.len());
+        for path in paths {
+            let (reply, rx) = oneshot::channel();
+            self.tx
+                .send(Message::Ocr { path, reply })
+                .await
+                .map_err(|_| stopped())?;
+            replies.push(rx);
+        }
+        let mut pages = Vec::with_capacity(replies.len());
+        for rx in replies {
+            pages.push(rx.await.map_err(|_| stopped())??);
+        }
+        Ok(pages)
+    }
+
+    /// Ask the engine to exit and wait until it has.
+    pub async fn shutdown(&self) {
+        let (done, rx) = oneshot::channel();
+        if self.tx.send(Message::Shutdown { done }).await.is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+/// Why a request to the engine process did not produce a response.
+enum Failure {
+    /// The process exited or its pipes closed.
+    Crashed,
+    /// No response within the time limit.
+    TimedOut,
+    /// The request could not be written.
+    Io(String),
+}
+
+/// JSON-RPC response from the engine.
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// A running `ocr-engine --serve` process.
+struct EngineProcess {
+    child: CommandChild,
+    events: Receiver<CommandEvent>,
+    next_id: u64,
+}
+
+impl EngineProcess {
+    fn spawn(app: &AppHandle) -> Result<Self, OcrError> {
+        let (events, child) = app
+            .shell()
+            .sidecar(SIDECAR)
+            .and_then(|cmd| cmd.arg("--serve").spawn())
+            .map_err(|e| OcrError::EngineInit(e.to_string()))?;
+        Ok(Self {
+            child,
+            events,
+            next_id: 1,
+        })
+    }
+
+    /// Send a request and wait for the response with the same id.
+    async fn call(
+        &mut self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Response, Failure> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut line =
+            json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string();
+        line.push('\n');
+        self.child
+            .write(line.as_bytes())
+            .map_err(|e| Failure::Io(e.to_string()))?;
+
+        let wait = async {
+            while let Some(event) = self.events.recv().await {
+                match event {
+                    CommandEvent::Stdout(line) => {
+                        // Notifications and stray output carry no matching id.
+                        if let Ok(response) = serde_json::from_slice::<Response>(&line) {
+                            if response.id == Some(id) {
+                                return Ok(response);
+                            }
+                        }
+                    }
+                    CommandEvent::Terminated(_) => return Err(Failure::Crashed),
+                    _ => {}
+                }
+            }
+            Err(Failure::Crashed)
+        };
+
+        tokio::time::timeout(timeout, wait)
+            .await
+            .unwrap_or(Err(Failure::TimedOut))
+    }
+
+    /// Drain events until the process exits. Used while idle.
+    async fn exited(&mut self) {
+        while let Some(event) = self.events.recv().await {
+            if let CommandEvent::Terminated(_) = event {
+                return;
+            }
+        }
+    }
+
+    /// Ask the process to exit, killing it if it does not.
+    async fn shutdown(mut self) {
+        let asked = self
+            .call("shutdown", Value::Null, SHUTDOWN_TIMEOUT)
+            .await
+            .is_ok();
+        if !asked
+            || tokio::time::timeout(SHUTDOWN_TIMEOUT, self.exited())
+                .await
+                .is_err()
+        {
+            let _ = self.child.kill();
+        }
+    }
+}
+
+/// Supervisor loop: owns the process and serves queued requests.
+async fn supervise(app: AppHandle, mut rx: mpsc::Receiver<Message>) {
+    let mut engine = EngineProcess::spawn(&app).ok();
+    let mut restarts: Vec<Instant> = Vec::new();
+
+    loop {
+        let message = match engine.as_mut() {
+            Some(process) => tokio::select! {
+                message = rx.recv() => message,
+                _ = process.exited() => {
+                    engine = None;
+                    if allow_restart(&mut restarts) {
+                        engine = EngineProcess::spawn(&app).ok();
+                    }
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+
+        match message {
+            Some(Message::Ocr { path, reply }) => {
+                let result = handle_ocr(&app, &mut engine, &mut restarts, path).await;
+                let _ = reply.send(result);
+            }
+            Some(Message::Shutdown { done }) => {
+                if let Some(process) = engine.take() {
+                    process.shutdown().await;
+                }
+                let _ = done.send(());
+                return;
+            }
+            None => {
+                if let Some(process) = engine.take() {
+                    process.shutdown().await;
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Run one OCR request, restarting the engine once if it crashed.
+async fn handle_ocr(
+    app: &AppHandle,
+    engine: &mut Option<EngineProcess>,
+    restarts: &mut Vec<Instant>,
+    path: String,
+) -> Result<Page, OcrError> {
+    let params = json!({ "path": path });
+    let mut retried = false;
+
+    loop {
+        if engine.is_none() {
+            *engine = Some(EngineProcess::spawn(app)?);
+        }
+        let process = engine.as_mut().expect("engine was just spawned");
+
+        match process.call("ocr", params.clone(), REQUEST_TIMEOUT).await {
+            Ok(response) => {
+                restarts.clear();
+                return parse_response(response);
+            }
+            Err(Failure::TimedOut) => {
+                if let Some(process) = engine.take() {
+                    let _ = process.child.kill();
+                }
+                return Err(OcrError::Timeout(format!(
+                    "no result after {}s",
+                    REQUEST_TIMEOUT.as_secs()
+                )));
+            }
+            Err(failure) => {
+                if let Some(process) = engine.take() {
+                    let _ = process.child.kill();
+                }
+                if retried || !allow_restart(restarts) {
+                    return Err(OcrError::EngineFailed(match failure {
+                        Failure::Io(msg) => msg,
+                        _ => "engine process exited unexpectedly".into(),
+                    }));
+                }
+                retried = true;
+            }
+        }
+    }
+}
+
+/// Record a restart and report whether it stays within the budget.
+fn allow_restart(restarts: &mut Vec<Instant>) -> bool {
+    let now = Instant::now();
+    restarts.retain(|at| now.duration_since(*at) < RESTART_WINDOW);
+    if restarts.len() >= MAX_RESTARTS {
+        return false;
+    }
+    restarts.push(now);
+    true
+}
+
+/// Map a JSON-RPC response onto results or a structured error.
+fn parse_response(response: Response) -> Result<Page, OcrError> {
+    if let Some(error) = response.error {
+        return Err(match error.code {
+            IMAGE_NOT_FOUND => OcrError::ImageNotFound(error.message),
+            ENGINE_INIT => OcrError::EngineInit(error.message),
+            _ => OcrError::EngineFailed(error.message),
+        });
+    }
+    let result = response
+        .result
+        .ok_or_else(|| OcrError::MalformedOutput("response has no result".into()))?;
+    serde_json::from_value(result).map_err(|e| OcrError::MalformedOutput(e.to_string()))
+}
@@ -0,0 +1,96 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Export commands.
+//!
+//! Thin wrappers around the `ocr-export` crate. Exports run on a
+//! blocking thread since they read images and write whole files.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use ocr_export::{PdfOptions, SourcePage};
+use ocr_model::Page;
+use serde::{Deserialize, Serialize};
+
+/// One scanned page as sent by the frontend.
+#[derive(Debug, Deserialize)]
+pub struct ExportPage {
+    /// Path of the image the page was scanned from.
+    pub image: PathBuf,
+    pub page: Page,
+}
+
+/// Errors surfaced to the frontend by the export commands.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ExportError {
+    /// Nothing to export.
+    NoPages(String),
+    /// Writing the output failed.
+    Failed(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoPages(msg) => write!(f, "nothing to export: {msg}"),
+            Self::Failed(msg) => write!(f, "export failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Save scanned pages as a searchable PDF at `output`.
+#[tauri::command]
+pub async fn export_pdf(pages: Vec<ExportPage>, output: PathBuf) -> Result<(), ExportError> {
+    run_export(pages, output, |pages, output| {
+        let options = PdfOptions {
+            title: title_of(output),
+            ..PdfOptions::default()
+        };
+        ocr_export::save_pdf(pages, &options, output)
+    })
+    .await
+}
+
+/// Save scanned pages as an hOCR document at `output`.
+#[tauri::command]
+pub async fn export_hocr(pages: Vec<ExportPage>, output: PathBuf) -> Result<(), ExportError> {
+    run_export(pages, output, ocr_export::save_hocr).await
+}
+
+/// Save scanned pages as an ALTO XML document at `output`.
+#[tauri::command]
+pub async fn export_alto(pages: Vec<ExportPage>, output: PathBuf) -> Result<(), ExportError> {
+    run_export(pages, output, ocr_export::save_alto).await
+}
+
+/// Run `save` for `pages` on a blocking thread.
+async fn run_export<F>(pages: Vec<ExportPage>, output: PathBuf, save: F) -> Result<(), ExportError>
+where
+    F: FnOnce(&[SourcePage<'_>], &Path) -> anyhow::Result<()> + Send + 'static,
+{
+    if pages.is_empty() {
+        return Err(ExportError::NoPages("no pages given".into()));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let sources: Vec<SourcePage<'_>> = pages
+            .iter()
+            .map(|p| SourcePage {
+                image: &p.image,
+                ocr: &p.page,
+            })
+            .collect();
+        save(&sources, &output).map_err(|e| ExportError::Failed(format!("{e:#}")))
+    })
+    .await
+    .map_err(|e| ExportError::Failed(e.to_string()))?
+}
+
+/// Use the output file name as the document title.
+fn title_of(path: &Path) -> Option<String> {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned())
+}
@@ -0,0 +1,338 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! External URLs and the ImgBB key window.
+//!
+//! Every URL the frontend wants to open in the system browser is checked
+//! against a [`UrlPolicy`] first. The policy defaults to the Google and
+//! ImgBB hosts the app links to and can be replaced by dropping a
+//! `url-policy.json` into the app config directory.
+//!
+//! The ImgBB window shows the remote site, which gets no IPC access. An
+//! injected bar takes the API key and hands it over by navigating to a
+//! sentinel URL; the navigation is intercepted, the key stored and
+//! passed to the main window as `clipboard-text`.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, Manager, State, Url, WebviewUrl, WebviewWindowBuilder, WindowEvent,
+};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::credentials::CredentialStore;
+
+/// Policy file name inside the app config directory.
+const POLICY_FILE: &str = "url-policy.json";
+
+/// Label of the main application window.
+const MAIN_WINDOW: &str = "main";
+
+/// Label and start page of the ImgBB key window.
+const IMGBB_WINDOW: &str = "imgbb";
+const IMGBB_URL: &str = "https://api.imgbb.com/";
+
+/// Key entry bar injected into the ImgBB window.
+const IMGBB_KEY_SCRIPT: &str = include_str!("imgbb_key.js");
+/// Where the key entry bar navigates to hand the key over. `.invalid`
+/// never resolves, so the page cannot load even if not intercepted.
+const KEY_HANDOFF_HOST: &str = "ocrmyimg.invalid";
+const KEY_HANDOFF_PATH: &str = "/imgbb-key";
+const IMGBB_PROVIDER: &str = "imgbb";
+const MAX_KEY_LEN: usize = 128;
+
+/// Payload of the `clipboard-text` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyHandoff {
+    pub provider: String,
+    pub key: String,
+}
+
+/// Errors surfaced to the frontend by the external commands.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ExternalError {
+    /// The URL could not be parsed.
+    InvalidUrl(String),
+    /// The URL is not allowed by the policy.
+    Blocked(String),
+    /// The system opener failed.
+    Open(String),
+    /// Creating or closing a window failed.
+    Window(String),
+}
+
+impl fmt::Display for ExternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(msg) => write!(f, "invalid URL: {msg}"),
+            Self::Blocked(msg) => write!(f, "URL not allowed: {msg}"),
+            Self::Open(msg) => write!(f, "failed to open URL: {msg}"),
+            Self::Window(msg) => write!(f, "window operation failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ExternalError {}
+
+/// Allowlist of URL schemes and hosts that may be opened externally.
+///
+/// Hosts match exactly, or as any subdomain when written as `*.example.com`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlPolicy {
+    pub schemes: Vec<String>,
+    pub hosts: Vec<String>,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        Self {
+            schemes: vec!["https".into()],
+            hosts: vec![
+                "google.com".into(),
+                "*.google.com".into(),
+                "imgbb.com".into(),
+                "*.imgbb.com".into(),
+                "ibb.co".into(),
+            ],
+        }
+    }
+}
+
+impl UrlPolicy {
+    /// Load the policy from `config_dir`, falling back to the default
+    /// when there is none. A policy that cannot be read or parsed also
+    /// falls back, with a warning, so a tightened policy is not dropped
+    /// without notice.
+    pub fn load(config_dir: &Path) -> Self {
+        let path = config_dir.join(POLICY_FILE);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                log::warn!(
+                    "Failed to read {}: {e}; using the default URL policy",
+                    path.display()
+                );
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            log::warn!(
+                "Malformed {}: {e}; using the default URL policy",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+
+    /// Parse `url` and return it if the policy allows it.
+    pub fn check(&self, url: &str) -> Result<Url, ExternalError> {
+        let parsed = Url::parse(url).map_err(|e| ExternalError::InvalidUrl(e.to_string()))?;
+
+        if !self
+            .schemes
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(parsed.scheme()))
+        {
+            return Err(ExternalError::Blocked(format!(
+                "scheme '{}' is not allowed",
+                parsed.scheme()
+            )));
+        }
+
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err(ExternalError::Blocked("credentials in URL".into()));
+        }
+
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| ExternalError::Blocked("URL has no host".into()))?
+            .to_ascii_lowercase();
+        if !self
+            .hosts
+            .iter()
+            .any(|pattern| host_matches(pattern, &host))
+        {
+            return Err(ExternalError::Blocked(format!(
+                "host '{host}' is not allowed"
+            )));
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Match a host against an allowlist entry.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+/// Open an allowed URL in the system browser.
+#[tauri::command]
+pub fn open_external_url(
+    app: AppHandle,
+    policy: State<'_, UrlPolicy>,
+    url: String,
+) -> Result<(), ExternalError> {
+    let url = policy.check(&url)?;
+    app.opener()
+        .open_url(url.as_str(), None::<&str>)
+        .map_err(|e| ExternalError::Open(e.to_string()))
+}
+
+/// The key handed over by navigating to `url`, or `None` when `url` is
+/// not the handoff URL or carries no plausible key.
+fn key_handoff(url: &Url) -> Option<KeyHandoff> {
+    if url.scheme() != "https"
+        || url.host_str() != Some(KEY_HANDOFF_HOST)
+        || url.path() != KEY_HANDOFF_PATH
+    {
+        return None;
+    }
+    let (_, key) = url.query_pairs().find(|(name, _)| name == "key")?;
+    let key = key.trim();
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key.bytes().all(|b| b.is_ascii_alphanumeric());
+    valid.then(|| KeyHandoff {
+        provider: IMGBB_PROVIDER.into(),
+        key: key.to_string(),
+    })
+}
+
+/// Store a handed-over key and pass it to the main window.
+fn accept_key(app: &AppHandle, handoff: KeyHandoff) {
+    // The key still serves the search waiting for it if storing fails.
+    if let Err(e) = app
+        .state::<CredentialStore>()
+        .set(&handoff.provider, &handoff.key)
+    {
+        log::warn!("Failed to store the {} key: {e}", handoff.provider);
+    }
+    if let Err(e) = app.emit_to(MAIN_WINDOW, "clipboard-text", handoff) {
+        log::warn!("Failed to pass the key to the main window: {e}");
+    }
+}
+
+/// Open the ImgBB window where the user gets their API key.
+///
+/// Focuses the window if it is already open. A key entered in the
+/// window is stored and sent to the main window as `clipboard-text`.
+/// When the user closes it, `imgbb-popup-closed` is emitted to the main
+/// window. Async so the window is not created on the main thread, which
+/// deadlocks on Windows.
+#[tauri::command]
+pub async fn open_imgbb_window(app: AppHandle) -> Result<(), ExternalError> {
+    if let Some(window) = app.get_webview_window(IMGBB_WINDOW) {
+        return window
+            .set_focus()
+            .map_err(|e| ExternalError::Window(e.to_string()));
+    }
+
+    let url = Url::parse(IMGBB_URL).map_err(|e| ExternalError::InvalidUrl(e.to_string()))?;
+    let handle = app.clone();
+    let mut builder = WebviewWindowBuilder::new(&app, IMGBB_WINDOW, WebviewUrl::External(url))
+        .title("ImgBB API Key")
+        .inner_size(520.0, 680.0)
+        .center()
+        .initialization_script(IMGBB_KEY_SCRIPT)
+        .on_navigation(move |url| match key_handoff(url) {
+            Some(handoff) => {
+                accept_key(&handle, handoff);
+                false
+            }
+            None => true,
+        });
+    if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+        builder = builder
+            .parent(&main)
+            .map_err(|e| ExternalError::Window(e.to_string()))?;
+    }
+    let window = builder
+        .build()
+        .map_err(|e| ExternalError::Window(e.to_string()))?;
+
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            let _ = handle.emit_to(MAIN_WINDOW, "imgbb-popup-closed", ());
+        }
+    });
+    Ok(())
+}
+
+/// Close the ImgBB window if it is open.
+#[tauri::command]
+pub fn close_imgbb_window(app: AppHandle) -> Result<(), ExternalError> {
+    match app.get_webview_window(IMGBB_WINDOW) {
+        Some(window) => window
+            .close()
+            .map_err(|e| ExternalError::Window(e.to_string())),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handoff(url: &str) -> Option<KeyHandoff> {
+        key_handoff(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn key_handoff_accepts_the_sentinel_url() {
+        assert_eq!(
+            handoff("https://ocrmyimg.invalid/imgbb-key?key=0123456789abcdef0123456789ABCDEF"),
+            Some(KeyHandoff {
+                provider: "imgbb".into(),
+                key: "0123456789abcdef0123456789ABCDEF".into(),
+            })
+        );
+        // As the key bar encodes it, surrounding blanks included.
+        assert_eq!(
+            handoff("https://ocrmyimg.invalid/imgbb-key?key=%20abc123%20").map(|h| h.key),
+            Some("abc123".into())
+        );
+    }
+
+    #[test]
+    fn key_handoff_ignores_other_navigation() {
+        assert_eq!(handoff("https://api.imgbb.com/?key=abc123"), None);
+        assert_eq!(handoff("https://ocrmyimg.invalid/other?key=abc123"), None);
+        assert_eq!(
+            handoff("http://ocrmyimg.invalid/imgbb-key?key=abc123"),
+            None
+        );
+        assert_eq!(
+            handoff("https://ocrmyimg.invalid.example.com/imgbb-key?key=abc123"),
+            None
+        );
+    }
+
+    #[test]
+    fn key_handoff_rejects_implausible_keys() {
+        assert_eq!(handoff("https://ocrmyimg.invalid/imgbb-key"), None);
+        assert_eq!(handoff("https://ocrmyimg.invalid/imgbb-key?key="), None);
+        assert_eq!(
+            handoff("https://ocrmyimg.invalid/imgbb-key?key=%3Cscript%3E"),
+            None
+        );
+        let long = "a".repeat(MAX_KEY_LEN + 1);
+        assert_eq!(
+            handoff(&format!("https://ocrmyimg.invalid/imgbb-key?key={long}")),
+            None
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+// Key entry bar injected into the ImgBB window. The page is remote and
+// has no IPC access, so the key is handed over by navigating to a
+// sentinel URL that the app intercepts and never loads.
+(function () {
+  if (window.top !== window) return;
+
+  const HANDOFF = "https://ocrmyimg.invalid/imgbb-key";
+  const BAR_ID = "ocrmyimg-key-bar";
+  const IMGBB_KEY = /^[0-9a-f]{32}$/i;
+
+  function mount() {
+    if (!document.body || document.getElementById(BAR_ID)) return;
+
+    const bar = document.createElement("form");
+    bar.id = BAR_ID;
+    bar.style.cssText =
+      "position:fixed;left:0;right:0;bottom:0;z-index:2147483647;display:flex;" +
+      "gap:8px;padding:10px;background:#202124;color:#fff;font:14px sans-serif";
+
+    const input = document.createElement("input");
+    input.placeholder = "Paste your ImgBB API key";
+    input.autocomplete = "off";
+    input.style.cssText = "flex:1;padding:6px";
+
+    const save = document.createElement("button");
+    save.type = "submit";
+    save.textContent = "Use key";
+
+    bar.append(input, save);
+    bar.addEventListener("submit", (event) => {
+      event.preventDefault();
+      const key = input.value.trim();
+      if (key) {
+        window.location.href = HANDOFF + "?key=" + encodeURIComponent(key);
+      }
+    });
+    document.body.appendChild(bar);
+
+    // Once signed in, the API page shows the key in a field; offer it.
+    setInterval(() => {
+      if (input.value) return;
+      const found = Array.from(document.querySelectorAll("input"))
+        .filter((field) => field !== input)
+        .map((field) => field.value.trim())
+        .find((value) => IMGBB_KEY.test(value));
+      if (found) input.value = found;
+    }, 1000);
+  }
+
+  if (document.readyState === "loading") {
+    document.addEventListener("DOMContentLoaded", mount);
+  } else {
+    mount();
+  }
+})();
@@ -0,0 +1,95 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Single-instance handling.
+//!
+//! Only one window runs at a time. `ocrmyimg <image>` while the app is
+//! already open hands the image to the running instance, which focuses
+//! its window and scans it; the new process exits straight away. The
+//! image from the command line of the first launch is held until the
+//! frontend asks for it with [`take_launch_image`], and so is an image
+//! forwarded before then, since the frontend may not be listening yet.
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Label of the main application window.
+const MAIN_WINDOW: &str = "main";
+
+/// Event carrying an image path forwarded by a later launch.
+const OPEN_IMAGE_EVENT: &str = "open-image";
+
+/// Name of the lock and socket in the runtime directory.
+#[cfg(unix)]
+pub const LOCK_NAME: &str = "ocrmyimg";
+
+/// The image to open at startup, until the frontend takes it.
+pub struct LaunchImage(Mutex<Launch>);
+
+struct Launch {
+    /// The image passed on the command line of this launch, or the
+    /// latest one forwarded before the frontend was ready.
+    image: Option<String>,
+    /// Whether the frontend has taken the launch image, and so listens
+    /// for forwarded ones.
+    taken: bool,
+}
+
+impl LaunchImage {
+    pub fn from_args(cwd: &Path, args: &[OsString]) -> Self {
+        Self(Mutex::new(Launch {
+            image: image_arg(cwd, args),
+            taken: false,
+        }))
+    }
+
+    /// Hold `path` for [`take_launch_image`] if the frontend has not
+    /// asked yet. Returns it back when the frontend is ready for it.
+    #[cfg(unix)]
+    fn hold(&self, path: String) -> Option<String> {
+        let mut launch = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if launch.taken {
+            return Some(path);
+        }
+        launch.image = Some(path);
+        None
+    }
+}
+
+/// Return the image to open at startup, once. The frontend listens for
+/// forwarded images before calling this.
+#[tauri::command]
+pub fn take_launch_image(launch: State<'_, LaunchImage>) -> Option<String> {
+    let mut launch = launch.0.lock().unwrap_or_else(|e| e.into_inner());
+    launch.taken = true;
+    launch.image.take()
+}
+
+/// The first argument that is not an option, as an absolute path.
+fn image_arg(cwd: &Path, args: &[OsString]) -> Option<String> {
+    args.iter()
+        .find(|arg| !arg.to_string_lossy().starts_with('-'))
+        .map(|arg| cwd.join(arg).to_string_lossy().into_owned())
+}
+
+/// Serve launches forwarded to `lock`: focus the main window and ask the
+/// frontend to scan the image, if one was given.
+#[cfg(unix)]
+pub fn listen(app: AppHandle, lock: &mut sys_instance_lock::InstanceLock) -> std::io::Result<()> {
+    lock.listen(move |request| {
+        if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        let path = image_arg(&request.cwd, &request.args)
+            .and_then(|path| app.state::<LaunchImage>().hold(path));
+        if let Some(path) = path {
+            let _ = app.emit_to(MAIN_WINDOW, OPEN_IMAGE_EVENT, path);
+        }
+    })?;
+    Ok(())
+}
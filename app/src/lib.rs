@@ -5,6 +5,22 @@
 //!
 //! Provides the main application entry point and command handlers.
 
+mod credentials;
+mod engine;
+mod export;
+mod external;
+mod instance;
+mod ocr;
+
+use std::ffi::OsString;
+
+use tauri::{Manager, RunEvent};
+
+use credentials::CredentialStore;
+use engine::EngineSupervisor;
+use external::UrlPolicy;
+use instance::LaunchImage;
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
@@ -12,10 +28,61 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let launch_image = LaunchImage::from_args(&cwd, &args);
+
+    // A second launch forwards its arguments and exits here.
+    #[cfg(unix)]
+    let instance_lock = {
+        use sys_instance_lock::{Instance, InstanceLock};
+        match InstanceLock::acquire(instance::LOCK_NAME, args) {
+            Ok(Instance::Primary(lock)) => Some(lock),
+            Ok(Instance::Secondary) => return,
+            Err(e) => {
+                // Run anyway, just without forwarding.
+                log::warn!("single-instance lock unavailable: {e}");
+                None
+            }
+        }
+    };
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .manage(launch_image)
+        .setup(move |app| {
+            #[cfg(unix)]
+            if let Some(mut lock) = instance_lock {
+                instance::listen(app.handle().clone(), &mut lock)?;
+                app.manage(lock);
+            }
+            app.manage(EngineSupervisor::start(app.handle().clone()));
+            let config_dir = app.path().app_config_dir()?;
+            app.manage(CredentialStore::open(&config_dir));
+            app.manage(UrlPolicy::load(&config_dir));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            instance::take_launch_image,
+            ocr::run_ocr,
+            credentials::get_api_key,
+            credentials::set_api_key,
+            credentials::delete_api_key,
+            external::open_external_url,
+            external::open_imgbb_window,
+            external::close_imgbb_window,
+            export::export_pdf,
+            export::export_hocr,
+            export::export_alto,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                let supervisor = app.state::<EngineSupervisor>();
+                tauri::async_runtime::block_on(supervisor.shutdown());
+            }
+        });
 }
@@ -0,0 +1,307 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! OCR command and result types.
+//!
+//! The frontend calls [`run_ocr`]; the work is delegated to the warm
+//! engine process managed by [`crate::engine`]. Multi-page inputs are
+//! split into page images first and each page is OCR'd in turn. Pages
+//! too large for the detector are read in overlapping tiles.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::DynamicImage;
+use image_ops::tiles::{self, Tile, TILE_OVERLAP, TILE_SIZE};
+use image_ops::{Affine, Loaded, Orientation, Preset, Processed, Source, SourceFormat};
+use ocr_model::{Page, Point};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::engine::EngineSupervisor;
+
+/// Errors surfaced to the frontend by [`run_ocr`].
+///
+/// Serialized as `{ "kind": "...", "message": "..." }` so the UI can
+/// branch on the kind and still show a readable message.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum OcrError {
+    /// The image path does not exist.
+    ImageNotFound(String),
+    /// The input could not be read or split into pages.
+    InvalidInput(String),
+    /// The sidecar could not be started or PaddleOCR failed to load.
+    EngineInit(String),
+    /// The engine ran but failed to process the image.
+    EngineFailed(String),
+    /// The engine did not finish within the time limit.
+    Timeout(String),
+    /// The engine output could not be parsed.
+    MalformedOutput(String),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImageNotFound(msg) => write!(f, "image not found: {msg}"),
+            Self::InvalidInput(msg) => write!(f, "could not read input: {msg}"),
+            Self::EngineInit(msg) => write!(f, "engine initialization failed: {msg}"),
+            Self::EngineFailed(msg) => write!(f, "OCR failed: {msg}"),
+            Self::Timeout(msg) => write!(f, "OCR timed out: {msg}"),
+            Self::MalformedOutput(msg) => write!(f, "malformed engine output: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// One page of results as returned to the frontend.
+///
+/// Has the same shape as [`crate::export::ExportPage`] so pages can be
+/// handed straight back for export.
+#[derive(Debug, Serialize)]
+pub struct OcrPage {
+    /// Image the page was read from: the input itself for upright plain
+    /// images, or a page written into the app cache for TIFFs, PDFs and
+    /// images that had to be turned upright.
+    pub image: PathBuf,
+    /// EXIF orientation applied to the stored pixels.
+    pub orientation: Orientation,
+    /// The results, with lines sorted into reading order. Boxes are in
+    /// the coordinates of `image`, even when the engine read a deskewed
+    /// or upscaled copy.
+    pub page: Page,
+    /// Skew corrected before OCR, in degrees; 0 when the page was
+    /// already straight or the preset does not deskew.
+    pub skew: f64,
+    /// Plain text of the whole page in reading order, used for
+    /// select-all.
+    pub text: String,
+}
+
+/// Results for every page of the input.
+#[derive(Debug, Serialize)]
+pub struct OcrDocument {
+    pub pages: Vec<OcrPage>,
+}
+
+/// Run the OCR engine on an image, multi-page TIFF or scanned PDF and
+/// return the results for all of its pages.
+///
+/// Each page goes through the chosen preprocessing `preset` first; the
+/// processed copy is what the engine sees, while the returned pages
+/// point at the unprocessed images for display. Requests are served by
+/// the warm engine owned by [`EngineSupervisor`].
+#[tauri::command]
+pub async fn run_ocr(
+    app: AppHandle,
+    supervisor: State<'_, EngineSupervisor>,
+    path: String,
+    preset: Option<Preset>,
+) -> Result<OcrDocument, OcrError> {
+    let input = PathBuf::from(&path);
+    if !input.is_file() {
+        return Err(OcrError::ImageNotFound(path));
+    }
+
+    let cache = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| OcrError::InvalidInput(e.to_string()))?
+        .join("pages");
+    let preset = preset.unwrap_or_default();
+    let prepared =
+        tauri::async_runtime::spawn_blocking(move || prepare_pages(&input, &cache, preset))
+            .await
+            .map_err(|e| OcrError::InvalidInput(e.to_string()))??;
+
+    let mut pages = Vec::with_capacity(prepared.len());
+    for PreparedPage {
+        image,
+        orientation,
+        ocr_input,
+        size,
+        transform,
+        skew,
+    } in prepared
+    {
+        let mut page = match ocr_input {
+            OcrInput::Whole(path) => supervisor.ocr(path.to_string_lossy().into_owned()).await?,
+            OcrInput::Tiled { tiles, size } => {
+                let paths = tiles
+                    .iter()
+                    .map(|(_, path)| path.to_string_lossy().into_owned())
+                    .collect();
+                let results = supervisor.ocr_many(paths).await?;
+                let tiles = tiles
+                    .iter()
+                    .zip(results)
+                    .map(|((tile, _), page)| (Point(tile.x.into(), tile.y.into()), page))
+                    .collect();
+                ocr_model::merge_tiles(tiles, size.0, size.1)
+            }
+        };
+        if !transform.is_identity() {
+            if let Some(inverse) = transform.invert() {
+                page.map_boxes(|p| {
+                    let (x, y) = inverse.apply(p.0, p.1);
+                    Point(x, y)
+                });
+            }
+            (page.width, page.height) = size;
+        }
+        let blocks = page.sort_reading_order();
+        let text = page.text_of(&blocks);
+        pages.push(OcrPage {
+            image,
+            orientation,
+            page,
+            skew,
+            text,
+        });
+    }
+    Ok(OcrDocument { pages })
+}
+
+/// A page ready for the engine.
+struct PreparedPage {
+    /// Unprocessed upright page image, shown to the user and used for
+    /// export.
+    image: PathBuf,
+    orientation: Orientation,
+    /// What the engine reads: `image` after preprocessing.
+    ocr_input: OcrInput,
+    /// Size of `image`, needed only when `transform` moves pixels.
+    size: (u32, u32),
+    /// Map from `image` onto `ocr_input`.
+    transform: Affine,
+    /// Skew corrected by preprocessing, in degrees.
+    skew: f64,
+}
+
+/// The image the engine reads for a page.
+enum OcrInput {
+    Whole(PathBuf),
+    /// Too large to read in one go: overlapping tiles of an image of
+    /// `size`, each saved to its own file.
+    Tiled {
+        tiles: Vec<(Tile, PathBuf)>,
+        size: (u32, u32),
+    },
+}
+
+/// Split `input` into pages and preprocess them.
+///
+/// An upright plain image that fits in one tile and that preprocessing
+/// leaves as it was is passed through untouched. Otherwise TIFF and PDF
+/// pages, images turned upright, preprocessed copies and tiles of large
+/// pages are written as PNGs into a fresh directory from [`scan_dir`].
+/// Each scan gets its own directory so the webview never shows a stale
+/// cached page.
+fn prepare_pages(
+    input: &Path,
+    cache: &Path,
+    preset: Preset,
+) -> Result<Vec<PreparedPage>, OcrError> {
+    let invalid = |e: anyhow::Error| OcrError::InvalidInput(format!("{e:#}"));
+    let source = Source::open(input).map_err(invalid)?;
+    let pipeline = preset.pipeline();
+    let is_image = source.format() == SourceFormat::Image;
+
+    let dir = scan_dir(cache).map_err(|e| OcrError::InvalidInput(e.to_string()))?;
+
+    source
+        .pages()
+        .enumerate()
+        .map(|(i, page)| {
+            let Loaded {
+                image: page,
+                orientation,
+                ..
+            } = page.map_err(invalid)?;
+            let save = |image: &DynamicImage, name: String| {
+                let path = dir.join(name);
+                image
+                    .save(&path)
+                    .map_err(|e| OcrError::InvalidInput(format!("page {}: {e}", i + 1)))?;
+                Ok::<_, OcrError>(path)
+            };
+
+            let image = if is_image && orientation == Orientation::Normal {
+                input.to_path_buf()
+            } else {
+                save(&page, format!("page-{:04}.png", i + 1))?
+            };
+            let size = (page.width(), page.height());
+            let Processed {
+                image: processed,
+                transform,
+                skew,
+            } = pipeline.run(page);
+
+            let (width, height) = (processed.width(), processed.height());
+            let ocr_input = if width > TILE_SIZE || height > TILE_SIZE {
+                let tiles = tiles::tiles(width, height, TILE_SIZE, TILE_OVERLAP)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(t, tile)| {
+                        let name = format!("page-{:04}.tile-{:02}.png", i + 1, t + 1);
+                        let (cropped, _) = tile.crop(&processed);
+                        Ok((tile, save(&cropped, name)?))
+                    })
+                    .collect::<Result<_, OcrError>>()?;
+                OcrInput::Tiled {
+                    tiles,
+                    size: (width, height),
+                }
+            } else if pipeline.is_geometric() && transform.is_identity() {
+                OcrInput::Whole(image.clone())
+            } else {
+                OcrInput::Whole(save(&processed, format!("page-{:04}.ocr.png", i + 1))?)
+            };
+            Ok(PreparedPage {
+                image,
+                orientation,
+                ocr_input,
+                size,
+                transform,
+                skew,
+            })
+        })
+        .collect()
+}
+
+/// Create a new, empty directory for one scan's pages under `cache`.
+///
+/// Scans of this run share a session directory and are never removed
+/// while the app is open, since their results may still be on screen
+/// or about to be exported. The first scan of a run removes the pages
+/// left behind by earlier runs instead.
+fn scan_dir(cache: &Path) -> io::Result<PathBuf> {
+    static SESSION: OnceLock<PathBuf> = OnceLock::new();
+    static SCANS: AtomicU32 = AtomicU32::new(0);
+
+    let session = SESSION.get_or_init(|| {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let name = format!("{stamp}-{}", process::id());
+        for entry in fs::read_dir(cache).into_iter().flatten().flatten() {
+            if entry.file_name() != name.as_str() {
+                let _ = fs::remove_dir_all(entry.path());
+            }
+        }
+        cache.join(name)
+    });
+    let dir = session.join(SCANS.fetch_add(1, Ordering::Relaxed).to_string());
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
@@ -0,0 +1,168 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Command-line front end for ocrmyimg.
+//!
+//! Runs the `ocr-engine` sidecar on images, or loads results it wrote
+//! earlier, and saves them through the same [`ocr_model`] types and
+//! exporters as the app.
+//!
+//! Usage:
+//!   ocrmyimg-cli recognize <image>... [--format F] [-o OUT]
+//!   ocrmyimg-cli export <result.json> --image <image>... [--format F] [-o OUT]
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use ocr_export::{write_alto, write_hocr, write_pdf, PdfOptions, SourcePage};
+use ocr_model::{Document, Page};
+
+#[derive(Parser)]
+#[command(name = "ocrmyimg-cli")]
+#[command(about = "Extract text from images with the ocrmyimg engine")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Run the OCR engine on images
+    Recognize {
+        images: Vec<PathBuf>,
+        /// Engine executable
+        #[arg(long, default_value = "ocr-engine")]
+        engine: PathBuf,
+        #[command(flatten)]
+        output: Output,
+    },
+
+    /// Convert a result saved as JSON
+    Export {
+        /// Page or document JSON, as written by the engine or `--format json`
+        result: PathBuf,
+        /// Source image of each page, in order; needed for PDF, hOCR and ALTO
+        #[arg(long = "image")]
+        images: Vec<PathBuf>,
+        #[command(flatten)]
+        output: Output,
+    },
+}
+
+#[derive(clap::Args)]
+struct Output {
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Output file; standard output when omitted
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Text,
+    Hocr,
+    Alto,
+    Pdf,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Recognize {
+            images,
+            engine,
+            output,
+        } => {
+            ensure!(!images.is_empty(), "no images given");
+            let pages = images
+                .iter()
+                .map(|image| recognize(&engine, image))
+                .collect::<Result<Vec<_>>>()?;
+            write(&Document { pages }, &images, &output)
+        }
+        Commands::Export {
+            result,
+            images,
+            output,
+        } => {
+            let data = fs::read(&result)
+                .with_context(|| format!("failed to read {}", result.display()))?;
+            let document = parse_document(&data)
+                .with_context(|| format!("failed to parse {}", result.display()))?;
+            write(&document, &images, &output)
+        }
+    }
+}
+
+/// Run the engine on one image and parse its page.
+fn recognize(engine: &Path, image: &Path) -> Result<Page> {
+    let output = Command::new(engine)
+        .arg(image)
+        .output()
+        .with_context(|| format!("failed to run {}", engine.display()))?;
+    // Failures are reported on stdout as an error object.
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("{} printed no result", engine.display()))?;
+    if let Some(error) = value.get("error").and_then(|e| e.as_str()) {
+        bail!("{}: {error}", image.display());
+    }
+    serde_json::from_value(value).context("unexpected engine output")
+}
+
+/// A document, or a single page as the engine writes it.
+fn parse_document(data: &[u8]) -> Result<Document> {
+    let value: serde_json::Value = serde_json::from_slice(data)?;
+    if value.get("pages").is_some() {
+        return Ok(serde_json::from_value(value)?);
+    }
+    Ok(Document {
+        pages: vec![serde_json::from_value(value)?],
+    })
+}
+
+fn write(document: &Document, images: &[PathBuf], output: &Output) -> Result<()> {
+    let mut out: Box<dyn Write> = match &output.output {
+        Some(path) => Box::new(io::BufWriter::new(
+            fs::File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let sources = || -> Result<Vec<SourcePage<'_>>> {
+        ensure!(
+            images.len() == document.pages.len(),
+            "{} pages but {} images",
+            document.pages.len(),
+            images.len()
+        );
+        Ok(images
+            .iter()
+            .zip(&document.pages)
+            .map(|(image, ocr)| SourcePage { image, ocr })
+            .collect())
+    };
+    match output.format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, document)?;
+            writeln!(out)?;
+        }
+        Format::Text => {
+            let texts: Vec<String> = document.pages.iter().map(Page::text).collect();
+            // Pages are separated by form feeds, as pdftotext does.
+            writeln!(out, "{}", texts.join("\n\x0c"))?;
+        }
+        Format::Hocr => write_hocr(&sources()?, &mut out)?,
+        Format::Alto => write_alto(&sources()?, &mut out)?,
+        Format::Pdf => write_pdf(&sources()?, &PdfOptions::default(), &mut out)?,
+    }
+    out.flush()?;
+    Ok(())
+}
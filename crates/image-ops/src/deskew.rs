@@ -0,0 +1,273 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Skew estimation and correction.
+//!
+//! The skew angle is found with projection profiles: ink pixels are
+//! projected onto the vertical axis at a range of candidate angles, and
+//! the angle at which text rows collapse into the sharpest peaks wins.
+//! The image is then rotated by the opposite angle onto a canvas large
+//! enough to keep every corner.
+//!
+//! Angles are in degrees, positive when text runs clockwise (downhill
+//! to the right), the same sense as `ocr_model::BoundingBox::angle`.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, Pixel, Rgb, Rgba};
+
+use crate::preprocess::ink_test;
+use crate::transform::Affine;
+
+/// Largest skew searched for by default, in degrees.
+pub const MAX_SKEW: f32 = 15.0;
+
+/// Skews smaller than this are left alone, in degrees.
+const MIN_SKEW: f64 = 0.1;
+
+/// Long edge of the copy the angle is estimated on.
+const ESTIMATE_SIZE: u32 = 1024;
+
+/// Upper bound on the ink pixels scored per candidate angle.
+const MAX_SAMPLES: usize = 200_000;
+
+/// A rotation applied to correct skew.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Skew {
+    /// Corrected skew in degrees.
+    pub angle: f64,
+    /// Map from the skewed image onto the corrected one.
+    pub transform: Affine,
+}
+
+/// Estimate the skew of the text in `image`, searching up to
+/// `max_angle` degrees either way. Returns 0 when there is no text to
+/// measure or the skew is negligible.
+pub fn estimate_skew(image: &DynamicImage, max_angle: f32) -> f64 {
+    let gray = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+    let gray = if width.max(height) > ESTIMATE_SIZE {
+        imageops::resize(
+            &gray,
+            (width * ESTIMATE_SIZE / width.max(height)).max(1),
+            (height * ESTIMATE_SIZE / width.max(height)).max(1),
+            FilterType::Triangle,
+        )
+    } else {
+        gray
+    };
+
+    let points = ink_points(&gray);
+    if points.len() < 32 {
+        return 0.0;
+    }
+
+    let max = f64::from(max_angle.abs());
+    let best = |from: f64, to: f64, step: f64| {
+        let steps = ((to - from) / step).round() as i64;
+        (0..=steps)
+            .map(|i| from + i as f64 * step)
+            .map(|angle| (angle, profile_score(&points, angle)))
+            // On a tie the smaller angle wins: small angles move too few
+            // pixels across a row boundary to change the score.
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.abs().total_cmp(&a.0.abs())))
+            .map(|(angle, _)| angle)
+            .unwrap_or(0.0)
+    };
+    let coarse = best(-max, max, 0.5);
+    let fine = best((coarse - 0.5).max(-max), (coarse + 0.5).min(max), 0.05);
+
+    if fine.abs() < MIN_SKEW {
+        0.0
+    } else {
+        fine
+    }
+}
+
+/// Estimate the skew of `image` and rotate it straight.
+///
+/// Returns `None` when the image is already straight.
+pub fn deskew(image: &DynamicImage, max_angle: f32) -> Option<(DynamicImage, Skew)> {
+    let angle = estimate_skew(image, max_angle);
+    if angle == 0.0 {
+        return None;
+    }
+    let (rotated, transform) = rotate(image, -angle);
+    Some((rotated, Skew { angle, transform }))
+}
+
+/// Rotate `image` clockwise by `degrees` about its center onto a canvas
+/// that fits the whole result, returning it with the map onto it.
+/// Uncovered areas are filled with white.
+pub fn rotate(image: &DynamicImage, degrees: f64) -> (DynamicImage, Affine) {
+    let rotated = match image {
+        DynamicImage::ImageLuma8(img) => {
+            DynamicImage::ImageLuma8(rotate_buffer(img, degrees, Luma([255])))
+        }
+        DynamicImage::ImageRgba8(img) => {
+            DynamicImage::ImageRgba8(rotate_buffer(img, degrees, Rgba([255, 255, 255, 255])))
+        }
+        other => DynamicImage::ImageRgb8(rotate_buffer(
+            &other.to_rgb8(),
+            degrees,
+            Rgb([255, 255, 255]),
+        )),
+    };
+    let half = |(w, h): (u32, u32)| (f64::from(w) / 2.0, f64::from(h) / 2.0);
+    let (cx, cy) = half(image.dimensions());
+    let (ox, oy) = half(rotated.dimensions());
+    let transform = Affine::translate(-cx, -cy)
+        .then(Affine::rotate(degrees))
+        .then(Affine::translate(ox, oy));
+    (rotated, transform)
+}
+
+fn rotate_buffer<P>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    degrees: f64,
+    fill: P,
+) -> ImageBuffer<P, Vec<u8>>
+where
+    P: Pixel<Subpixel = u8>,
+{
+    let (width, height) = image.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (w, h) = (f64::from(width), f64::from(height));
+    let out_w = (w * cos.abs() + h * sin.abs()).round().max(1.0) as u32;
+    let out_h = (w * sin.abs() + h * cos.abs()).round().max(1.0) as u32;
+
+    let (cx, cy) = (w / 2.0, h / 2.0);
+    let (ox, oy) = (f64::from(out_w) / 2.0, f64::from(out_h) / 2.0);
+
+    let mut out = ImageBuffer::from_pixel(out_w, out_h, fill);
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        // Inverse map: rotate the output pixel center back by -degrees.
+        let (dx, dy) = (f64::from(x) + 0.5 - ox, f64::from(y) + 0.5 - oy);
+        let sx = dx * cos + dy * sin + cx - 0.5;
+        let sy = -dx * sin + dy * cos + cy - 0.5;
+        if sx < -0.5 || sy < -0.5 || sx > w - 0.5 || sy > h - 0.5 {
+            continue;
+        }
+
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+        let sample = |ix: f64, iy: f64| {
+            let ix = ix.clamp(0.0, w - 1.0) as u32;
+            let iy = iy.clamp(0.0, h - 1.0) as u32;
+            image.get_pixel(ix, iy)
+        };
+        let (p00, p10) = (sample(x0, y0), sample(x0 + 1.0, y0));
+        let (p01, p11) = (sample(x0, y0 + 1.0), sample(x0 + 1.0, y0 + 1.0));
+        for (c, dst) in pixel.channels_mut().iter_mut().enumerate() {
+            let top = f64::from(p00.channels()[c]) * (1.0 - fx) + f64::from(p10.channels()[c]) * fx;
+            let bottom =
+                f64::from(p01.channels()[c]) * (1.0 - fx) + f64::from(p11.channels()[c]) * fx;
+            *dst = (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    out
+}
+
+/// Coordinates of ink pixels relative to the image center.
+fn ink_points(gray: &GrayImage) -> Vec<(f64, f64)> {
+    let is_ink = ink_test(gray);
+    let total = gray.pixels().filter(|p| is_ink(p.0[0])).count();
+    let stride = total.div_ceil(MAX_SAMPLES).max(1);
+    let (cx, cy) = (
+        f64::from(gray.width()) / 2.0,
+        f64::from(gray.height()) / 2.0,
+    );
+
+    gray.enumerate_pixels()
+        .filter(|(_, _, p)| is_ink(p.0[0]))
+        .step_by(stride)
+        .map(|(x, y, _)| (f64::from(x) - cx, f64::from(y) - cy))
+        .collect()
+}
+
+/// How sharply ink rows line up after undoing a skew of `degrees`: the
+/// sum of squared row counts, which peaks when rows are tightest.
+fn profile_score(points: &[(f64, f64)], degrees: f64) -> f64 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let rows: Vec<i64> = points
+        .iter()
+        .map(|&(x, y)| (-x * sin + y * cos).round() as i64)
+        .collect();
+    let (Some(&min), Some(&max)) = (rows.iter().min(), rows.iter().max()) else {
+        return 0.0;
+    };
+    let mut histogram = vec![0u64; (max - min + 1) as usize];
+    for row in rows {
+        histogram[(row - min) as usize] += 1;
+    }
+    histogram.iter().map(|&c| (c * c) as f64).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A page of text-like rows: dark blocks of varying width, 12 px
+    /// tall, with gaps between words and lines.
+    fn page() -> DynamicImage {
+        let image = GrayImage::from_fn(600, 400, |x, y| {
+            let (row, line_y) = (y / 30, y % 30);
+            let word = (x + row * 37) % 70;
+            let ink = (40..360).contains(&y)
+                && (40..560).contains(&x)
+                && line_y < 12
+                && word < 50
+                && (x / 3 + row) % 4 != 0;
+            Luma([if ink { 20 } else { 235 }])
+        });
+        DynamicImage::ImageLuma8(image)
+    }
+
+    #[test]
+    fn straightens_a_rotated_page() {
+        let straight = page();
+        assert_eq!(estimate_skew(&straight, MAX_SKEW), 0.0);
+
+        let (skewed, _) = rotate(&straight, 4.0);
+        let (corrected, skew) = deskew(&skewed, MAX_SKEW).expect("a skew");
+        assert!((skew.angle - 4.0).abs() < 0.15, "estimated {}", skew.angle);
+        assert!(estimate_skew(&corrected, MAX_SKEW).abs() < 0.15);
+
+        // The transform maps the skewed page's center onto the corrected
+        // one's.
+        let center = |image: &DynamicImage| {
+            (
+                f64::from(image.width()) / 2.0,
+                f64::from(image.height()) / 2.0,
+            )
+        };
+        let (x, y) = skew.transform.apply(center(&skewed).0, center(&skewed).1);
+        let (cx, cy) = center(&corrected);
+        assert!((x - cx).abs() < 1e-9 && (y - cy).abs() < 1e-9);
+
+        // Rows of ink are level again: the top row of the text block
+        // starts and ends at the same height.
+        let gray = corrected.into_luma8();
+        let first_ink = |x: u32| (0..gray.height()).find(|&y| gray.get_pixel(x, y).0[0] < 128);
+        let offset = (f64::from(gray.width()) - 600.0) / 2.0;
+        let (left, right) = (offset as u32 + 45, offset as u32 + 545);
+        let (Some(top_left), Some(top_right)) = (first_ink(left), first_ink(right)) else {
+            panic!("no ink found");
+        };
+        assert!(
+            top_left.abs_diff(top_right) <= 2,
+            "{top_left} vs {top_right}"
+        );
+    }
+
+    #[test]
+    fn estimates_skew_either_way() {
+        for angle in [-7.5, -2.0, 1.0, 11.0] {
+            let (skewed, _) = rotate(&page(), angle);
+            let estimate = estimate_skew(&skewed, MAX_SKEW);
+            assert!((estimate - angle).abs() < 0.15, "{angle}: {estimate}");
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Input stage: turn a file into the page images to OCR.
+//!
+//! Plain images are a single page. Multi-page TIFFs are expanded into
+//! one page per directory, and scanned PDFs into one page per PDF page
+//! by pulling out the raster image each page displays. PDFs are not
+//! rendered, so pages made of vector text or drawings have no image to
+//! extract and are reported as errors.
+//!
+//! Pages come out upright: the EXIF orientation of images, the
+//! Orientation tag of TIFF directories and the `/Rotate` entry of PDF
+//! pages are applied on decode.
+//!
+//! Pages are decoded on demand so long documents never need to be held
+//! in memory all at once.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::orientation::{self, Loaded, Orientation};
+
+mod pdf;
+mod tiff;
+
+/// Largest decoded page accepted, in bytes: an A3 page scanned at
+/// 600 dpi in 8-bit RGB with room to spare. Also the `image` crate's
+/// default allocation limit, which bounds embedded JPEGs.
+const MAX_PAGE_BYTES: usize = 512 << 20;
+
+/// Container format of an input file, detected from its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    /// Any single-image format the `image` crate can decode.
+    Image,
+    Tiff,
+    Pdf,
+}
+
+impl SourceFormat {
+    /// Detect the format from the first bytes of a file.
+    pub fn detect(bytes: &[u8]) -> Self {
+        let head = &bytes[..bytes.len().min(1024)];
+        if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+            SourceFormat::Tiff
+        } else if head.windows(5).any(|w| w == b"%PDF-") {
+            // Some producers put junk before the header; readers accept
+            // it anywhere in the first kilobyte.
+            SourceFormat::Pdf
+        } else {
+            SourceFormat::Image
+        }
+    }
+}
+
+/// An opened input file and its pages.
+pub struct Source {
+    format: SourceFormat,
+    pages: Pages,
+}
+
+enum Pages {
+    Image(Vec<u8>),
+    Tiff(tiff::TiffPages),
+    Pdf(Box<pdf::PdfPages>),
+}
+
+impl Source {
+    /// Read and index the file at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Index an in-memory file.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let format = SourceFormat::detect(&bytes);
+        let pages = match format {
+            SourceFormat::Image => Pages::Image(bytes),
+            SourceFormat::Tiff => Pages::Tiff(tiff::TiffPages::new(bytes)?),
+            SourceFormat::Pdf => Pages::Pdf(Box::new(pdf::PdfPages::new(&bytes)?)),
+        };
+        Ok(Self { format, pages })
+    }
+
+    pub fn format(&self) -> SourceFormat {
+        self.format
+    }
+
+    /// Number of pages in the file.
+    pub fn page_count(&self) -> usize {
+        match &self.pages {
+            Pages::Image(_) => 1,
+            Pages::Tiff(pages) => pages.len(),
+            Pages::Pdf(pages) => pages.len(),
+        }
+    }
+
+    /// Orientation of page `index` without decoding it.
+    pub fn orientation(&self, index: usize) -> Result<Orientation> {
+        self.check(index)?;
+        match &self.pages {
+            Pages::Image(bytes) => Ok(Orientation::from_exif(bytes)),
+            Pages::Tiff(pages) => pages.orientation(index),
+            Pages::Pdf(pages) => pages.orientation(index),
+        }
+        .with_context(|| format!("page {}", index + 1))
+    }
+
+    /// Decode page `index` (zero based) and turn it upright.
+    pub fn page(&self, index: usize) -> Result<Loaded> {
+        self.check(index)?;
+        match &self.pages {
+            Pages::Image(bytes) => orientation::load(bytes),
+            Pages::Tiff(pages) => pages.page(index),
+            Pages::Pdf(pages) => {
+                let orientation = pages.orientation(index)?;
+                pages
+                    .page(index)
+                    .map(|image| Loaded::new(image, orientation))
+            }
+        }
+        .with_context(|| format!("page {}", index + 1))
+    }
+
+    /// Decode every page in order.
+    pub fn pages(&self) -> impl Iterator<Item = Result<Loaded>> + '_ {
+        (0..self.page_count()).map(|i| self.page(i))
+    }
+
+    fn check(&self, index: usize) -> Result<()> {
+        let count = self.page_count();
+        if index >= count {
+            bail!("page {} out of range, the file has {count}", index + 1);
+        }
+        Ok(())
+    }
+}
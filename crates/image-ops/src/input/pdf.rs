@@ -0,0 +1,959 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Raster images out of scanned PDFs.
+//!
+//! Scanners and "save as PDF" tools put each page's scan in an image
+//! XObject, so OCR can use that image instead of rendering the page.
+//! When a page draws several images the largest one is taken; small
+//! ones are logos or stamps, not the scan. Images are decoded with the
+//! filters scanned PDFs actually use: Flate, LZW, the ASCII and
+//! run-length encodings, DCT (JPEG) and CCITT fax. JPEG 2000 and JBIG2
+//! are reported as unsupported.
+//!
+//! Pages scanned sideways are usually fixed with the page's `/Rotate`
+//! entry rather than by rotating the image, so that entry is reported
+//! as the page's orientation.
+
+use std::io::Read;
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use super::tiff::cmyk_to_rgb;
+use super::MAX_PAGE_BYTES;
+use crate::orientation::Orientation;
+
+/// How deep to look into form XObjects for the page image.
+const MAX_FORM_DEPTH: usize = 3;
+
+pub(super) struct PdfPages {
+    doc: Document,
+    pages: Vec<ObjectId>,
+}
+
+impl PdfPages {
+    pub fn new(bytes: &[u8]) -> Result<Self> {
+        let doc = Document::load_mem(bytes).context("failed to read PDF")?;
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        if pages.is_empty() {
+            bail!("the PDF has no pages");
+        }
+        Ok(Self { doc, pages })
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn page(&self, index: usize) -> Result<DynamicImage> {
+        let page = self.doc.get_dictionary(self.pages[index])?;
+        let mut images = Vec::new();
+        if let Some(resources) = self.resources(page) {
+            self.collect_images(resources, 0, &mut images);
+        }
+
+        let image = images
+            .into_iter()
+            .max_by_key(|s| {
+                let dim = |key: &[u8]| s.dict.get(key).and_then(Object::as_i64).unwrap_or(0);
+                dim(b"Width") * dim(b"Height")
+            })
+            .ok_or_else(|| {
+                anyhow!("no raster image on this page; only scanned PDFs are supported")
+            })?;
+
+        decode_image(&self.doc, image)
+    }
+
+    /// How page `index` is turned for display, from its `/Rotate`
+    /// entry: a multiple of 90 degrees clockwise.
+    pub fn orientation(&self, index: usize) -> Result<Orientation> {
+        let page = self.doc.get_dictionary(self.pages[index])?;
+        let degrees = self
+            .inherited(page, b"Rotate")
+            .and_then(|o| o.as_i64().ok())
+            .unwrap_or(0);
+        Ok(match degrees.rem_euclid(360) {
+            90 => Orientation::Rotate90,
+            180 => Orientation::Rotate180,
+            270 => Orientation::Rotate270,
+            _ => Orientation::Normal,
+        })
+    }
+
+    /// The page's resource dictionary, which may be inherited from an
+    /// ancestor in the page tree.
+    fn resources<'a>(&'a self, node: &'a Dictionary) -> Option<&'a Dictionary> {
+        self.inherited(node, b"Resources")?.as_dict().ok()
+    }
+
+    /// An inheritable page attribute: the value on the page itself or
+    /// on the nearest ancestor in the page tree that has one.
+    fn inherited<'a>(&'a self, mut node: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+        for _ in 0..64 {
+            if let Ok(value) = node.get(key) {
+                return Some(self.resolve(value));
+            }
+            node = self.resolve(node.get(b"Parent").ok()?).as_dict().ok()?;
+        }
+        None
+    }
+
+    fn collect_images<'a>(
+        &'a self,
+        resources: &'a Dictionary,
+        depth: usize,
+        out: &mut Vec<&'a Stream>,
+    ) {
+        let Some(xobjects) = resources
+            .get(b"XObject")
+            .ok()
+            .and_then(|o| self.resolve(o).as_dict().ok())
+        else {
+            return;
+        };
+
+        for (_, value) in xobjects.iter() {
+            let Ok(stream) = self.resolve(value).as_stream() else {
+                continue;
+            };
+            match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                Ok(b"Image") => out.push(stream),
+                Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+                    if let Some(inner) = stream
+                        .dict
+                        .get(b"Resources")
+                        .ok()
+                        .and_then(|o| self.resolve(o).as_dict().ok())
+                    {
+                        self.collect_images(inner, depth + 1, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn resolve<'a>(&'a self, object: &'a Object) -> &'a Object {
+        resolve(&self.doc, object)
+    }
+}
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).map(|(_, o)| o).unwrap_or(object)
+}
+
+/// Color spaces images are converted from.
+enum Space {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// A single colorant; a tint of 1 is full ink.
+    Separation,
+    Indexed {
+        base: Box<Space>,
+        lookup: Vec<u8>,
+    },
+}
+
+impl Space {
+    fn components(&self) -> usize {
+        match self {
+            Space::Gray | Space::Separation | Space::Indexed { .. } => 1,
+            Space::Rgb => 3,
+            Space::Cmyk => 4,
+        }
+    }
+
+    fn parse(doc: &Document, object: &Object) -> Result<Space> {
+        let object = resolve(doc, object);
+        if let Ok(name) = object.as_name() {
+            return Space::from_name(name);
+        }
+
+        let array = object.as_array().context("invalid color space")?;
+        let family = array
+            .first()
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_name().ok())
+            .context("invalid color space")?;
+        match family {
+            b"ICCBased" => {
+                let stream = array
+                    .get(1)
+                    .map(|o| resolve(doc, o))
+                    .and_then(|o| o.as_stream().ok())
+                    .context("invalid ICC color space")?;
+                match stream.dict.get(b"N").and_then(Object::as_i64) {
+                    Ok(1) => Ok(Space::Gray),
+                    Ok(3) => Ok(Space::Rgb),
+                    Ok(4) => Ok(Space::Cmyk),
+                    _ => match stream.dict.get(b"Alternate") {
+                        Ok(alternate) => Space::parse(doc, alternate),
+                        Err(_) => bail!("unsupported ICC color space"),
+                    },
+                }
+            }
+            b"Indexed" | b"I" => {
+                let base = Space::parse(doc, array.get(1).context("invalid indexed color space")?)?;
+                let lookup = match array.get(3).map(|o| resolve(doc, o)) {
+                    Some(Object::String(bytes, _)) => bytes.clone(),
+                    Some(Object::Stream(stream)) => stream
+                        .decompressed_content()
+                        .unwrap_or_else(|_| stream.content.clone()),
+                    _ => bail!("invalid indexed color space"),
+                };
+                Ok(Space::Indexed {
+                    base: Box::new(base),
+                    lookup,
+                })
+            }
+            b"Separation" => Ok(Space::Separation),
+            b"CalGray" => Ok(Space::Gray),
+            b"CalRGB" => Ok(Space::Rgb),
+            other => bail!("unsupported color space {}", String::from_utf8_lossy(other)),
+        }
+    }
+
+    fn from_name(name: &[u8]) -> Result<Space> {
+        match name {
+            b"DeviceGray" | b"CalGray" | b"G" => Ok(Space::Gray),
+            b"DeviceRGB" | b"CalRGB" | b"RGB" => Ok(Space::Rgb),
+            b"DeviceCMYK" | b"CMYK" => Ok(Space::Cmyk),
+            other => bail!("unsupported color space {}", String::from_utf8_lossy(other)),
+        }
+    }
+}
+
+fn decode_image(doc: &Document, stream: &Stream) -> Result<DynamicImage> {
+    let dict = &stream.dict;
+    let int = |key: &[u8]| {
+        dict.get(key)
+            .map(|o| resolve(doc, o))
+            .and_then(Object::as_i64)
+    };
+    let width = u32::try_from(int(b"Width")?).context("invalid image width")?;
+    let height = u32::try_from(int(b"Height")?).context("invalid image height")?;
+    let is_mask = dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let invert = dict
+        .get(b"Decode")
+        .map(|o| resolve(doc, o))
+        .and_then(Object::as_array)
+        .map(|d| match (d.first(), d.get(1)) {
+            (Some(a), Some(b)) => number(a) > number(b),
+            _ => false,
+        })
+        .unwrap_or(false);
+
+    let filters = names(doc, dict.get(b"Filter").ok());
+    let params = decode_params(doc, dict.get(b"DecodeParms").ok(), filters.len());
+
+    // The size the dictionary declares is checked before anything is
+    // decoded, and decoding stops once it has produced that much. The
+    // JPEG and fax decoders check their own output.
+    let layout = if is_mask {
+        Ok((Space::Gray, 1))
+    } else {
+        match dict.get(b"ColorSpace") {
+            Ok(cs) => Space::parse(doc, cs).and_then(|space| {
+                let bits = match int(b"BitsPerComponent").unwrap_or(8) {
+                    bits @ (1 | 2 | 4 | 8 | 16) => bits as u32,
+                    other => bail!("unsupported bit depth {other}"),
+                };
+                Ok((space, bits))
+            }),
+            Err(_) => Err(anyhow!("image has no color space")),
+        }
+    };
+    let expected = match &layout {
+        Ok((space, bits)) => Some(raw_size(width, height, space.components(), *bits)?),
+        Err(_) => None,
+    };
+
+    let mut data = stream.content.clone();
+    for (i, filter) in filters.iter().enumerate() {
+        let params = params[i];
+        // Room for a PNG predictor's tag byte on every row.
+        let limit = match expected {
+            Some(size) if !filters[i + 1..].iter().any(|f| is_raster(f)) => {
+                size.saturating_add(height as usize)
+            }
+            _ => MAX_PAGE_BYTES,
+        };
+        data = match filter.as_slice() {
+            b"FlateDecode" => predict(inflate(&data, limit)?, params)?,
+            b"LZWDecode" => predict(lzw(&data, params, limit)?, params)?,
+            b"ASCIIHexDecode" => ascii_hex(&data),
+            b"ASCII85Decode" => ascii85(&data)?,
+            b"RunLengthDecode" => run_length(&data, limit),
+            b"DCTDecode" => {
+                let image = image::load_from_memory_with_format(&data, ImageFormat::Jpeg)
+                    .context("failed to decode JPEG image")?;
+                return Ok(match image {
+                    DynamicImage::ImageLuma8(mut gray) if invert => {
+                        image::imageops::invert(&mut gray);
+                        DynamicImage::ImageLuma8(gray)
+                    }
+                    other => other,
+                });
+            }
+            b"CCITTFaxDecode" => {
+                let mut gray = ccitt(&data, params, width, height)?;
+                if invert {
+                    image::imageops::invert(&mut gray);
+                }
+                return Ok(DynamicImage::ImageLuma8(gray));
+            }
+            other => bail!(
+                "{} images are not supported",
+                String::from_utf8_lossy(other)
+            ),
+        };
+    }
+
+    let (space, bits) = layout?;
+    let indexed = matches!(space, Space::Indexed { .. });
+    let mut samples = unpack(&data, width, height, space.components(), bits, !indexed);
+    if invert && !indexed {
+        samples.iter_mut().for_each(|v| *v = 255 - *v);
+    }
+    to_image(width, height, &space, samples)
+}
+
+/// Filters that decode straight to pixels and end a filter chain.
+fn is_raster(filter: &[u8]) -> bool {
+    matches!(filter, b"DCTDecode" | b"CCITTFaxDecode")
+}
+
+/// Bytes of encoded samples in an image of `width` by `height` pixels,
+/// failing when it, the unpacked samples or the converted image would
+/// exceed [`MAX_PAGE_BYTES`].
+fn raw_size(width: u32, height: u32, components: usize, bits: u32) -> Result<usize> {
+    let too_large = || anyhow!("a {width}×{height} image is too large");
+    let width = width as usize;
+    let stride = width
+        .checked_mul(components)
+        .and_then(|samples| samples.checked_mul(bits as usize))
+        .ok_or_else(too_large)?
+        .div_ceil(8);
+    // Unpacked samples and the converted image take at most four bytes
+    // a pixel.
+    let widest = width.checked_mul(4).ok_or_else(too_large)?.max(stride);
+    match widest.checked_mul(height as usize) {
+        Some(size) if size <= MAX_PAGE_BYTES => Ok(stride * height as usize),
+        _ => Err(too_large()),
+    }
+}
+
+/// Unpack rows of `bits`-deep samples into one byte per sample, scaled
+/// to 0..=255 when `scale` is set. Missing data reads as zero.
+///
+/// The caller checks the size with [`raw_size`] first.
+fn unpack(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    components: usize,
+    bits: u32,
+    scale: bool,
+) -> Vec<u8> {
+    let per_row = width as usize * components;
+    let stride = (per_row * bits as usize).div_ceil(8);
+    let mut out = vec![0u8; per_row * height as usize];
+
+    for (y, row) in data.chunks(stride).take(height as usize).enumerate() {
+        let dst = &mut out[y * per_row..(y + 1) * per_row];
+        match bits {
+            8 => dst[..row.len().min(per_row)].copy_from_slice(&row[..row.len().min(per_row)]),
+            // Keep the high byte of 16-bit samples.
+            16 => dst
+                .iter_mut()
+                .zip(row.chunks_exact(2))
+                .for_each(|(d, s)| *d = s[0]),
+            _ => {
+                let max = (1u32 << bits) - 1;
+                for (i, d) in dst.iter_mut().enumerate() {
+                    let bit = i * bits as usize;
+                    let Some(&byte) = row.get(bit / 8) else {
+                        break;
+                    };
+                    let value = (u32::from(byte) >> (8 - bits as usize - bit % 8)) & max;
+                    *d = if scale {
+                        (value * 255 / max) as u8
+                    } else {
+                        value as u8
+                    };
+                }
+            }
+        }
+    }
+    out
+}
+
+fn to_image(width: u32, height: u32, space: &Space, samples: Vec<u8>) -> Result<DynamicImage> {
+    let short = || anyhow!("image data is truncated");
+    match space {
+        Space::Gray => Ok(DynamicImage::ImageLuma8(
+            GrayImage::from_raw(width, height, samples).ok_or_else(short)?,
+        )),
+        Space::Separation => {
+            let gray = samples.into_iter().map(|v| 255 - v).collect();
+            Ok(DynamicImage::ImageLuma8(
+                GrayImage::from_raw(width, height, gray).ok_or_else(short)?,
+            ))
+        }
+        Space::Rgb => Ok(DynamicImage::ImageRgb8(
+            RgbImage::from_raw(width, height, samples).ok_or_else(short)?,
+        )),
+        Space::Cmyk => {
+            let rgb = samples
+                .chunks_exact(4)
+                .flat_map(|p| cmyk_to_rgb(p[0], p[1], p[2], p[3]))
+                .collect();
+            Ok(DynamicImage::ImageRgb8(
+                RgbImage::from_raw(width, height, rgb).ok_or_else(short)?,
+            ))
+        }
+        Space::Indexed { base, lookup } => {
+            if matches!(**base, Space::Indexed { .. }) {
+                bail!("nested indexed color spaces are not allowed");
+            }
+            let n = base.components();
+            let expanded = samples
+                .into_iter()
+                .flat_map(|i| {
+                    let start = usize::from(i) * n;
+                    (0..n).map(move |c| lookup.get(start + c).copied().unwrap_or(0))
+                })
+                .collect();
+            to_image(width, height, base, expanded)
+        }
+    }
+}
+
+/// Filter names, resolving a single name or an array of them.
+fn names(doc: &Document, object: Option<&Object>) -> Vec<Vec<u8>> {
+    match object.map(|o| resolve(doc, o)) {
+        Some(Object::Name(name)) => vec![name.clone()],
+        Some(Object::Array(items)) => items
+            .iter()
+            .filter_map(|o| resolve(doc, o).as_name().ok().map(<[u8]>::to_vec))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// One optional parameter dictionary per filter.
+fn decode_params<'a>(
+    doc: &'a Document,
+    object: Option<&'a Object>,
+    count: usize,
+) -> Vec<Option<&'a Dictionary>> {
+    let mut params = match object.map(|o| resolve(doc, o)) {
+        Some(Object::Dictionary(dict)) => vec![Some(dict)],
+        Some(Object::Array(items)) => items
+            .iter()
+            .map(|o| resolve(doc, o).as_dict().ok())
+            .collect(),
+        _ => Vec::new(),
+    };
+    params.resize(count, None);
+    params
+}
+
+fn param(params: Option<&Dictionary>, key: &[u8], default: i64) -> i64 {
+    params
+        .and_then(|p| p.get(key).and_then(Object::as_i64).ok())
+        .unwrap_or(default)
+}
+
+fn number(object: &Object) -> f32 {
+    object.as_float().unwrap_or(0.0)
+}
+
+/// Inflate a zlib stream, stopping after `limit` bytes. Truncated
+/// streams are common in the wild, so whatever was decoded before an
+/// error is kept.
+fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match ZlibDecoder::new(data)
+        .take(limit as u64)
+        .read_to_end(&mut out)
+    {
+        Ok(_) => Ok(out),
+        Err(_) if !out.is_empty() => Ok(out),
+        Err(e) => Err(e).context("failed to inflate image data"),
+    }
+}
+
+/// Decode LZW data, stopping after `limit` bytes. As with Flate, what
+/// was decoded before an error is kept.
+fn lzw(data: &[u8], params: Option<&Dictionary>, limit: usize) -> Result<Vec<u8>> {
+    use weezl::{decode::Decoder, BitOrder, LzwStatus};
+
+    let mut decoder = if param(params, b"EarlyChange", 1) != 0 {
+        Decoder::with_tiff_size_switch(BitOrder::Msb, 8)
+    } else {
+        Decoder::new(BitOrder::Msb, 8)
+    };
+    let mut out = Vec::new();
+    let mut buffer = [0; 4096];
+    let mut input = data;
+    while out.len() < limit {
+        let result = decoder.decode_bytes(input, &mut buffer);
+        input = &input[result.consumed_in..];
+        out.extend_from_slice(&buffer[..result.consumed_out]);
+        match result.status {
+            Ok(LzwStatus::Ok) => {}
+            Ok(LzwStatus::Done | LzwStatus::NoProgress) => break,
+            Err(_) if !out.is_empty() => break,
+            Err(e) => return Err(e).context("failed to decode LZW data"),
+        }
+    }
+    out.truncate(limit);
+    Ok(out)
+}
+
+/// Undo a TIFF or PNG predictor.
+fn predict(data: Vec<u8>, params: Option<&Dictionary>) -> Result<Vec<u8>> {
+    let predictor = param(params, b"Predictor", 1);
+    if predictor == 1 {
+        return Ok(data);
+    }
+
+    let colors = param(params, b"Colors", 1).max(1) as usize;
+    let bits = param(params, b"BitsPerComponent", 8).max(1) as usize;
+    let columns = param(params, b"Columns", 1).max(1) as usize;
+    let bpp = (colors * bits).div_ceil(8);
+    let row = (colors * bits * columns).div_ceil(8);
+
+    if predictor == 2 {
+        if bits != 8 {
+            bail!("TIFF predictor with {bits}-bit samples is not supported");
+        }
+        let mut data = data;
+        for line in data.chunks_mut(row) {
+            for i in bpp..line.len() {
+                line[i] = line[i].wrapping_add(line[i - bpp]);
+            }
+        }
+        return Ok(data);
+    }
+
+    // PNG predictors: every row starts with its filter type.
+    let mut out = Vec::with_capacity(data.len() / (row + 1) * row);
+    let mut prior = vec![0u8; row];
+    for chunk in data.chunks(row + 1) {
+        let Some((&kind, input)) = chunk.split_first() else {
+            break;
+        };
+        let mut line = vec![0u8; row];
+        line[..input.len()].copy_from_slice(input);
+        for i in 0..row {
+            let left = if i >= bpp { line[i - bpp] } else { 0 };
+            let up = prior[i];
+            let up_left = if i >= bpp { prior[i - bpp] } else { 0 };
+            line[i] = line[i].wrapping_add(match kind {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                other => bail!("invalid PNG predictor {other}"),
+            });
+        }
+        out.extend_from_slice(&line);
+        prior = line;
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn ascii_hex(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .take_while(|&&c| c != b'>')
+        .filter_map(|&c| (c as char).to_digit(16).map(|d| d as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn ascii85(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 4 / 5);
+    let mut group = [0u8; 5];
+    let mut len = 0;
+    for &c in data {
+        match c {
+            b'~' => break,
+            b'z' if len == 0 => out.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group[len] = c - b'!';
+                len += 1;
+                if len == 5 {
+                    out.extend_from_slice(&ascii85_group(&group));
+                    len = 0;
+                }
+            }
+            c if c.is_ascii_whitespace() => {}
+            other => bail!("invalid ASCII85 character {:?}", other as char),
+        }
+    }
+    if len > 1 {
+        group[len..].fill(b'u' - b'!');
+        out.extend_from_slice(&ascii85_group(&group)[..len - 1]);
+    }
+    Ok(out)
+}
+
+fn ascii85_group(group: &[u8; 5]) -> [u8; 4] {
+    let value = group.iter().fold(0u32, |acc, &d| {
+        acc.wrapping_mul(85).wrapping_add(u32::from(d))
+    });
+    value.to_be_bytes()
+}
+
+/// Decode run-length data, stopping after `limit` bytes.
+fn run_length(data: &[u8], limit: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while out.len() < limit {
+        let Some(&n) = data.get(i) else {
+            break;
+        };
+        match n {
+            128 => break,
+            0..=127 => {
+                let end = (i + 2 + usize::from(n)).min(data.len());
+                out.extend_from_slice(&data[i + 1..end]);
+                i = end;
+            }
+            _ => {
+                if let Some(&byte) = data.get(i + 1) {
+                    out.extend(std::iter::repeat_n(byte, 257 - usize::from(n)));
+                }
+                i += 2;
+            }
+        }
+    }
+    out.truncate(limit);
+    out
+}
+
+/// Decode CCITT Group 3 or 4 fax data to an 8-bit image with the
+/// filter's own sample values: 0 for black unless `BlackIs1` is set.
+fn ccitt(data: &[u8], params: Option<&Dictionary>, width: u32, height: u32) -> Result<GrayImage> {
+    let k = param(params, b"K", 0);
+    let columns = param(params, b"Columns", 1728);
+    let rows = match param(params, b"Rows", 0) {
+        0 => i64::from(height),
+        rows => rows,
+    };
+    let black_is_1 = params
+        .and_then(|p| p.get(b"BlackIs1").and_then(Object::as_bool).ok())
+        .unwrap_or(false);
+    let columns = u16::try_from(columns).context("fax image is too wide")?;
+    let rows = u16::try_from(rows).context("fax image is too tall")?;
+    if usize::from(columns) * usize::from(rows) > MAX_PAGE_BYTES {
+        bail!("a {columns}×{rows} fax image is too large");
+    }
+
+    let (black, white) = if black_is_1 { (255, 0) } else { (0, 255) };
+    let mut pixels = Vec::with_capacity(usize::from(columns) * usize::from(rows));
+    let mut push_line = |transitions: &[u16]| {
+        if pixels.len() < usize::from(columns) * usize::from(rows) {
+            pixels.extend(fax::decoder::pels(transitions, columns).map(|c| match c {
+                fax::Color::Black => black,
+                fax::Color::White => white,
+            }));
+        }
+    };
+
+    let input = data.iter().copied();
+    let decoded = if k < 0 {
+        fax::decoder::decode_g4(input, columns, Some(rows), &mut push_line)
+    } else {
+        fax::decoder::decode_g3(input, &mut push_line)
+    };
+    if decoded.is_none() && pixels.is_empty() {
+        bail!("failed to decode fax image");
+    }
+
+    // Pad or crop to the declared size.
+    pixels.resize(usize::from(columns) * usize::from(rows), white);
+    let image = GrayImage::from_raw(u32::from(columns), u32::from(rows), pixels)
+        .context("invalid fax image size")?;
+    Ok(
+        if (u32::from(columns), u32::from(rows)) == (width, height) {
+            image
+        } else {
+            image::imageops::crop_imm(&image, 0, 0, width, height).to_image()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use fax::encoder::Encoder;
+    use fax::{Color, VecWriter};
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use lopdf::StringFormat;
+
+    use super::*;
+
+    fn params(entries: &[(&str, i64)]) -> Dictionary {
+        let mut dict = Dictionary::new();
+        for &(key, value) in entries {
+            dict.set(key, value);
+        }
+        dict
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn decodes_ascii_hex() {
+        assert_eq!(ascii_hex(b"48 65\n6c6C 6f>ff"), b"Hello");
+        // A missing last digit is zero.
+        assert_eq!(ascii_hex(b"7>"), [0x70]);
+    }
+
+    #[test]
+    fn decodes_ascii85() {
+        assert_eq!(ascii85(b"87cURD]i,\"Ebo7~>").unwrap(), b"Hello World");
+        // A short last group, and `z` for four zero bytes.
+        assert_eq!(ascii85(b"87cT~>").unwrap(), b"Hel");
+        assert_eq!(ascii85(b"z@:B~>").unwrap(), b"\0\0\0\0ab");
+        // Whitespace is skipped and nothing after `~>` is read.
+        assert_eq!(ascii85(b" 87c\nT ~>87cT").unwrap(), b"Hel");
+        assert!(ascii85(b"87{T~>").is_err());
+    }
+
+    #[test]
+    fn decodes_run_length() {
+        // Three literal bytes, `x` four times, then end of data.
+        let data = [2, b'a', b'b', b'c', 253, b'x', 128, b'z'];
+        assert_eq!(run_length(&data, usize::MAX), b"abcxxxx");
+        assert_eq!(run_length(&data, 5), b"abcxx");
+    }
+
+    #[test]
+    fn decodes_lzw() {
+        // The example from the PDF specification, section 7.4.4.2.
+        let data = [0x80, 0x0b, 0x60, 0x50, 0x22, 0x0c, 0x0c, 0x85, 0x01];
+        assert_eq!(lzw(&data, None, usize::MAX).unwrap(), b"-----A---B");
+        assert_eq!(lzw(&data, None, 4).unwrap(), b"----");
+    }
+
+    #[test]
+    fn inflates_within_the_limit() {
+        let data = deflate(&[7; 1000]);
+        assert_eq!(inflate(&data, usize::MAX).unwrap(), [7; 1000]);
+        assert_eq!(inflate(&data, 10).unwrap(), [7; 10]);
+        // A truncated stream keeps what decoded.
+        let truncated = inflate(&deflate(b"partial data")[..8], usize::MAX).unwrap();
+        assert!(b"partial data".starts_with(&truncated));
+        assert!(inflate(b"not zlib", usize::MAX).is_err());
+    }
+
+    #[test]
+    fn undoes_png_predictors() {
+        let params = params(&[("Predictor", 15), ("Columns", 3)]);
+        // Each row is tagged with its filter type, 0 to 4.
+        let encoded = [
+            [0, 10, 20, 30],
+            [1, 15, 10, 10],
+            [2, 1, 2, 254],
+            [3, 12, 7, 9],
+            [4, 5, 254, 10],
+        ]
+        .concat();
+        assert_eq!(
+            predict(encoded, Some(&params)).unwrap(),
+            [10, 20, 30, 15, 25, 35, 16, 27, 33, 20, 30, 40, 25, 28, 50]
+        );
+        assert!(predict(vec![5, 0, 0, 0], Some(&params)).is_err());
+    }
+
+    #[test]
+    fn undoes_the_tiff_predictor() {
+        let params = params(&[("Predictor", 2), ("Colors", 2), ("Columns", 2)]);
+        assert_eq!(
+            predict(vec![10, 100, 5, 1, 3, 3, 1, 1], Some(&params)).unwrap(),
+            [10, 100, 15, 101, 3, 3, 4, 4]
+        );
+    }
+
+    #[test]
+    fn picks_the_paeth_predictor() {
+        assert_eq!(paeth(10, 20, 30), 10);
+        assert_eq!(paeth(10, 20, 5), 20);
+        assert_eq!(paeth(10, 20, 15), 15);
+    }
+
+    #[test]
+    fn unpacks_samples() {
+        assert_eq!(unpack(&[0b1011_0000], 4, 1, 1, 1, true), [255, 0, 255, 255]);
+        assert_eq!(unpack(&[0b0001_1011], 4, 1, 1, 2, true), [0, 85, 170, 255]);
+        assert_eq!(unpack(&[0x1f, 0x20], 3, 1, 1, 4, false), [1, 15, 2]);
+        assert_eq!(
+            unpack(&[0xab, 0xcd, 0x12, 0x34], 2, 1, 1, 16, true),
+            [0xab, 0x12]
+        );
+        // Rows start on byte boundaries; missing data reads as zero.
+        assert_eq!(
+            unpack(&[0b1000_0000, 0b0100_0000], 3, 3, 1, 1, true),
+            [255, 0, 0, 0, 255, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn decodes_group_4_fax() {
+        let (width, height) = (16u16, 4u16);
+        let ink = |x: u16, y: u16| x == y * 2;
+        let mut encoder = Encoder::new(VecWriter::new());
+        for y in 0..height {
+            let line = (0..width).map(|x| {
+                if ink(x, y) {
+                    Color::Black
+                } else {
+                    Color::White
+                }
+            });
+            encoder.encode_line(line, width).unwrap();
+        }
+        let data = encoder.finish().unwrap().finish();
+
+        let params = params(&[("K", -1), ("Columns", 16), ("Rows", 4)]);
+        let image = ccitt(&data, Some(&params), 16, 4).unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if ink(x as u16, y as u16) { 0 } else { 255 };
+            assert_eq!(pixel.0[0], expected, "pixel ({x}, {y})");
+        }
+
+        let mut inverted = params.clone();
+        inverted.set("BlackIs1", true);
+        let image = ccitt(&data, Some(&inverted), 16, 4).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0]);
+    }
+
+    /// An image XObject with `dict` entries over `data`.
+    fn stream(entries: Vec<(&str, Object)>, data: Vec<u8>) -> Stream {
+        let mut dict = Dictionary::new();
+        for (key, value) in entries {
+            dict.set(key, value);
+        }
+        Stream::new(dict, data)
+    }
+
+    #[test]
+    fn looks_up_indexed_colors() {
+        let palette = vec![255, 0, 0, 0, 0, 255];
+        let space = Object::Array(vec![
+            Object::Name(b"Indexed".to_vec()),
+            Object::Name(b"DeviceRGB".to_vec()),
+            Object::Integer(1),
+            Object::String(palette, StringFormat::Hexadecimal),
+        ]);
+        let image = stream(
+            vec![
+                ("Width", 3.into()),
+                ("Height", 1.into()),
+                ("ColorSpace", space),
+                ("BitsPerComponent", 1.into()),
+            ],
+            vec![0b0100_0000],
+        );
+        let image = decode_image(&Document::new(), &image).unwrap().into_rgb8();
+        let pixels: Vec<[u8; 3]> = image.pixels().map(|p| p.0).collect();
+        assert_eq!(pixels, [[255, 0, 0], [0, 0, 255], [255, 0, 0]]);
+    }
+
+    #[test]
+    fn reads_separation_tints_as_ink() {
+        let space = Object::Array(vec![
+            Object::Name(b"Separation".to_vec()),
+            Object::Name(b"Black".to_vec()),
+            Object::Name(b"DeviceGray".to_vec()),
+        ]);
+        let image = stream(
+            vec![
+                ("Width", 3.into()),
+                ("Height", 1.into()),
+                ("ColorSpace", space),
+                ("Filter", Object::Name(b"ASCIIHexDecode".to_vec())),
+            ],
+            b"00 80 ff>".to_vec(),
+        );
+        let image = decode_image(&Document::new(), &image).unwrap().into_luma8();
+        assert_eq!(image.into_raw(), [255, 127, 0]);
+    }
+
+    #[test]
+    fn rejects_oversized_images_before_decoding() {
+        for (width, height) in [
+            (100_000, 100_000),
+            (i64::from(u32::MAX), i64::from(u32::MAX)),
+        ] {
+            let image = stream(
+                vec![
+                    ("Width", width.into()),
+                    ("Height", height.into()),
+                    ("ColorSpace", Object::Name(b"DeviceCMYK".to_vec())),
+                    ("BitsPerComponent", 16.into()),
+                    ("Filter", Object::Name(b"FlateDecode".to_vec())),
+                ],
+                deflate(&[0; 64]),
+            );
+            let error = decode_image(&Document::new(), &image).unwrap_err();
+            assert!(error.to_string().contains("too large"), "{error}");
+        }
+    }
+
+    #[test]
+    fn stops_inflating_at_the_declared_size() {
+        // A bomb: far more data than the 2×2 gray image needs.
+        let image = stream(
+            vec![
+                ("Width", 2.into()),
+                ("Height", 2.into()),
+                ("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+                ("Filter", Object::Name(b"FlateDecode".to_vec())),
+            ],
+            deflate(&vec![9; 8 << 20]),
+        );
+        let image = decode_image(&Document::new(), &image).unwrap().into_luma8();
+        assert_eq!(image.into_raw(), [9; 4]);
+    }
+}
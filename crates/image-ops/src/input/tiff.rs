@@ -0,0 +1,298 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Multi-page TIFF input.
+//!
+//! The `image` crate only ever decodes the first directory of a TIFF,
+//! so pages are read with the `tiff` decoder directly and converted to
+//! 8-bit images here. Each directory carries its own Orientation tag.
+//!
+//! The `tiff` decoder has no CCITT codecs, so bilevel fax pages, the
+//! usual output of document scanners, are decoded with the `fax` crate
+//! straight from their strips.
+
+use std::io::Cursor;
+
+use ::tiff::decoder::{Decoder, DecodingResult, Limits};
+use ::tiff::tags::{CompressionMethod, PhotometricInterpretation, Tag};
+use ::tiff::ColorType;
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+
+use super::MAX_PAGE_BYTES;
+use crate::orientation::{Loaded, Orientation};
+
+/// T4Options, which the `tiff` crate has no name for.
+const T4_OPTIONS: Tag = Tag::Unknown(292);
+
+pub(super) struct TiffPages {
+    bytes: Vec<u8>,
+    count: usize,
+}
+
+impl TiffPages {
+    pub fn new(bytes: Vec<u8>) -> Result<Self> {
+        let mut decoder = Decoder::new(Cursor::new(&bytes)).context("failed to read TIFF")?;
+        let mut count = 1;
+        while decoder.more_images() {
+            decoder
+                .next_image()
+                .with_context(|| format!("failed to read TIFF directory {}", count + 1))?;
+            count += 1;
+        }
+        Ok(Self { bytes, count })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn orientation(&self, index: usize) -> Result<Orientation> {
+        let mut decoder = self.decoder(index)?;
+        read_orientation(&mut decoder)
+    }
+
+    pub fn page(&self, index: usize) -> Result<Loaded> {
+        let mut decoder = self.decoder(index)?;
+        let orientation = read_orientation(&mut decoder)?;
+
+        let (width, height) = decoder.dimensions()?;
+        let compression = decoder.find_tag_unsigned::<u16>(Tag::Compression)?.map_or(
+            CompressionMethod::None,
+            CompressionMethod::from_u16_exhaustive,
+        );
+        if matches!(
+            compression,
+            CompressionMethod::Fax3 | CompressionMethod::Fax4
+        ) {
+            let image = self.read_fax(&mut decoder, compression, width, height)?;
+            return Ok(Loaded::new(DynamicImage::ImageLuma8(image), orientation));
+        }
+
+        let color = decoder.colortype()?;
+        let samples = match decoder.read_image()? {
+            DecodingResult::U8(data) => data,
+            // Keep the high byte of 16-bit samples.
+            DecodingResult::U16(data) => data.into_iter().map(|v| (v >> 8) as u8).collect(),
+            _ => bail!("unsupported TIFF sample format"),
+        };
+
+        let image = to_image(width, height, color, samples)?;
+        Ok(Loaded::new(image, orientation))
+    }
+
+    fn decoder(&self, index: usize) -> Result<Decoder<Cursor<&[u8]>>> {
+        let mut decoder = Decoder::new(Cursor::new(&self.bytes[..]))
+            .context("failed to read TIFF")?
+            .with_limits(limits());
+        decoder.seek_to_image(index)?;
+        Ok(decoder)
+    }
+
+    /// Decode a CCITT Group 3 (one-dimensional) or Group 4 page strip by
+    /// strip.
+    fn read_fax(
+        &self,
+        decoder: &mut Decoder<Cursor<&[u8]>>,
+        compression: CompressionMethod,
+        width: u32,
+        height: u32,
+    ) -> Result<GrayImage> {
+        if decoder.find_tag(Tag::TileOffsets)?.is_some() {
+            bail!("tiled fax TIFFs are not supported");
+        }
+        if compression == CompressionMethod::Fax3
+            && decoder.find_tag_unsigned::<u32>(T4_OPTIONS)?.unwrap_or(0) & 1 != 0
+        {
+            bail!("two-dimensional Group 3 fax TIFFs are not supported");
+        }
+        let columns = u16::try_from(width).context("fax image is too wide")?;
+        let pixels = width as usize * height as usize;
+        if pixels > MAX_PAGE_BYTES {
+            bail!("TIFF page is too large");
+        }
+
+        let offsets = decoder.get_tag_u64_vec(Tag::StripOffsets)?;
+        let counts = decoder.get_tag_u64_vec(Tag::StripByteCounts)?;
+        let rows_per_strip = decoder
+            .find_tag_unsigned::<u32>(Tag::RowsPerStrip)?
+            .unwrap_or(height)
+            .clamp(1, height.max(1));
+        let reversed = decoder.find_tag_unsigned::<u16>(Tag::FillOrder)? == Some(2);
+        // Fax codes name colors, not sample values; a BlackIsZero page
+        // stores them the other way round.
+        let black_is_zero = decoder
+            .find_tag_unsigned::<u16>(Tag::PhotometricInterpretation)?
+            .and_then(PhotometricInterpretation::from_u16)
+            == Some(PhotometricInterpretation::BlackIsZero);
+        let (black, white) = if black_is_zero { (255, 0) } else { (0, 255) };
+
+        let mut out = Vec::with_capacity(pixels);
+        for (strip, (&offset, &count)) in offsets.iter().zip(&counts).enumerate() {
+            let first_row = strip as u64 * u64::from(rows_per_strip);
+            if first_row >= u64::from(height) {
+                break;
+            }
+            let rows = (u64::from(height) - first_row).min(u64::from(rows_per_strip)) as u16;
+            let data = usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(count).ok())
+                .and_then(|(start, len)| self.bytes.get(start..start.checked_add(len)?))
+                .with_context(|| format!("TIFF strip {} is out of bounds", strip + 1))?;
+            let input = data
+                .iter()
+                .map(move |&b| if reversed { b.reverse_bits() } else { b });
+
+            let start = out.len();
+            let end = start + usize::from(rows) * usize::from(columns);
+            let mut push_line = |transitions: &[u16]| {
+                if out.len() < end {
+                    out.extend(fax::decoder::pels(transitions, columns).map(|c| match c {
+                        fax::Color::Black => black,
+                        fax::Color::White => white,
+                    }));
+                }
+            };
+            let decoded = if compression == CompressionMethod::Fax4 {
+                fax::decoder::decode_g4(input, columns, Some(rows), &mut push_line)
+            } else {
+                fax::decoder::decode_g3(input, &mut push_line)
+            };
+            if decoded.is_none() && out.len() == start {
+                bail!("failed to decode fax strip {}", strip + 1);
+            }
+            out.resize(end, white);
+        }
+
+        out.resize(pixels, white);
+        GrayImage::from_raw(width, height, out).context("invalid fax image size")
+    }
+}
+
+/// Limits for decoding one page, which is read in one piece.
+fn limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.decoding_buffer_size = MAX_PAGE_BYTES;
+    limits
+}
+
+fn read_orientation(decoder: &mut Decoder<Cursor<&[u8]>>) -> Result<Orientation> {
+    let value = decoder.find_tag_unsigned::<u32>(Tag::Orientation)?;
+    Ok(value.map_or(Orientation::Normal, Orientation::from_tag))
+}
+
+fn to_image(width: u32, height: u32, color: ColorType, samples: Vec<u8>) -> Result<DynamicImage> {
+    let short = || anyhow::anyhow!("TIFF image data is truncated");
+    let image = match color {
+        ColorType::Gray(8 | 16) => {
+            DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, samples).ok_or_else(short)?)
+        }
+        ColorType::Gray(bits @ (1 | 2 | 4)) => {
+            let gray = unpack_gray(&samples, width, height, bits);
+            DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, gray).ok_or_else(short)?)
+        }
+        ColorType::GrayA(8 | 16) => DynamicImage::ImageLumaA8(
+            GrayAlphaImage::from_raw(width, height, samples).ok_or_else(short)?,
+        ),
+        ColorType::RGB(8 | 16) => {
+            DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, samples).ok_or_else(short)?)
+        }
+        ColorType::RGBA(8 | 16) => {
+            DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, samples).ok_or_else(short)?)
+        }
+        ColorType::CMYK(8) => {
+            let rgb = samples
+                .chunks_exact(4)
+                .flat_map(|p| cmyk_to_rgb(p[0], p[1], p[2], p[3]))
+                .collect();
+            DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, rgb).ok_or_else(short)?)
+        }
+        other => bail!("unsupported TIFF color type {other:?}"),
+    };
+    Ok(image)
+}
+
+/// Expand packed 1, 2 or 4-bit gray samples to 8 bits. Rows start on a
+/// byte boundary.
+fn unpack_gray(data: &[u8], width: u32, height: u32, bits: u8) -> Vec<u8> {
+    let (width, height, bits) = (width as usize, height as usize, bits as usize);
+    let stride = (width * bits).div_ceil(8);
+    let max = (1u16 << bits) - 1;
+    let mut out = Vec::with_capacity(width * height);
+    for row in data.chunks(stride).take(height) {
+        for x in 0..width {
+            let bit = x * bits;
+            let byte = row.get(bit / 8).copied().unwrap_or(0);
+            let value = (byte >> (8 - bits - bit % 8)) as u16 & max;
+            out.push((value * 255 / max) as u8);
+        }
+    }
+    out
+}
+
+pub(super) fn cmyk_to_rgb(c: u8, m: u8, y: u8, k: u8) -> [u8; 3] {
+    let channel = |v: u8| ((255 - u16::from(v)) * (255 - u16::from(k)) / 255) as u8;
+    [channel(c), channel(m), channel(y)]
+}
+
+#[cfg(test)]
+mod tests {
+    use fax::encoder::Encoder;
+    use fax::{Color, VecWriter};
+
+    use super::*;
+
+    /// A one-strip little-endian TIFF holding `data` with the given
+    /// compression, for a `width` × `height` bilevel page.
+    fn tiff(width: u16, height: u16, compression: u16, data: &[u8]) -> Vec<u8> {
+        let entries: [(u16, u16, u32); 8] = [
+            (256, 3, width.into()),
+            (257, 3, height.into()),
+            (258, 3, 1),
+            (259, 3, compression.into()),
+            (262, 3, 0),
+            (273, 4, 8 + 2 + 8 * 12 + 4),
+            (278, 3, height.into()),
+            (279, 4, data.len() as u32),
+        ];
+        let mut out = b"II*\0".to_vec();
+        out.extend(8u32.to_le_bytes());
+        out.extend((entries.len() as u16).to_le_bytes());
+        for (tag, kind, value) in entries {
+            out.extend(tag.to_le_bytes());
+            out.extend(kind.to_le_bytes());
+            out.extend(1u32.to_le_bytes());
+            out.extend(value.to_le_bytes());
+        }
+        out.extend(0u32.to_le_bytes());
+        out.extend(data);
+        out
+    }
+
+    #[test]
+    fn decodes_group_4_pages() {
+        let (width, height) = (40u16, 6u16);
+        // A black bar in columns 10..20 on every other row.
+        let ink = |x: u16, y: u16| y.is_multiple_of(2) && (10..20).contains(&x);
+        let mut encoder = Encoder::new(VecWriter::new());
+        for y in 0..height {
+            let line = (0..width).map(|x| {
+                if ink(x, y) {
+                    Color::Black
+                } else {
+                    Color::White
+                }
+            });
+            encoder.encode_line(line, width).unwrap();
+        }
+        let data = encoder.finish().unwrap().finish();
+
+        let pages = TiffPages::new(tiff(width, height, 4, &data)).unwrap();
+        let page = pages.page(0).unwrap().image.into_luma8();
+        assert_eq!(page.dimensions(), (width.into(), height.into()));
+        for (x, y, pixel) in page.enumerate_pixels() {
+            let expected = if ink(x as u16, y as u16) { 0 } else { 255 };
+            assert_eq!(pixel.0[0], expected, "pixel ({x}, {y})");
+        }
+    }
+}
@@ -0,0 +1,22 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Image handling around OCR: reading input files into pages, cleaning
+//! them up for the engine and preparing images for upload.
+
+pub mod deskew;
+pub mod input;
+pub mod optimize;
+pub mod orientation;
+pub mod preprocess;
+pub mod tiles;
+pub mod transform;
+pub mod upscale;
+
+pub use deskew::Skew;
+pub use input::{Source, SourceFormat};
+pub use optimize::{optimize_for_ai, OptimizeOptions, Optimized, OutputFormat, SizeLimit};
+pub use orientation::{Loaded, Orientation};
+pub use preprocess::{Pipeline, Preset, Processed, Step};
+pub use tiles::Tile;
+pub use transform::Affine;
@@ -0,0 +1,303 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Preparing images for upload to vision models.
+//!
+//! Each model has its own limits on image size and request payload, so
+//! the size cap, output format and byte budget are all options. When a
+//! budget is set, lossy formats first trade quality for size, and the
+//! image is only shrunk further once the lowest acceptable quality is
+//! still too large.
+
+use std::io::Cursor;
+
+use anyhow::{anyhow, bail, Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{ColorType, DynamicImage, ImageEncoder};
+use serde::Deserialize;
+
+use crate::orientation::{self, Loaded, Orientation};
+use crate::transform::Affine;
+
+/// Lowest quality tried when searching for a byte budget.
+const MIN_QUALITY: u8 = 40;
+
+/// Give up shrinking for a byte budget after this many rescales.
+const MAX_RESCALES: usize = 8;
+
+/// Longest side libwebp can encode.
+const WEBP_MAX_EDGE: u32 = 16383;
+
+/// Encoding of the optimized image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Jpeg,
+    /// Lossless; byte budgets are met by scaling alone.
+    Png,
+    WebP,
+}
+
+impl OutputFormat {
+    fn is_lossy(self) -> bool {
+        self != Self::Png
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::WebP => "image/webp",
+        }
+    }
+}
+
+/// Upper bound on the size of the optimized image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeLimit {
+    /// Longest side in pixels.
+    LongEdge(u32),
+    /// Width times height.
+    Pixels(u64),
+}
+
+/// Options for [`optimize_for_ai`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct OptimizeOptions {
+    /// Shrink images larger than this. Images are never enlarged.
+    pub max_size: Option<SizeLimit>,
+    pub format: OutputFormat,
+    /// Quality for lossy formats, 1 to 100. With a byte budget this is
+    /// the highest quality tried.
+    pub quality: u8,
+    /// Largest acceptable encoded size in bytes.
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self {
+            max_size: Some(SizeLimit::LongEdge(1024)),
+            format: OutputFormat::Jpeg,
+            quality: 80,
+            max_bytes: None,
+        }
+    }
+}
+
+/// An image re-encoded for upload.
+#[derive(Debug, Clone)]
+pub struct Optimized {
+    pub bytes: Vec<u8>,
+    pub format: OutputFormat,
+    /// Size of the encoded image.
+    pub width: u32,
+    pub height: u32,
+    /// Encoded size over upright input size, at most 1.
+    pub scale: f64,
+    /// Quality the image was encoded at; `None` for lossless formats.
+    pub quality: Option<u8>,
+    /// Transform applied from the EXIF orientation before the metadata
+    /// was dropped by re-encoding.
+    pub orientation: Orientation,
+    /// Map from the stored pixels of the input to the encoded image.
+    pub transform: Affine,
+}
+
+/// Decode an image, turn it upright and re-encode it within the limits
+/// in `options`.
+pub fn optimize_for_ai(image_data: &[u8], options: &OptimizeOptions) -> Result<Optimized> {
+    let Loaded {
+        image,
+        orientation,
+        transform,
+    } = orientation::load(image_data)?;
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 {
+        bail!("image is empty");
+    }
+
+    let quality = options.quality.clamp(1, 100);
+    let mut scale = options
+        .max_size
+        .map_or(1.0, |limit| fit_scale(width, height, limit));
+    if options.format == OutputFormat::WebP {
+        scale = scale.min(fit_scale(width, height, SizeLimit::LongEdge(WEBP_MAX_EDGE)));
+    }
+    for _ in 0..=MAX_RESCALES {
+        let (resized, resize_transform) = resize(&image, scale);
+        let (encoded, used) = encode_within(&resized, options.format, quality, options.max_bytes)?;
+        let Some(max_bytes) = options.max_bytes.filter(|&max| encoded.len() > max) else {
+            return Ok(Optimized {
+                bytes: encoded,
+                format: options.format,
+                width: resized.width(),
+                height: resized.height(),
+                scale: f64::from(resized.width()) / f64::from(width),
+                quality: options.format.is_lossy().then_some(used),
+                orientation,
+                transform: transform.then(resize_transform),
+            });
+        };
+
+        // Encoded size grows roughly with pixel count; aim a little
+        // under the budget so the next attempt usually fits.
+        let ratio = max_bytes as f64 / encoded.len() as f64;
+        scale *= (ratio.sqrt() * 0.9).min(0.9);
+        if (f64::from(width.max(height)) * scale) < 1.0 {
+            break;
+        }
+    }
+    bail!(
+        "could not fit the image in {} bytes",
+        options.max_bytes.unwrap_or_default()
+    )
+}
+
+/// Scale that brings `width` × `height` within `limit`, at most 1.
+fn fit_scale(width: u32, height: u32, limit: SizeLimit) -> f64 {
+    let scale = match limit {
+        SizeLimit::LongEdge(edge) => f64::from(edge) / f64::from(width.max(height)),
+        SizeLimit::Pixels(pixels) => {
+            (pixels as f64 / (f64::from(width) * f64::from(height))).sqrt()
+        }
+    };
+    scale.min(1.0)
+}
+
+fn resize(image: &DynamicImage, scale: f64) -> (DynamicImage, Affine) {
+    if scale >= 1.0 {
+        return (image.clone(), Affine::IDENTITY);
+    }
+    let from = (image.width(), image.height());
+    let to = (
+        (f64::from(from.0) * scale).round().max(1.0) as u32,
+        (f64::from(from.1) * scale).round().max(1.0) as u32,
+    );
+    let resized = image.resize_exact(to.0, to.1, FilterType::Lanczos3);
+    (resized, Affine::resize(from, to))
+}
+
+/// Encode at `quality`, or for lossy formats with a budget, at the
+/// highest quality down to [`MIN_QUALITY`] that fits. Returns the
+/// smallest attempt when nothing fits, together with its quality.
+fn encode_within(
+    image: &DynamicImage,
+    format: OutputFormat,
+    quality: u8,
+    max_bytes: Option<usize>,
+) -> Result<(Vec<u8>, u8)> {
+    let best = encode(image, format, quality)?;
+    let Some(max_bytes) = max_bytes else {
+        return Ok((best, quality));
+    };
+    if best.len() <= max_bytes || !format.is_lossy() || quality <= MIN_QUALITY {
+        return Ok((best, quality));
+    }
+
+    let floor = encode(image, format, MIN_QUALITY)?;
+    if floor.len() > max_bytes {
+        return Ok((floor, MIN_QUALITY));
+    }
+
+    // Binary search for the highest quality that fits; `low` always
+    // fits and `high` never does.
+    let (mut low, mut high) = (MIN_QUALITY, quality);
+    let mut fitting = floor;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        let encoded = encode(image, format, mid)?;
+        if encoded.len() <= max_bytes {
+            low = mid;
+            fitting = encoded;
+        } else {
+            high = mid;
+        }
+    }
+    Ok((fitting, low))
+}
+
+fn encode(image: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>> {
+    let (width, height) = (image.width(), image.height());
+    let mut out = Vec::new();
+    match format {
+        OutputFormat::Jpeg => {
+            let encoder = JpegEncoder::new_with_quality(Cursor::new(&mut out), quality);
+            if image.color().has_color() {
+                encoder.write_image(&image.to_rgb8(), width, height, ColorType::Rgb8)
+            } else {
+                encoder.write_image(&image.to_luma8(), width, height, ColorType::L8)
+            }
+            .context("failed to encode JPEG")?;
+        }
+        OutputFormat::Png => {
+            let encoder = PngEncoder::new_with_quality(
+                Cursor::new(&mut out),
+                CompressionType::Best,
+                PngFilter::Adaptive,
+            );
+            let image = match image.color() {
+                ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => {
+                    image.clone()
+                }
+                _ if image.color().has_alpha() => DynamicImage::ImageRgba8(image.to_rgba8()),
+                _ => DynamicImage::ImageRgb8(image.to_rgb8()),
+            };
+            encoder
+                .write_image(image.as_bytes(), width, height, image.color())
+                .context("failed to encode PNG")?;
+        }
+        OutputFormat::WebP => {
+            let quality = f32::from(quality);
+            let encoded = if image.color().has_alpha() {
+                let rgba = image.to_rgba8();
+                webp::Encoder::from_rgba(&rgba, width, height).encode_simple(false, quality)
+            } else {
+                let rgb = image.to_rgb8();
+                webp::Encoder::from_rgb(&rgb, width, height).encode_simple(false, quality)
+            }
+            .map_err(|e| anyhow!("failed to encode WebP: {e:?}"))?;
+            out.extend_from_slice(&encoded);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, ImageFormat};
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageLuma8(GrayImage::new(width, height));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn webp_shrinks_images_wider_than_libwebp_allows() {
+        let options = OptimizeOptions {
+            max_size: None,
+            format: OutputFormat::WebP,
+            ..Default::default()
+        };
+        let optimized = optimize_for_ai(&png(WEBP_MAX_EDGE + 1, 2), &options).unwrap();
+        assert_eq!((optimized.width, optimized.height), (WEBP_MAX_EDGE, 2));
+        assert!(optimized.bytes.starts_with(b"RIFF"));
+    }
+
+    #[test]
+    fn webp_encoding_errors_are_returned() {
+        let image = DynamicImage::ImageLuma8(GrayImage::new(WEBP_MAX_EDGE + 1, 1));
+        assert!(encode(&image, OutputFormat::WebP, 80).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! EXIF orientation.
+//!
+//! Cameras store pixels in sensor order and record how to turn them
+//! upright in the Orientation tag. The `image` crate ignores the tag, so
+//! every loader in this crate reads it and applies the matching rotation
+//! or flip before anything else touches the pixels. Re-encoding drops
+//! EXIF metadata, which is only safe once the orientation has been baked
+//! into the pixels.
+
+use std::io::Cursor;
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use serde::Serialize;
+
+use crate::transform::Affine;
+
+/// How stored pixels are turned upright, one variant per value of the
+/// EXIF Orientation tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    /// 1: already upright.
+    #[default]
+    Normal,
+    /// 2: mirrored left to right.
+    FlipHorizontal,
+    /// 3: upside down.
+    Rotate180,
+    /// 4: mirrored top to bottom.
+    FlipVertical,
+    /// 5: mirrored across the top-left to bottom-right diagonal.
+    Transpose,
+    /// 6: needs a quarter turn clockwise.
+    Rotate90,
+    /// 7: mirrored across the top-right to bottom-left diagonal.
+    Transverse,
+    /// 8: needs a quarter turn counter-clockwise.
+    Rotate270,
+}
+
+impl Orientation {
+    /// Map an Orientation tag value. Unknown values are treated as
+    /// upright, as viewers do.
+    pub fn from_tag(value: u32) -> Self {
+        match value {
+            2 => Self::FlipHorizontal,
+            3 => Self::Rotate180,
+            4 => Self::FlipVertical,
+            5 => Self::Transpose,
+            6 => Self::Rotate90,
+            7 => Self::Transverse,
+            8 => Self::Rotate270,
+            _ => Self::Normal,
+        }
+    }
+
+    /// Read the orientation from the EXIF block of an encoded image.
+    /// Images without EXIF data, or with a damaged block, are upright.
+    pub fn from_exif(bytes: &[u8]) -> Self {
+        exif::Reader::new()
+            .read_from_container(&mut Cursor::new(bytes))
+            .ok()
+            .and_then(|exif| {
+                exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                    .and_then(|field| field.value.get_uint(0))
+            })
+            .map_or(Self::Normal, Self::from_tag)
+    }
+
+    /// Map from stored to upright coordinates for stored pixels of
+    /// `width` × `height`.
+    pub fn transform(self, width: u32, height: u32) -> Affine {
+        let (w, h) = (f64::from(width), f64::from(height));
+        let (a, b, c, d, e, f) = match self {
+            Self::Normal => return Affine::IDENTITY,
+            Self::FlipHorizontal => (-1.0, 0.0, w, 0.0, 1.0, 0.0),
+            Self::Rotate180 => (-1.0, 0.0, w, 0.0, -1.0, h),
+            Self::FlipVertical => (1.0, 0.0, 0.0, 0.0, -1.0, h),
+            Self::Transpose => (0.0, 1.0, 0.0, 1.0, 0.0, 0.0),
+            Self::Rotate90 => (0.0, -1.0, h, 1.0, 0.0, 0.0),
+            Self::Transverse => (0.0, -1.0, h, -1.0, 0.0, w),
+            Self::Rotate270 => (0.0, 1.0, 0.0, -1.0, 0.0, w),
+        };
+        Affine { a, b, c, d, e, f }
+    }
+
+    /// Turn stored pixels upright.
+    pub fn apply(self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Self::Normal => image,
+            Self::FlipHorizontal => image.fliph(),
+            Self::Rotate180 => image.rotate180(),
+            Self::FlipVertical => image.flipv(),
+            Self::Transpose => image.rotate90().fliph(),
+            Self::Rotate90 => image.rotate90(),
+            Self::Transverse => image.rotate270().fliph(),
+            Self::Rotate270 => image.rotate270(),
+        }
+    }
+}
+
+/// A decoded image, turned upright.
+#[derive(Debug, Clone)]
+pub struct Loaded {
+    pub image: DynamicImage,
+    /// Transform applied to the stored pixels.
+    pub orientation: Orientation,
+    /// Map from stored to upright coordinates.
+    pub transform: Affine,
+}
+
+impl Loaded {
+    /// Turn stored pixels upright.
+    pub fn new(stored: DynamicImage, orientation: Orientation) -> Self {
+        let transform = orientation.transform(stored.width(), stored.height());
+        Self {
+            image: orientation.apply(stored),
+            orientation,
+            transform,
+        }
+    }
+}
+
+/// Decode an image and apply its EXIF orientation.
+pub fn load(bytes: &[u8]) -> Result<Loaded> {
+    let image = image::load_from_memory(bytes).context("failed to decode image")?;
+    Ok(Loaded::new(image, Orientation::from_exif(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImageView, GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn maps_points_like_the_pixels_move() {
+        // The center of the top-right pixel of a 4 × 2 stored image.
+        let expected = [
+            (1, (3.5, 0.5)),
+            (2, (0.5, 0.5)),
+            (3, (0.5, 1.5)),
+            (4, (3.5, 1.5)),
+            (5, (0.5, 3.5)),
+            (6, (1.5, 3.5)),
+            (7, (1.5, 0.5)),
+            (8, (0.5, 0.5)),
+        ];
+        for (tag, point) in expected {
+            let orientation = Orientation::from_tag(tag);
+            assert_eq!(
+                orientation.transform(4, 2).apply(3.5, 0.5),
+                point,
+                "tag {tag}"
+            );
+
+            // The marked pixel lands where the transform says.
+            let mut stored = GrayImage::new(4, 2);
+            stored.put_pixel(3, 0, Luma([255]));
+            let upright = Loaded::new(DynamicImage::ImageLuma8(stored), orientation).image;
+            let (x, y) = (point.0 as u32, point.1 as u32);
+            assert_eq!(upright.get_pixel(x, y).0[0], 255, "tag {tag}");
+        }
+    }
+}
@@ -0,0 +1,477 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Preprocessing before OCR.
+//!
+//! A [`Pipeline`] is an ordered list of [`Step`]s run on each page
+//! before it is handed to the engine. Deskew and upscale move pixels;
+//! the transform they compose to is returned with the image so boxes
+//! found on the processed image can be mapped back onto the original.
+//! [`Preset`]s name the pipelines the UI offers per scan.
+
+use image::{DynamicImage, GrayImage, Luma};
+use serde::Deserialize;
+
+use crate::deskew::{self, MAX_SKEW};
+use crate::transform::Affine;
+use crate::upscale::{self, MIN_TEXT_HEIGHT};
+
+/// Default window side for Sauvola thresholding, in pixels.
+pub const SAUVOLA_WINDOW: u32 = 31;
+
+/// Default Sauvola sensitivity. Higher values push more pixels to white.
+pub const SAUVOLA_K: f32 = 0.2;
+
+/// Dynamic range of the standard deviation in Sauvola's formula.
+const SAUVOLA_R: f32 = 128.0;
+
+/// One preprocessing operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// Convert to 8-bit grayscale.
+    Grayscale,
+    /// Stretch contrast so the `low` and `high` percentiles of the
+    /// luminance histogram map to black and white. Color is kept.
+    Normalize { low: f32, high: f32 },
+    /// Binarize with a single global threshold chosen by Otsu's method.
+    Otsu,
+    /// Binarize with a per-pixel threshold from the mean and deviation
+    /// of a `window`-sized neighbourhood. Copes with uneven lighting.
+    Sauvola { window: u32, k: f32 },
+    /// Replace each pixel with the median of the square of the given
+    /// radius around it. Removes speckle noise while keeping edges.
+    Median { radius: u32 },
+    /// Estimate the skew, up to `max_angle` degrees either way, and
+    /// rotate the image straight.
+    Deskew { max_angle: f32 },
+    /// Enlarge the image when its text is shorter than `min_height`
+    /// pixels.
+    Upscale { min_height: u32 },
+}
+
+impl Step {
+    /// Run the step. Steps that move pixels also return the map onto
+    /// the new image, and deskew the skew it corrected.
+    fn apply(&self, image: DynamicImage) -> (DynamicImage, Affine, f64) {
+        let image = match *self {
+            Step::Grayscale => DynamicImage::ImageLuma8(image.into_luma8()),
+            Step::Normalize { low, high } => normalize(image, low, high),
+            Step::Otsu => {
+                let mut gray = image.into_luma8();
+                let threshold = otsu_threshold(&gray);
+                threshold_in_place(&mut gray, |_, _| threshold);
+                DynamicImage::ImageLuma8(gray)
+            }
+            Step::Sauvola { window, k } => {
+                DynamicImage::ImageLuma8(sauvola(&image.into_luma8(), window, k))
+            }
+            Step::Median { radius } => {
+                DynamicImage::ImageLuma8(median(&image.into_luma8(), radius))
+            }
+            Step::Deskew { max_angle } => match deskew::deskew(&image, max_angle) {
+                Some((rotated, skew)) => return (rotated, skew.transform, skew.angle),
+                None => image,
+            },
+            Step::Upscale { min_height } => match upscale::upscale(&image, min_height) {
+                Some((upscaled, transform)) => return (upscaled, transform, 0.0),
+                None => image,
+            },
+        };
+        (image, Affine::IDENTITY, 0.0)
+    }
+}
+
+/// Output of [`Pipeline::run`].
+#[derive(Debug, Clone)]
+pub struct Processed {
+    pub image: DynamicImage,
+    /// Map from the input image onto `image`, composed from every step
+    /// that moved pixels.
+    pub transform: Affine,
+    /// Total skew corrected, in degrees.
+    pub skew: f64,
+}
+
+impl Processed {
+    /// Map a point on the processed image back onto the input image.
+    pub fn to_original(&self, x: f64, y: f64) -> (f64, f64) {
+        self.transform
+            .invert()
+            .map_or((x, y), |inverse| inverse.apply(x, y))
+    }
+}
+
+/// An ordered list of preprocessing steps.
+///
+/// ```ignore
+/// let pipeline = Pipeline::new().grayscale().normalize().median(1).otsu();
+/// let processed = pipeline.run(image).image;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn grayscale(self) -> Self {
+        self.step(Step::Grayscale)
+    }
+
+    /// Contrast stretch between the 1st and 99th percentiles.
+    pub fn normalize(self) -> Self {
+        self.step(Step::Normalize {
+            low: 0.01,
+            high: 0.99,
+        })
+    }
+
+    pub fn otsu(self) -> Self {
+        self.step(Step::Otsu)
+    }
+
+    /// Sauvola thresholding with the default window and sensitivity.
+    pub fn sauvola(self) -> Self {
+        self.step(Step::Sauvola {
+            window: SAUVOLA_WINDOW,
+            k: SAUVOLA_K,
+        })
+    }
+
+    pub fn median(self, radius: u32) -> Self {
+        self.step(Step::Median { radius })
+    }
+
+    /// Deskew, searching up to [`MAX_SKEW`] degrees.
+    pub fn deskew(self) -> Self {
+        self.step(Step::Deskew {
+            max_angle: MAX_SKEW,
+        })
+    }
+
+    /// Upscale text shorter than [`MIN_TEXT_HEIGHT`].
+    pub fn upscale(self) -> Self {
+        self.step(Step::Upscale {
+            min_height: MIN_TEXT_HEIGHT,
+        })
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Whether every step only moves pixels, so a run whose transform
+    /// is the identity returned the image as it was.
+    pub fn is_geometric(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| matches!(step, Step::Deskew { .. } | Step::Upscale { .. }))
+    }
+
+    /// Run every step in order.
+    pub fn run(&self, image: DynamicImage) -> Processed {
+        let start = Processed {
+            image,
+            transform: Affine::IDENTITY,
+            skew: 0.0,
+        };
+        self.steps.iter().fold(start, |processed, step| {
+            let (image, transform, skew) = step.apply(processed.image);
+            Processed {
+                image,
+                transform: processed.transform.then(transform),
+                skew: processed.skew + skew,
+            }
+        })
+    }
+}
+
+/// Named pipelines offered to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// Hand the image to the engine untouched, unless its text is too
+    /// small to read, in which case it is only enlarged.
+    #[default]
+    Original,
+    /// Grayscale with stretched contrast, and small text enlarged. Safe
+    /// for screenshots, small captures and clean photos.
+    Grayscale,
+    /// Flatbed scans: straighten and denoise, then a global threshold.
+    Scan,
+    /// Phone photos of documents with shadows or uneven light:
+    /// straighten and denoise, then an adaptive threshold.
+    Photo,
+}
+
+impl Preset {
+    pub fn pipeline(self) -> Pipeline {
+        match self {
+            Preset::Original => Pipeline::new().upscale(),
+            Preset::Grayscale => Pipeline::new().grayscale().normalize().upscale(),
+            Preset::Scan => Pipeline::new()
+                .grayscale()
+                .normalize()
+                .upscale()
+                .deskew()
+                .median(1)
+                .otsu(),
+            Preset::Photo => Pipeline::new()
+                .grayscale()
+                .normalize()
+                .upscale()
+                .deskew()
+                .median(1)
+                .sauvola(),
+        }
+    }
+}
+
+fn normalize(image: DynamicImage, low: f32, high: f32) -> DynamicImage {
+    let mut histogram = [0u64; 256];
+    let gray = image.to_luma8();
+    for p in gray.pixels() {
+        histogram[usize::from(p.0[0])] += 1;
+    }
+    let total = u64::from(gray.width()) * u64::from(gray.height());
+    if total == 0 {
+        return image;
+    }
+
+    let percentile = |fraction: f32| {
+        let target = (fraction.clamp(0.0, 1.0) as f64 * total as f64) as u64;
+        let mut seen = 0;
+        for (value, &count) in histogram.iter().enumerate() {
+            seen += count;
+            if seen > target {
+                return value as u8;
+            }
+        }
+        255
+    };
+    let (lo, hi) = (percentile(low), percentile(high));
+    if hi <= lo {
+        return image;
+    }
+
+    let scale = 255.0 / f32::from(hi - lo);
+    let lut: Vec<u8> = (0..=255u8)
+        .map(|v| (f32::from(v.saturating_sub(lo)) * scale).round().min(255.0) as u8)
+        .collect();
+    let map = |v: &mut u8| *v = lut[usize::from(*v)];
+
+    match image {
+        DynamicImage::ImageLuma8(mut img) => {
+            img.iter_mut().for_each(map);
+            DynamicImage::ImageLuma8(img)
+        }
+        DynamicImage::ImageRgba8(mut img) => {
+            // Leave alpha alone.
+            img.pixels_mut()
+                .for_each(|p| p.0[..3].iter_mut().for_each(map));
+            DynamicImage::ImageRgba8(img)
+        }
+        other => {
+            let mut img = other.into_rgb8();
+            img.iter_mut().for_each(map);
+            DynamicImage::ImageRgb8(img)
+        }
+    }
+}
+
+/// Split `image` into ink and background with Otsu's threshold and
+/// return a test for ink values. Ink is the minority class, so light
+/// text on a dark background works too.
+pub(crate) fn ink_test(image: &GrayImage) -> impl Fn(u8) -> bool {
+    let threshold = otsu_threshold(image);
+    let dark = image.pixels().filter(|p| p.0[0] <= threshold).count();
+    let ink_is_dark = dark * 2 <= image.len();
+    move |v| (v <= threshold) == ink_is_dark
+}
+
+/// Threshold that best separates the histogram into two classes.
+pub fn otsu_threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for p in image.pixels() {
+        histogram[usize::from(p.0[0])] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(v, &c)| v as f64 * c as f64)
+        .sum();
+
+    let (mut best, mut best_variance) = (0u8, -1.0);
+    let (mut weight_bg, mut sum_bg) = (0u64, 0.0);
+    for (value, &count) in histogram.iter().enumerate() {
+        weight_bg += count;
+        if weight_bg == 0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0 {
+            break;
+        }
+        sum_bg += value as f64 * count as f64;
+        let mean_bg = sum_bg / weight_bg as f64;
+        let mean_fg = (sum - sum_bg) / weight_fg as f64;
+        let variance = weight_bg as f64 * weight_fg as f64 * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = value as u8;
+        }
+    }
+    best
+}
+
+/// Set pixels above the threshold for their position to white and the
+/// rest to black.
+fn threshold_in_place(image: &mut GrayImage, threshold: impl Fn(u32, u32) -> u8) {
+    for (x, y, p) in image.enumerate_pixels_mut() {
+        p.0[0] = if p.0[0] > threshold(x, y) { 255 } else { 0 };
+    }
+}
+
+fn sauvola(image: &GrayImage, window: u32, k: f32) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 {
+        return image.clone();
+    }
+
+    // Integral images of values and squared values, with a zero row and
+    // column in front so window sums need no bounds checks.
+    let stride = w + 1;
+    let mut sum = vec![0u64; stride * (h + 1)];
+    let mut sq = vec![0u64; stride * (h + 1)];
+    for y in 0..h {
+        let (mut row_sum, mut row_sq) = (0u64, 0u64);
+        for x in 0..w {
+            let v = u64::from(image.get_pixel(x as u32, y as u32).0[0]);
+            row_sum += v;
+            row_sq += v * v;
+            let i = (y + 1) * stride + x + 1;
+            sum[i] = sum[i - stride] + row_sum;
+            sq[i] = sq[i - stride] + row_sq;
+        }
+    }
+
+    let half = (window.max(3) / 2) as usize;
+    let mut out = GrayImage::new(width, height);
+    for y in 0..h {
+        let (y0, y1) = (y.saturating_sub(half), (y + half + 1).min(h));
+        for x in 0..w {
+            let (x0, x1) = (x.saturating_sub(half), (x + half + 1).min(w));
+            let area = ((y1 - y0) * (x1 - x0)) as f64;
+            let window_sum = |t: &[u64]| {
+                (t[y1 * stride + x1] + t[y0 * stride + x0]) as f64
+                    - (t[y0 * stride + x1] + t[y1 * stride + x0]) as f64
+            };
+            let mean = window_sum(&sum) / area;
+            let variance = (window_sum(&sq) / area - mean * mean).max(0.0);
+            let threshold =
+                mean * (1.0 + f64::from(k) * (variance.sqrt() / f64::from(SAUVOLA_R) - 1.0));
+
+            let v = image.get_pixel(x as u32, y as u32).0[0];
+            out.put_pixel(
+                x as u32,
+                y as u32,
+                Luma([if f64::from(v) > threshold { 255 } else { 0 }]),
+            );
+        }
+    }
+    out
+}
+
+fn median(image: &GrayImage, radius: u32) -> GrayImage {
+    if radius == 0 {
+        return image.clone();
+    }
+    let (width, height) = image.dimensions();
+    let r = radius as i64;
+    let mut window = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+    let mut out = GrayImage::new(width, height);
+    for y in 0..i64::from(height) {
+        for x in 0..i64::from(width) {
+            window.clear();
+            // Clamp at the borders so edge pixels see a full window.
+            for dy in -r..=r {
+                let sy = (y + dy).clamp(0, i64::from(height) - 1) as u32;
+                for dx in -r..=r {
+                    let sx = (x + dx).clamp(0, i64::from(width) - 1) as u32;
+                    window.push(image.get_pixel(sx, sy).0[0]);
+                }
+            }
+            let mid = window.len() / 2;
+            let (_, m, _) = window.select_nth_unstable(mid);
+            out.put_pixel(x as u32, y as u32, Luma([*m]));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otsu_splits_a_bimodal_histogram() {
+        // Dark ink spread over 40..=60 on paper spread over 190..=210.
+        let image = GrayImage::from_fn(100, 100, |x, y| {
+            let spread = ((x + y) % 21) as u8;
+            Luma([if x < 30 { 40 + spread } else { 190 + spread }])
+        });
+        let threshold = otsu_threshold(&image);
+        assert!((60..190).contains(&threshold), "threshold {threshold}");
+
+        let binary = Pipeline::new().otsu().run(DynamicImage::ImageLuma8(image));
+        let binary = binary.image.into_luma8();
+        for (x, _, p) in binary.enumerate_pixels() {
+            assert_eq!(p.0[0], if x < 30 { 0 } else { 255 });
+        }
+    }
+
+    #[test]
+    fn sauvola_follows_a_shaded_background() {
+        // Paper shading from 100 on the left to 240 on the right, with
+        // strokes 70 darker than the paper around them. Ink on the right
+        // is lighter than paper on the left, so no global threshold works.
+        let paper = |x: u32| 100 + (x * 140 / 199) as u8;
+        let is_stroke = |x: u32, y: u32| (20..40).contains(&y) && x % 10 < 2;
+        let image = GrayImage::from_fn(200, 60, |x, y| {
+            Luma([if is_stroke(x, y) {
+                paper(x) - 70
+            } else {
+                paper(x)
+            }])
+        });
+        assert!(paper(199) - 70 > paper(0));
+
+        let binary = sauvola(&image, SAUVOLA_WINDOW, SAUVOLA_K);
+        for (x, y, p) in binary.enumerate_pixels() {
+            let expected = if is_stroke(x, y) { 0 } else { 255 };
+            assert_eq!(p.0[0], expected, "pixel ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn median_removes_speckles() {
+        let mut image = GrayImage::from_pixel(9, 9, Luma([255]));
+        image.put_pixel(4, 4, Luma([0]));
+        image.put_pixel(0, 0, Luma([0]));
+        assert!(median(&image, 1).pixels().all(|p| p.0[0] == 255));
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Splitting large images into overlapping tiles.
+//!
+//! The detector shrinks large inputs to a fixed size, which wipes out
+//! small text on full multi-monitor screenshots and large scans. Cutting
+//! the image into tiles keeps text at its native resolution; the overlap
+//! gives every line short enough to fit in it one tile that sees it
+//! whole.
+
+use image::DynamicImage;
+
+use crate::transform::Affine;
+
+/// Default tile side, in pixels.
+pub const TILE_SIZE: u32 = 1600;
+
+/// Default overlap between neighbouring tiles, in pixels.
+pub const TILE_OVERLAP: u32 = 256;
+
+/// A rectangle of the image, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Cut the tile out of `image`, with the map from image to tile
+    /// coordinates.
+    pub fn crop(&self, image: &DynamicImage) -> (DynamicImage, Affine) {
+        let tile = image.crop_imm(self.x, self.y, self.width, self.height);
+        let transform = Affine::translate(-f64::from(self.x), -f64::from(self.y));
+        (tile, transform)
+    }
+}
+
+/// Cover a `width` × `height` image with tiles of at most `size` pixels
+/// a side, overlapping by at least `overlap`. Tiles are spread evenly,
+/// so the last row and column end flush with the image, and are listed
+/// row by row.
+pub fn tiles(width: u32, height: u32, size: u32, overlap: u32) -> Vec<Tile> {
+    let size = size.max(1);
+    let overlap = overlap.min(size - 1);
+    let xs = starts(width, size, overlap);
+    let ys = starts(height, size, overlap);
+    ys.iter()
+        .flat_map(|&y| {
+            xs.iter().map(move |&x| Tile {
+                x,
+                y,
+                width: size.min(width - x),
+                height: size.min(height - y),
+            })
+        })
+        .collect()
+}
+
+/// Start offsets along one axis.
+fn starts(length: u32, size: u32, overlap: u32) -> Vec<u32> {
+    if length <= size {
+        return vec![0];
+    }
+    let stride = size - overlap;
+    let count = (length - overlap).div_ceil(stride);
+    let last = length - size;
+    (0..count)
+        .map(|i| (u64::from(last) * u64::from(i) / u64::from(count - 1)) as u32)
+        .collect()
+}
@@ -0,0 +1,165 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Coordinate transforms between images.
+//!
+//! Every operation in this crate that moves pixels (orienting,
+//! resizing, cropping, rotating) returns the [`Affine`] map from input
+//! to output coordinates alongside its image, and pipelines compose
+//! them. Inverting the composite takes a box found on the processed
+//! image back onto the original.
+//!
+//! Coordinates are continuous: pixel `(i, j)` covers the square from
+//! `(i, j)` to `(i + 1, j + 1)`, so an image `w` pixels wide spans `0` to
+//! `w`. This is the convention the engine reports boxes in.
+
+/// A 2D affine map `(x, y) -> (a·x + b·y + c, d·x + e·y + f)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 0.0,
+        e: 1.0,
+        f: 0.0,
+    };
+
+    pub fn translate(dx: f64, dy: f64) -> Self {
+        Self {
+            c: dx,
+            f: dy,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self {
+            a: sx,
+            e: sy,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Rotation by `degrees` about the origin, clockwise on screen since
+    /// y grows downwards.
+    pub fn rotate(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            a: cos,
+            b: -sin,
+            d: sin,
+            e: cos,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Map that scales a `from` sized image onto a `to` sized one.
+    pub fn resize(from: (u32, u32), to: (u32, u32)) -> Self {
+        Self::scale(
+            f64::from(to.0) / f64::from(from.0),
+            f64::from(to.1) / f64::from(from.1),
+        )
+    }
+
+    /// `self` followed by `next`.
+    pub fn then(self, next: Affine) -> Affine {
+        Affine {
+            a: next.a * self.a + next.b * self.d,
+            b: next.a * self.b + next.b * self.e,
+            c: next.a * self.c + next.b * self.f + next.c,
+            d: next.d * self.a + next.e * self.d,
+            e: next.d * self.b + next.e * self.e,
+            f: next.d * self.c + next.e * self.f + next.f,
+        }
+    }
+
+    /// The reverse map, or `None` when the map collapses the plane.
+    pub fn invert(self) -> Option<Affine> {
+        let det = self.a * self.e - self.b * self.d;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (a, b, d, e) = (self.e / det, -self.b / det, -self.d / det, self.a / det);
+        Some(Affine {
+            a,
+            b,
+            c: -(a * self.c + b * self.f),
+            d,
+            e,
+            f: -(d * self.c + e * self.f),
+        })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.b * y + self.c,
+            self.d * x + self.e * y + self.f,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Affine, expected: Affine) {
+        let parts = |t: Affine| [t.a, t.b, t.c, t.d, t.e, t.f];
+        for (x, y) in parts(actual).into_iter().zip(parts(expected)) {
+            assert!((x - y).abs() < 1e-9, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn composes_in_order() {
+        // Scale then move is not move then scale.
+        let t = Affine::scale(2.0, 3.0).then(Affine::translate(10.0, 20.0));
+        assert_eq!(t.apply(1.0, 1.0), (12.0, 23.0));
+        let t = Affine::translate(10.0, 20.0).then(Affine::scale(2.0, 3.0));
+        assert_eq!(t.apply(1.0, 1.0), (22.0, 63.0));
+        // A quarter turn clockwise on screen takes +x to +y.
+        let (x, y) = Affine::rotate(90.0).apply(1.0, 0.0);
+        assert!(x.abs() < 1e-12 && (y - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn inverts_compositions() {
+        let transforms = [
+            Affine::rotate(17.5),
+            Affine::resize((640, 480), (1600, 1000)),
+            Affine::translate(-320.0, -240.0)
+                .then(Affine::rotate(-4.25))
+                .then(Affine::translate(330.0, 262.0)),
+            Affine::resize((100, 50), (300, 200))
+                .then(Affine::rotate(90.0))
+                .then(Affine::translate(200.0, 0.0)),
+        ];
+        for t in transforms {
+            let inverse = t.invert().expect("invertible");
+            assert_near(t.then(inverse), Affine::IDENTITY);
+            assert_near(inverse.then(t), Affine::IDENTITY);
+            let (x, y) = inverse.apply(t.apply(12.0, 34.0).0, t.apply(12.0, 34.0).1);
+            assert!((x - 12.0).abs() < 1e-9 && (y - 34.0).abs() < 1e-9);
+        }
+        assert_eq!(Affine::scale(0.0, 1.0).invert(), None);
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Upscaling images with small text.
+//!
+//! The recognizer misses text only a few pixels tall, which is common in
+//! small screen captures. The dominant text height is estimated from the
+//! connected components of the ink: glyphs make up most components, so
+//! the median height of the glyph-shaped ones tracks the font size. When
+//! it is below the threshold the image is enlarged with a sharp cubic
+//! filter that keeps strokes crisp.
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+
+use crate::preprocess::ink_test;
+use crate::transform::Affine;
+
+/// Default smallest acceptable median glyph height, in pixels.
+pub const MIN_TEXT_HEIGHT: u32 = 16;
+
+/// Largest factor an image is ever enlarged by.
+const MAX_UPSCALE: f64 = 4.0;
+
+/// Upscaling never produces more pixels than this.
+const MAX_PIXELS: f64 = 24_000_000.0;
+
+/// Fewer glyph-shaped components than this is not enough to measure.
+const MIN_COMPONENTS: usize = 5;
+
+/// Factors below this are not worth the resampling.
+const MIN_UPSCALE: f64 = 1.1;
+
+/// Median height in pixels of the glyph-shaped ink components of
+/// `image`, or `None` when there are too few to tell.
+pub fn text_height(image: &DynamicImage) -> Option<f64> {
+    let gray = image.to_luma8();
+    let max_height = gray.height() / 2;
+    let mut heights: Vec<u32> = components(&gray)
+        .into_iter()
+        .filter(|c| {
+            // Drop specks, rules, frames and anything too sparse to be a
+            // glyph.
+            let (w, h) = (c.width(), c.height());
+            h >= 3 && h <= max_height && w <= h * 8 && c.pixels * 10 >= w * h
+        })
+        .map(|c| c.height())
+        .collect();
+    if heights.len() < MIN_COMPONENTS {
+        return None;
+    }
+    let mid = heights.len() / 2;
+    let (_, median, _) = heights.select_nth_unstable(mid);
+    Some(f64::from(*median))
+}
+
+/// Enlarge `image` so its text is at least `min_height` pixels tall,
+/// returning the image with the map onto it.
+///
+/// Returns `None` when the text is already tall enough or its height
+/// cannot be measured.
+pub fn upscale(image: &DynamicImage, min_height: u32) -> Option<(DynamicImage, Affine)> {
+    let height = text_height(image)?;
+    let (width, image_height) = (f64::from(image.width()), f64::from(image.height()));
+    let factor = (f64::from(min_height) / height)
+        .min(MAX_UPSCALE)
+        .min((MAX_PIXELS / (width * image_height)).sqrt());
+    if factor < MIN_UPSCALE {
+        return None;
+    }
+
+    let to = (
+        (width * factor).round() as u32,
+        (image_height * factor).round() as u32,
+    );
+    let upscaled = image.resize_exact(to.0, to.1, FilterType::CatmullRom);
+    let transform = Affine::resize((image.width(), image.height()), to);
+    Some((upscaled, transform))
+}
+
+/// Bounding box and size of one 8-connected ink component.
+struct Component {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    pixels: u32,
+}
+
+impl Component {
+    fn width(&self) -> u32 {
+        self.right - self.left + 1
+    }
+
+    fn height(&self) -> u32 {
+        self.bottom - self.top + 1
+    }
+}
+
+fn components(gray: &GrayImage) -> Vec<Component> {
+    let is_ink = ink_test(gray);
+    let (width, height) = gray.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut unseen: Vec<bool> = gray.pixels().map(|p| is_ink(p.0[0])).collect();
+
+    let mut found = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..unseen.len() {
+        if !unseen[start] {
+            continue;
+        }
+        unseen[start] = false;
+        stack.push(start);
+        let (x, y) = ((start % w) as u32, (start / w) as u32);
+        let mut component = Component {
+            left: x,
+            top: y,
+            right: x,
+            bottom: y,
+            pixels: 0,
+        };
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            component.pixels += 1;
+            component.left = component.left.min(x as u32);
+            component.right = component.right.max(x as u32);
+            component.bottom = component.bottom.max(y as u32);
+            for ny in y.saturating_sub(1)..(y + 2).min(h) {
+                for nx in x.saturating_sub(1)..(x + 2).min(w) {
+                    let j = ny * w + nx;
+                    if unseen[j] {
+                        unseen[j] = false;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        found.push(component);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImageView, Luma};
+
+    use super::*;
+    use crate::preprocess::Preset;
+
+    /// Rows of glyph-like blocks `size` px tall on white, with a stroke
+    /// gap in each so they are not solid.
+    fn text(size: u32) -> DynamicImage {
+        let pitch = size * 2;
+        let image = GrayImage::from_fn(20 * pitch, 6 * pitch, |x, y| {
+            let (gx, gy) = (x % pitch, y % pitch);
+            let ink = gx < size * 2 / 3 && gy < size && !(gx == size / 3 && gy > size / 2);
+            Luma([if ink { 0 } else { 255 }])
+        });
+        DynamicImage::ImageLuma8(image)
+    }
+
+    #[test]
+    fn measures_glyph_height() {
+        assert_eq!(text_height(&text(8)), Some(8.0));
+        assert_eq!(text_height(&text(10)), Some(10.0));
+        let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(100, 100, Luma([255])));
+        assert_eq!(text_height(&blank), None);
+    }
+
+    #[test]
+    fn enlarges_small_text() {
+        let small = text(9);
+        let (upscaled, transform) = upscale(&small, MIN_TEXT_HEIGHT).expect("small text");
+        assert_eq!(upscaled.dimensions(), (640, 192));
+        assert_eq!(transform.apply(360.0, 108.0), (640.0, 192.0));
+        let height = text_height(&upscaled).unwrap();
+        assert!((15.0..=17.0).contains(&height), "height {height}");
+
+        assert!(upscale(&text(MIN_TEXT_HEIGHT), MIN_TEXT_HEIGHT).is_none());
+    }
+
+    #[test]
+    fn the_default_preset_enlarges_small_text() {
+        let processed = Preset::default().pipeline().run(text(9));
+        assert_eq!(processed.image.dimensions(), (640, 192));
+        let untouched = Preset::default().pipeline().run(text(20));
+        assert!(untouched.transform.is_identity());
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! ALTO v4 export.
+//!
+//! Writes `Page` → `PrintSpace` → `TextBlock` → `TextLine` → `String`
+//! elements in pixel units, with `SP` elements between words and word
+//! confidences in `WC`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ocr_model::{BoundingBox, Rect};
+
+use crate::xml::{escape, px};
+use crate::{blocks, SourcePage};
+
+/// Write an ALTO document with one `Page` per entry to `out`.
+pub fn write_alto<W: Write>(pages: &[SourcePage<'_>], mut out: W) -> Result<()> {
+    let mut doc = String::new();
+    doc.push_str(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <alto xmlns=\"http://www.loc.gov/standards/alto/ns-v4#\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.loc.gov/standards/alto/ns-v4# \
+         http://www.loc.gov/alto/v4/alto-4-2.xsd\">\n",
+    );
+
+    doc.push_str(" <Description>\n  <MeasurementUnit>pixel</MeasurementUnit>\n");
+    // The schema allows a single source image; later pages are
+    // identified by their `PHYSICAL_IMG_NR`.
+    if let Some(page) = pages.first() {
+        let _ = writeln!(
+            doc,
+            "  <sourceImageInformation>\n   <fileName>{}</fileName>\n  </sourceImageInformation>",
+            escape(&page.image.to_string_lossy())
+        );
+        let engine = &page.ocr.engine;
+        let _ = writeln!(
+            doc,
+            "  <Processing ID=\"OCR_0\">\n   <processingSoftware>\n    \
+             <softwareName>{}</softwareName>\n    \
+             <softwareVersion>{}</softwareVersion>\n   \
+             </processingSoftware>\n  </Processing>",
+            escape(&engine.name),
+            escape(&engine.version)
+        );
+    }
+    doc.push_str(" </Description>\n <Layout>\n");
+
+    for (index, page) in pages.iter().enumerate() {
+        write_page(&mut doc, index, page);
+    }
+
+    doc.push_str(" </Layout>\n</alto>\n");
+    out.write_all(doc.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Write an ALTO document to a file.
+pub fn save_alto(pages: &[SourcePage<'_>], path: &Path) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    write_alto(pages, std::io::BufWriter::new(file))
+}
+
+fn write_page(doc: &mut String, index: usize, page: &SourcePage<'_>) {
+    let n = index + 1;
+    let ocr = page.ocr;
+
+    let _ = writeln!(
+        doc,
+        "  <Page ID=\"page_{n}\" PHYSICAL_IMG_NR=\"{n}\" WIDTH=\"{}\" HEIGHT=\"{}\">",
+        ocr.width, ocr.height
+    );
+    let _ = writeln!(
+        doc,
+        "   <PrintSpace HPOS=\"0\" VPOS=\"0\" WIDTH=\"{}\" HEIGHT=\"{}\">",
+        ocr.width, ocr.height
+    );
+
+    let mut line_no = 0;
+    let mut word_no = 0;
+    for (b, block) in blocks(ocr).iter().enumerate() {
+        let _ = writeln!(
+            doc,
+            "    <TextBlock ID=\"block_{n}_{}\" {}>",
+            b + 1,
+            position(&block.bounds)
+        );
+        for line in &block.lines {
+            line_no += 1;
+            let _ = writeln!(
+                doc,
+                "     <TextLine ID=\"line_{n}_{line_no}\" {}>",
+                position(&line.bbox.bounds())
+            );
+
+            let words: Vec<(&str, &BoundingBox, f32)> = if line.words.is_empty() {
+                vec![(line.text.as_str(), &line.bbox, line.confidence)]
+            } else {
+                line.words
+                    .iter()
+                    .map(|w| (w.text.as_str(), &w.bbox, w.confidence))
+                    .collect()
+            };
+
+            let mut previous: Option<Rect> = None;
+            for (text, bbox, confidence) in words {
+                let bounds = bbox.bounds();
+                if let Some(prev) = previous {
+                    let _ = writeln!(
+                        doc,
+                        "      <SP HPOS=\"{}\" VPOS=\"{}\" WIDTH=\"{}\"/>",
+                        px(prev.x1),
+                        px(prev.y0),
+                        px((bounds.x0 - prev.x1).max(0.0))
+                    );
+                }
+                word_no += 1;
+                let _ = writeln!(
+                    doc,
+                    "      <String ID=\"word_{n}_{word_no}\" {} CONTENT=\"{}\" WC=\"{:.2}\"/>",
+                    position(&bounds),
+                    escape(text),
+                    confidence.clamp(0.0, 1.0)
+                );
+                previous = Some(bounds);
+            }
+
+            doc.push_str("     </TextLine>\n");
+        }
+        doc.push_str("    </TextBlock>\n");
+    }
+
+    doc.push_str("   </PrintSpace>\n  </Page>\n");
+}
+
+/// `HPOS`, `VPOS`, `WIDTH` and `HEIGHT` attributes for a rectangle.
+fn position(rect: &Rect) -> String {
+    format!(
+        "HPOS=\"{}\" VPOS=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\"",
+        px(rect.x0),
+        px(rect.y0),
+        px(rect.width()),
+        px(rect.height())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_in_order, sample_page};
+
+    #[test]
+    fn writes_escaped_nested_elements() {
+        let ocr = sample_page();
+        let page = SourcePage {
+            image: Path::new("/scans/a&b.png"),
+            ocr: &ocr,
+        };
+        let mut out = Vec::new();
+        write_alto(&[page], &mut out).unwrap();
+        let doc = String::from_utf8(out).unwrap();
+
+        assert_in_order(
+            &doc,
+            &[
+                "<fileName>/scans/a&amp;b.png</fileName>",
+                "<Layout>",
+                "<Page ID=\"page_1\" PHYSICAL_IMG_NR=\"1\" WIDTH=\"300\" HEIGHT=\"100\">",
+                "<PrintSpace HPOS=\"0\" VPOS=\"0\" WIDTH=\"300\" HEIGHT=\"100\">",
+                "<TextBlock ID=\"block_1_1\" HPOS=\"10\" VPOS=\"20\" WIDTH=\"200\" HEIGHT=\"30\">",
+                "<TextLine ID=\"line_1_1\" HPOS=\"10\" VPOS=\"20\" WIDTH=\"200\" HEIGHT=\"30\">",
+                "<String ID=\"word_1_1\" HPOS=\"10\" VPOS=\"20\" WIDTH=\"100\" HEIGHT=\"30\" \
+                 CONTENT=\"5&lt;&amp;&quot;&gt;\" WC=\"0.90\"/>",
+                "<SP HPOS=\"110\" VPOS=\"20\" WIDTH=\"10\"/>",
+                "<String ID=\"word_1_2\" HPOS=\"120\" VPOS=\"20\" WIDTH=\"90\" HEIGHT=\"30\" \
+                 CONTENT=\"end\" WC=\"0.90\"/>",
+                "</TextLine>",
+                "</TextBlock>",
+                "</PrintSpace>",
+                "</Page>",
+                "</Layout>",
+            ],
+        );
+    }
+}
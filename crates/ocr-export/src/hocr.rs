@@ -0,0 +1,200 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! hOCR export.
+//!
+//! Writes an XHTML document following the hOCR 1.2 spec with
+//! `ocr_page` → `ocr_carea` → `ocr_par` → `ocr_line` → `ocrx_word`
+//! elements. Confidences go into `x_wconf` as percentages.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ocr_model::{BoundingBox, Line, Rect};
+
+use crate::xml::{escape, px};
+use crate::{blocks, SourcePage};
+
+/// Write an hOCR document with one `ocr_page` per entry to `out`.
+pub fn write_hocr<W: Write>(pages: &[SourcePage<'_>], mut out: W) -> Result<()> {
+    let mut doc = String::new();
+    doc.push_str(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \
+         \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n",
+    );
+
+    let lang = pages
+        .first()
+        .map(|p| p.ocr.engine.lang.as_str())
+        .filter(|l| !l.is_empty())
+        .unwrap_or("en");
+    let _ = writeln!(
+        doc,
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"{0}\" lang=\"{0}\">",
+        escape(lang)
+    );
+    doc.push_str(" <head>\n  <title></title>\n");
+    doc.push_str("  <meta http-equiv=\"Content-Type\" content=\"text/html;charset=utf-8\"/>\n");
+    if let Some(page) = pages.first() {
+        let engine = &page.ocr.engine;
+        let _ = writeln!(
+            doc,
+            "  <meta name=\"ocr-system\" content=\"{}\"/>",
+            escape(format!("{} {}", engine.name, engine.version).trim())
+        );
+    }
+    doc.push_str(
+        "  <meta name=\"ocr-capabilities\" \
+         content=\"ocr_page ocr_carea ocr_par ocr_line ocrx_word\"/>\n",
+    );
+    doc.push_str(" </head>\n <body>\n");
+
+    for (index, page) in pages.iter().enumerate() {
+        write_page(&mut doc, index, page);
+    }
+
+    doc.push_str(" </body>\n</html>\n");
+    out.write_all(doc.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Write an hOCR document to a file.
+pub fn save_hocr(pages: &[SourcePage<'_>], path: &Path) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    write_hocr(pages, std::io::BufWriter::new(file))
+}
+
+fn write_page(doc: &mut String, index: usize, page: &SourcePage<'_>) {
+    let n = index + 1;
+    let ocr = page.ocr;
+    let image = page
+        .image
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let _ = writeln!(
+        doc,
+        "  <div class=\"ocr_page\" id=\"page_{n}\" \
+         title=\"image &quot;{}&quot;; bbox 0 0 {} {}; ppageno {index}\">",
+        escape(&image),
+        ocr.width,
+        ocr.height
+    );
+
+    let mut line_no = 0;
+    let mut word_no = 0;
+    for (b, block) in blocks(ocr).iter().enumerate() {
+        let b = b + 1;
+        let bbox = bbox_attr(&block.bounds);
+        let _ = writeln!(
+            doc,
+            "   <div class=\"ocr_carea\" id=\"block_{n}_{b}\" title=\"{bbox}\">"
+        );
+        let _ = writeln!(
+            doc,
+            "    <p class=\"ocr_par\" id=\"par_{n}_{b}\" title=\"{bbox}\">"
+        );
+        for line in &block.lines {
+            line_no += 1;
+            let _ = writeln!(
+                doc,
+                "     <span class=\"ocr_line\" id=\"line_{n}_{line_no}\" title=\"{}\">",
+                line_title(line)
+            );
+            let words: Vec<(&str, &BoundingBox, f32)> = if line.words.is_empty() {
+                vec![(line.text.as_str(), &line.bbox, line.confidence)]
+            } else {
+                line.words
+                    .iter()
+                    .map(|w| (w.text.as_str(), &w.bbox, w.confidence))
+                    .collect()
+            };
+            for (text, bbox, confidence) in words {
+                word_no += 1;
+                let _ = writeln!(
+                    doc,
+                    "      <span class=\"ocrx_word\" id=\"word_{n}_{word_no}\" \
+                     title=\"{}; x_wconf {}\">{}</span>",
+                    bbox_attr(&bbox.bounds()),
+                    (confidence * 100.0).round().clamp(0.0, 100.0) as i64,
+                    escape(text)
+                );
+            }
+            doc.push_str("     </span>\n");
+        }
+        doc.push_str("    </p>\n   </div>\n");
+    }
+
+    doc.push_str("  </div>\n");
+}
+
+/// `title` properties of a line: bbox, baseline and x_size.
+fn line_title(line: &Line) -> String {
+    let bounds = line.bbox.bounds();
+    let slope = line.bbox.angle().tan();
+    let offset = line.bbox.bottom_left().1 - bounds.y1;
+    format!(
+        "{}; baseline {:.3} {}; x_size {}",
+        bbox_attr(&bounds),
+        slope,
+        px(offset),
+        px(line.bbox.height())
+    )
+}
+
+fn bbox_attr(rect: &Rect) -> String {
+    format!(
+        "bbox {} {} {} {}",
+        px(rect.x0),
+        px(rect.y0),
+        px(rect.x1),
+        px(rect.y1)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_in_order, sample_page};
+
+    #[test]
+    fn writes_escaped_nested_elements() {
+        let ocr = sample_page();
+        let page = SourcePage {
+            image: Path::new("/scans/a&b.png"),
+            ocr: &ocr,
+        };
+        let mut out = Vec::new();
+        write_hocr(&[page], &mut out).unwrap();
+        let doc = String::from_utf8(out).unwrap();
+
+        assert_in_order(
+            &doc,
+            &[
+                "<body>",
+                "<div class=\"ocr_page\" id=\"page_1\" \
+                 title=\"image &quot;a&amp;b.png&quot;; bbox 0 0 300 100; ppageno 0\">",
+                "<div class=\"ocr_carea\" id=\"block_1_1\" title=\"bbox 10 20 210 50\">",
+                "<p class=\"ocr_par\" id=\"par_1_1\" title=\"bbox 10 20 210 50\">",
+                "<span class=\"ocr_line\" id=\"line_1_1\" \
+                 title=\"bbox 10 20 210 50; baseline 0.000 0; x_size 30\">",
+                "<span class=\"ocrx_word\" id=\"word_1_1\" \
+                 title=\"bbox 10 20 110 50; x_wconf 90\">5&lt;&amp;&quot;&gt;</span>",
+                "<span class=\"ocrx_word\" id=\"word_1_2\" \
+                 title=\"bbox 120 20 210 50; x_wconf 90\">end</span>",
+                "</span>",
+                "</p>",
+                "</div>",
+                "</div>",
+                "</body>",
+            ],
+        );
+    }
+}
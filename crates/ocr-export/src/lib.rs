@@ -0,0 +1,89 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exporters for OCR results.
+//!
+//! Turn [`ocr_model`] pages into files other tools can read:
+//! searchable PDF, hOCR and ALTO XML.
+
+use std::path::Path;
+
+use ocr_model::{Line, Page, Rect};
+
+pub mod alto;
+pub mod hocr;
+pub mod pdf;
+
+mod xml;
+
+pub use alto::{save_alto, write_alto};
+pub use hocr::{save_hocr, write_hocr};
+pub use pdf::{save_pdf, write_pdf, PdfOptions};
+
+/// One output page: the source image and its OCR results.
+#[derive(Debug, Clone, Copy)]
+pub struct SourcePage<'a> {
+    pub image: &'a Path,
+    pub ocr: &'a Page,
+}
+
+/// A group of lines exported as one text block.
+pub(crate) struct Block<'a> {
+    pub bounds: Rect,
+    pub lines: Vec<&'a Line>,
+}
+
+/// A page's lines grouped into blocks, in reading order.
+pub(crate) fn blocks(page: &Page) -> Vec<Block<'_>> {
+    page.blocks()
+        .into_iter()
+        .map(|block| Block {
+            bounds: block.bounds,
+            lines: block.lines().map(|i| &page.lines[i]).collect(),
+        })
+        .collect()
+}
+
+/// A 300×100 page with one line of two words, the first full of
+/// characters XML has to escape.
+#[cfg(test)]
+pub(crate) fn sample_page() -> Page {
+    use ocr_model::{BoundingBox, Word};
+
+    let rect = |x0, x1| {
+        BoundingBox::from_rect(Rect {
+            x0,
+            y0: 20.0,
+            x1,
+            y1: 50.0,
+        })
+    };
+    let word = |text: &str, x0, x1| Word {
+        text: text.into(),
+        bbox: rect(x0, x1),
+        confidence: 0.9,
+    };
+    Page {
+        width: 300,
+        height: 100,
+        lines: vec![Line {
+            text: "5<&\"> end".into(),
+            bbox: rect(10.0, 210.0),
+            confidence: 0.9,
+            words: vec![word("5<&\">", 10.0, 110.0), word("end", 120.0, 210.0)],
+        }],
+        ..Default::default()
+    }
+}
+
+/// Assert that `parts` appear in `doc` in this order.
+#[cfg(test)]
+pub(crate) fn assert_in_order(doc: &str, parts: &[&str]) {
+    let mut rest = doc;
+    for part in parts {
+        let Some(at) = rest.find(part) else {
+            panic!("{part:?} missing or out of order in:\n{doc}");
+        };
+        rest = &rest[at + part.len()..];
+    }
+}
@@ -0,0 +1,765 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Searchable PDF export.
+//!
+//! Each page shows the original image with an invisible text layer
+//! (render mode 3) on top. Every word is placed on its quad with the
+//! baseline rotated to match and horizontally scaled to the box width,
+//! so selection and search line up with the picture in any viewer.
+//!
+//! The text is set in a glyphless font, as OCR layers usually are: a
+//! Type0 font with Identity-H encoding whose embedded TrueType has no
+//! outlines. Text is written as UTF-16BE code units, one per glyph, and
+//! a `/ToUnicode` CMap maps each code back to itself, so every script
+//! extracts as it was recognized.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use ocr_model::{BoundingBox, Page, Point};
+
+use crate::{blocks, SourcePage};
+
+/// Options for [`write_pdf`].
+#[derive(Debug, Clone)]
+pub struct PdfOptions {
+    /// Resolution used to turn image pixels into PDF points.
+    pub dpi: f64,
+    /// Document title stored in the info dictionary.
+    pub title: Option<String>,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            dpi: 96.0,
+            title: None,
+        }
+    }
+}
+
+/// Fraction of the box height below the baseline.
+const DESCENT: f64 = 0.2;
+
+/// Advance width of every glyph of the text font, in 1/1000 em.
+const GLYPH_WIDTH: u16 = 500;
+
+/// Objects written once per file.
+const CATALOG: usize = 1;
+const PAGE_TREE: usize = 2;
+const FONT: usize = 3;
+const INFO: usize = 4;
+const CID_FONT: usize = 5;
+const FONT_DESCRIPTOR: usize = 6;
+const FONT_FILE: usize = 7;
+const TO_UNICODE: usize = 8;
+const CID_TO_GID: usize = 9;
+
+/// Write a searchable PDF with one page per entry to `out`.
+pub fn write_pdf<W: Write>(pages: &[SourcePage<'_>], options: &PdfOptions, out: W) -> Result<()> {
+    let mut pdf = PdfWriter::new(out);
+    pdf.header()?;
+
+    // Each page is followed by its content stream and image.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| CID_TO_GID + 1 + i * 3).collect();
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    pdf.object(
+        CATALOG,
+        format!("<< /Type /Catalog /Pages {PAGE_TREE} 0 R >>").as_bytes(),
+    )?;
+    pdf.object(
+        PAGE_TREE,
+        format!("<< /Type /Pages /Kids [{kids}] /Count {} >>", pages.len()).as_bytes(),
+    )?;
+    let mut info = String::from("<< /Producer (ocrmyimg)");
+    if let Some(title) = &options.title {
+        info.push_str(" /Title ");
+        info.push_str(&text_string(title));
+    }
+    info.push_str(" >>");
+    pdf.object(INFO, info.as_bytes())?;
+    write_font(&mut pdf)?;
+
+    for (page, &page_id) in pages.iter().zip(&page_ids) {
+        let image = PdfImage::load(page.image)?;
+        let scale = 72.0 / options.dpi;
+        let width = f64::from(image.width) * scale;
+        let height = f64::from(image.height) * scale;
+
+        let content = page_content(page.ocr, &image, width, height, scale);
+        let content = deflate(content.as_bytes())?;
+
+        pdf.object(
+            page_id,
+            format!(
+                "<< /Type /Page /Parent {PAGE_TREE} 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 {FONT} 0 R >> /XObject << /Im0 {} 0 R >> >> \
+                 /Contents {} 0 R >>",
+                num(width),
+                num(height),
+                page_id + 2,
+                page_id + 1
+            )
+            .as_bytes(),
+        )?;
+        pdf.stream(page_id + 1, "/Filter /FlateDecode", &content)?;
+        pdf.stream(
+            page_id + 2,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace {} /BitsPerComponent 8 /Filter {}",
+                image.width, image.height, image.color_space, image.filter
+            ),
+            &image.data,
+        )?;
+    }
+
+    pdf.finish(CID_TO_GID + pages.len() * 3)
+}
+
+/// Write the glyphless text font and the objects it refers to.
+fn write_font<W: Write>(pdf: &mut PdfWriter<W>) -> Result<()> {
+    pdf.object(
+        FONT,
+        format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /GlyphLessFont /Encoding /Identity-H \
+             /DescendantFonts [{CID_FONT} 0 R] /ToUnicode {TO_UNICODE} 0 R >>"
+        )
+        .as_bytes(),
+    )?;
+    pdf.object(
+        CID_FONT,
+        format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /GlyphLessFont \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             /FontDescriptor {FONT_DESCRIPTOR} 0 R /DW {GLYPH_WIDTH} \
+             /CIDToGIDMap {CID_TO_GID} 0 R >>"
+        )
+        .as_bytes(),
+    )?;
+    pdf.object(
+        FONT_DESCRIPTOR,
+        format!(
+            "<< /Type /FontDescriptor /FontName /GlyphLessFont /Flags 5 \
+             /FontBBox [0 -200 {GLYPH_WIDTH} 800] /ItalicAngle 0 /Ascent 800 /Descent -200 \
+             /CapHeight 800 /StemV 80 /FontFile2 {FONT_FILE} 0 R >>"
+        )
+        .as_bytes(),
+    )?;
+    let font = glyphless_font();
+    pdf.stream(
+        FONT_FILE,
+        &format!("/Length1 {} /Filter /FlateDecode", font.len()),
+        &deflate(&font)?,
+    )?;
+    pdf.stream(
+        TO_UNICODE,
+        "/Filter /FlateDecode",
+        &deflate(to_unicode().as_bytes())?,
+    )?;
+    // Every code is drawn with the one blank glyph.
+    let gids: Vec<u8> = [0, 1].repeat(0x10000);
+    pdf.stream(CID_TO_GID, "/Filter /FlateDecode", &deflate(&gids)?)
+}
+
+/// A TrueType font with a blank `.notdef` and one blank glyph, both
+/// [`GLYPH_WIDTH`] wide: only the tables PDF needs of an embedded font.
+fn glyphless_font() -> Vec<u8> {
+    fn be(out: &mut Vec<u8>, values: &[u16]) {
+        for v in values {
+            out.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+    fn checksum(data: &[u8]) -> u32 {
+        data.chunks(4).fold(0u32, |sum, chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            sum.wrapping_add(u32::from_be_bytes(word))
+        })
+    }
+
+    // Version 1.0, revision 1.0, checksum adjustment (filled in last),
+    // magic, flags, 1000 units per em, created and modified, the
+    // bounding box, style, smallest size, direction, short offsets and
+    // glyph format.
+    let mut head = Vec::new();
+    be(&mut head, &[1, 0, 1, 0, 0, 0, 0x5f0f, 0x3cf5, 0x000b, 1000]);
+    head.extend_from_slice(&[0; 16]);
+    be(
+        &mut head,
+        &[0, (-200i16) as u16, GLYPH_WIDTH, 800, 0, 8, 2, 0, 0],
+    );
+    // Ascender, descender, line gap, widest advance, zero bearings and
+    // extent, an upright caret, four reserved fields, metric format and
+    // two horizontal metrics.
+    let mut hhea = Vec::new();
+    be(&mut hhea, &[1, 0, 800, (-200i16) as u16, 0, GLYPH_WIDTH]);
+    be(&mut hhea, &[0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2]);
+    // Version 1.0 limits for two glyphs without outlines.
+    let mut maxp = Vec::new();
+    be(&mut maxp, &[1, 0, 2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let mut hmtx = Vec::new();
+    be(&mut hmtx, &[GLYPH_WIDTH, 0, GLYPH_WIDTH, 0]);
+    // Both glyphs are empty, so every offset is zero.
+    let loca = vec![0; 6];
+
+    // Sorted by tag, as the table directory must be.
+    let tables: [(&[u8; 4], Vec<u8>); 6] = [
+        (b"glyf", Vec::new()),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"loca", loca),
+        (b"maxp", maxp),
+    ];
+    let mut font = Vec::new();
+    // Search parameters for six tables: 4 * 16, log2(4), 6 * 16 - 64.
+    be(&mut font, &[1, 0, tables.len() as u16, 64, 2, 32]);
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in &tables {
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    let mut head_at = 0;
+    for (tag, data) in &tables {
+        if *tag == b"head" {
+            head_at = font.len();
+        }
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    let adjustment = 0xb1b0_afbau32.wrapping_sub(checksum(&font));
+    font[head_at + 8..head_at + 12].copy_from_slice(&adjustment.to_be_bytes());
+    font
+}
+
+/// CMap mapping each two-byte code to the UTF-16 code unit it is.
+fn to_unicode() -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n\
+         12 dict begin\n\
+         begincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n\
+         /CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    // A range may only vary in its last byte, and a section holds at
+    // most 100 of them.
+    let ranges: Vec<u16> = (0..=0xffu16).map(|high| high << 8).collect();
+    for section in ranges.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfrange", section.len());
+        for &start in section {
+            let _ = writeln!(cmap, "<{start:04X}> <{:04X}> <{start:04X}>", start | 0xff);
+        }
+        cmap.push_str("endbfrange\n");
+    }
+    cmap.push_str(
+        "endcmap\n\
+         CMapName currentdict /CMap defineresource pop\n\
+         end\n\
+         end\n",
+    );
+    cmap
+}
+
+/// Write a searchable PDF to a file.
+pub fn save_pdf(pages: &[SourcePage<'_>], options: &PdfOptions, path: &Path) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    write_pdf(pages, options, std::io::BufWriter::new(file))
+}
+
+/// Build the content stream: the image, then the invisible text.
+fn page_content(ocr: &Page, image: &PdfImage, width: f64, height: f64, scale: f64) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "q {} 0 0 {} 0 0 cm /Im0 Do Q", num(width), num(height));
+
+    // OCR coordinates may refer to a differently sized copy of the image.
+    let sx = if ocr.width > 0 {
+        f64::from(image.width) / f64::from(ocr.width)
+    } else {
+        1.0
+    };
+    let sy = if ocr.height > 0 {
+        f64::from(image.height) / f64::from(ocr.height)
+    } else {
+        1.0
+    };
+
+    let placer = TextPlacer {
+        sx,
+        sy,
+        scale,
+        page_height: height,
+    };
+    // Emit text in reading order so extraction and copy follow the
+    // layout rather than detector order.
+    out.push_str("BT 3 Tr\n");
+    for line in blocks(ocr).iter().flat_map(|b| b.lines.iter().copied()) {
+        if line.words.is_empty() {
+            placer.place(&mut out, &line.text, &line.bbox, false);
+        } else {
+            let last = line.words.len() - 1;
+            for (i, word) in line.words.iter().enumerate() {
+                placer.place(&mut out, &word.text, &word.bbox, i < last);
+            }
+        }
+    }
+    out.push_str("ET\n");
+    out
+}
+
+/// Maps OCR boxes onto the page and emits fitted text runs.
+struct TextPlacer {
+    sx: f64,
+    sy: f64,
+    scale: f64,
+    page_height: f64,
+}
+
+impl TextPlacer {
+    /// Emit one run of text fitted to `bbox`.
+    ///
+    /// With `space` set, a trailing space is appended after the fitted
+    /// text so extractors see a word break.
+    fn place(&self, out: &mut String, text: &str, bbox: &BoundingBox, space: bool) {
+        let mut units: Vec<u16> = text.encode_utf16().collect();
+        if units.is_empty() {
+            return;
+        }
+
+        // Image space (y down, pixels) to PDF space (y up, points).
+        let quad = bbox.map(|p| {
+            Point(
+                p.0 * self.sx * self.scale,
+                self.page_height - p.1 * self.sy * self.scale,
+            )
+        });
+        let font_size = quad.height();
+        if font_size <= 0.0 {
+            return;
+        }
+
+        // The quad's angle is measured in flipped space, so it is already
+        // the counter-clockwise PDF rotation.
+        let (sin, cos) = quad.angle().sin_cos();
+        let origin = quad.bottom_left();
+        let descent = font_size * DESCENT;
+        let x = origin.0 - sin * descent;
+        let y = origin.1 + cos * descent;
+
+        let natural = units.len() as f64 * f64::from(GLYPH_WIDTH) * font_size / 1000.0;
+        let stretch = if natural > 0.0 {
+            100.0 * quad.width() / natural
+        } else {
+            100.0
+        };
+
+        if space {
+            units.push(u16::from(b' '));
+        }
+        let _ = writeln!(
+            out,
+            "/F1 {} Tf {} Tz {} {} {} {} {} {} Tm <{}> Tj",
+            num(font_size),
+            num(stretch),
+            num(cos),
+            num(sin),
+            num(-sin),
+            num(cos),
+            num(x),
+            num(y),
+            hex(&units)
+        );
+    }
+}
+
+/// UTF-16 code units as the digits of a hex string.
+fn hex(units: &[u16]) -> String {
+    units.iter().fold(String::new(), |mut out, unit| {
+        let _ = write!(out, "{unit:04X}");
+        out
+    })
+}
+
+/// A PDF text string: a literal for printable ASCII, otherwise UTF-16BE
+/// behind a byte order mark.
+fn text_string(text: &str) -> String {
+    if !text.bytes().all(|b| (0x20..=0x7e).contains(&b)) {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        return format!("<FEFF{}>", hex(&units));
+    }
+    let mut out = String::from("(");
+    for c in text.chars() {
+        if matches!(c, '(' | ')' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push(')');
+    out
+}
+
+/// Format a number compactly with at most four decimals.
+fn num(value: f64) -> String {
+    let s = format!("{value:.4}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".into()
+    } else {
+        s.into()
+    }
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Image data ready to embed as an XObject.
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    filter: &'static str,
+    data: Vec<u8>,
+}
+
+impl PdfImage {
+    /// Load an image, passing baseline JPEGs through untouched.
+    fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        if let Some((width, height, components)) = jpeg_info(&bytes) {
+            let color_space = match components {
+                1 => Some("/DeviceGray"),
+                3 => Some("/DeviceRGB"),
+                _ => None,
+            };
+            if let Some(color_space) = color_space {
+                return Ok(Self {
+                    width,
+                    height,
+                    color_space,
+                    filter: "/DCTDecode",
+                    data: bytes,
+                });
+            }
+        }
+
+        let image = image::load_from_memory(&bytes)
+            .with_context(|| format!("failed to decode {}", path.display()))?;
+        let (color_space, raw) = if image.color().has_color() {
+            ("/DeviceRGB", image.to_rgb8().into_raw())
+        } else {
+            ("/DeviceGray", image.to_luma8().into_raw())
+        };
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            color_space,
+            filter: "/FlateDecode",
+            data: deflate(&raw)?,
+        })
+    }
+}
+
+/// Read size and component count from a JPEG's SOF marker.
+fn jpeg_info(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    if bytes.get(..2)? != [0xff, 0xd8] {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= bytes.len() {
+        if bytes[i] != 0xff {
+            return None;
+        }
+        let marker = bytes[i + 1];
+        if marker == 0xff {
+            i += 1;
+            continue;
+        }
+        let len = usize::from(u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]));
+        let is_sof = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+        if is_sof {
+            let sof = bytes.get(i + 4..i + 10)?;
+            let height = u32::from(u16::from_be_bytes([sof[1], sof[2]]));
+            let width = u32::from(u16::from_be_bytes([sof[3], sof[4]]));
+            return Some((width, height, sof[5]));
+        }
+        i += 2 + len;
+    }
+    None
+}
+
+/// Minimal PDF object writer that tracks offsets for the xref table.
+struct PdfWriter<W: Write> {
+    out: W,
+    offset: usize,
+    offsets: Vec<(usize, usize)>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            offset: 0,
+            offsets: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.out.write_all(data)?;
+        self.offset += data.len();
+        Ok(())
+    }
+
+    fn header(&mut self) -> Result<()> {
+        self.write(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")
+    }
+
+    fn object(&mut self, id: usize, body: &[u8]) -> Result<()> {
+        self.offsets.push((id, self.offset));
+        self.write(format!("{id} 0 obj\n").as_bytes())?;
+        self.write(body)?;
+        self.write(b"\nendobj\n")
+    }
+
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) -> Result<()> {
+        self.offsets.push((id, self.offset));
+        self.write(
+            format!("{id} 0 obj\n<< {dict} /Length {} >>\nstream\n", data.len()).as_bytes(),
+        )?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    fn finish(mut self, count: usize) -> Result<()> {
+        self.offsets.sort_unstable();
+        let xref = self.offset;
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", count + 1);
+        for (_, offset) in &self.offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            count + 1
+        );
+        self.write(table.as_bytes())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lopdf::content::Content;
+    use lopdf::{Document, Object};
+    use ocr_model::Line;
+
+    use super::*;
+
+    /// A blank page image in a fresh directory of its own.
+    fn image(test: &str, width: u32, height: u32) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ocrmyimg-pdf-test-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("page.png");
+        image::GrayImage::new(width, height).save(&path).unwrap();
+        path
+    }
+
+    fn line(text: &str, bbox: BoundingBox) -> Line {
+        Line {
+            text: text.into(),
+            bbox,
+            confidence: 0.9,
+            words: Vec::new(),
+        }
+    }
+
+    fn page(width: u32, height: u32, lines: Vec<Line>) -> Page {
+        Page {
+            width,
+            height,
+            lines,
+            ..Default::default()
+        }
+    }
+
+    fn render(pages: &[SourcePage<'_>], options: &PdfOptions) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_pdf(pages, options, &mut out).unwrap();
+        out
+    }
+
+    /// Operands of every `op` in the first page's content stream.
+    fn operands(doc: &Document, op: &str) -> Vec<Vec<Object>> {
+        let page = *doc.get_pages().values().next().unwrap();
+        let content = Content::decode(&doc.get_page_content(page).unwrap()).unwrap();
+        content
+            .operations
+            .into_iter()
+            .filter(|o| o.operator == op)
+            .map(|o| o.operands)
+            .collect()
+    }
+
+    fn number(object: &Object) -> f64 {
+        match *object {
+            Object::Integer(i) => i as f64,
+            Object::Real(r) => f64::from(r),
+            _ => panic!("not a number: {object:?}"),
+        }
+    }
+
+    #[test]
+    fn xref_points_at_every_object() {
+        let path = image("xref_points_at_every_object", 40, 20);
+        let ocr = page(40, 20, Vec::new());
+        let source = SourcePage {
+            image: &path,
+            ocr: &ocr,
+        };
+        let pdf = render(&[source, source, source], &PdfOptions::default());
+
+        // The trailer is ASCII, unlike the binary streams before it.
+        let tail = pdf.len() - 64;
+        let trailer = std::str::from_utf8(&pdf[tail..]).unwrap();
+        let startxref: usize = trailer
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|rest| rest.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let table = std::str::from_utf8(&pdf[startxref..]).unwrap();
+        assert!(table.starts_with("xref\n0 19\n"));
+        let entries = table.lines().skip(3).take(18);
+        for (id, entry) in (1..).zip(entries) {
+            let offset: usize = entry[..10].parse().unwrap();
+            let object = format!("{id} 0 obj\n");
+            assert_eq!(&pdf[offset..offset + object.len()], object.as_bytes());
+        }
+
+        let doc = Document::load_mem(&pdf).unwrap();
+        assert_eq!(doc.get_pages().len(), 3);
+    }
+
+    #[test]
+    fn escapes_the_title() {
+        let path = image("escapes_the_title", 40, 20);
+        let ocr = page(40, 20, Vec::new());
+        let source = SourcePage {
+            image: &path,
+            ocr: &ocr,
+        };
+        for (title, expected) in [
+            ("Scan (1) \\ draft", b"Scan (1) \\ draft".to_vec()),
+            (
+                "مسح",
+                [0xfe, 0xff, 0x06, 0x45, 0x06, 0x33, 0x06, 0x2d].to_vec(),
+            ),
+        ] {
+            let options = PdfOptions {
+                title: Some(title.into()),
+                ..Default::default()
+            };
+            let doc = Document::load_mem(&render(&[source], &options)).unwrap();
+            let info = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+            let info = doc.get_dictionary(info).unwrap();
+            assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn places_a_rotated_line_on_its_quad() {
+        let path = image("places_a_rotated_line_on_its_quad", 200, 200);
+        // Reading bottom to top: the baseline is the right edge.
+        let quad = BoundingBox([
+            Point(100.0, 150.0),
+            Point(100.0, 50.0),
+            Point(120.0, 50.0),
+            Point(120.0, 150.0),
+        ]);
+        let ocr = page(200, 200, vec![line("مرحبا (1)", quad)]);
+        let source = SourcePage {
+            image: &path,
+            ocr: &ocr,
+        };
+        // 72 dpi, so a pixel is a point.
+        let options = PdfOptions {
+            dpi: 72.0,
+            title: None,
+        };
+        let doc = Document::load_mem(&render(&[source], &options)).unwrap();
+
+        let tm: Vec<f64> = operands(&doc, "Tm")[0].iter().map(number).collect();
+        // Rotated a quarter turn counter-clockwise. The quad's bottom
+        // edge is at x = 120 and up is towards -x, so the baseline is the
+        // descent, a fifth of the 20 pt height, further left.
+        let expected = [0.0, 1.0, -1.0, 0.0, 116.0, 50.0];
+        for (got, want) in tm.iter().zip(expected) {
+            assert!((got - want).abs() < 1e-3, "Tm {tm:?}");
+        }
+        let tf = &operands(&doc, "Tf")[0];
+        assert_eq!(number(&tf[1]), 20.0);
+
+        let tj = &operands(&doc, "Tj")[0];
+        let units: Vec<u16> = "مرحبا (1)".encode_utf16().collect();
+        let bytes: Vec<u8> = units.iter().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(tj[0].as_str().unwrap(), bytes);
+
+        // The font maps those codes back to the text.
+        let font = doc.get_object((FONT as u32, 0)).unwrap().as_dict().unwrap();
+        assert_eq!(
+            font.get(b"Encoding").unwrap().as_name().unwrap(),
+            b"Identity-H"
+        );
+        let cmap = doc
+            .get_object(font.get(b"ToUnicode").unwrap().as_reference().unwrap())
+            .unwrap()
+            .as_stream()
+            .unwrap()
+            .decompressed_content()
+            .unwrap();
+        let cmap = String::from_utf8(cmap).unwrap();
+        assert!(cmap.contains("<0600> <06FF> <0600>"));
+    }
+
+    #[test]
+    fn builds_a_consistent_font() {
+        let font = glyphless_font();
+        // The whole file sums to the magic constant.
+        let sum = font.chunks(4).fold(0u32, |sum, word| {
+            sum.wrapping_add(u32::from_be_bytes(word.try_into().unwrap()))
+        });
+        assert_eq!(sum, 0xb1b0_afba);
+        let tables = u16::from_be_bytes([font[4], font[5]]);
+        for record in font[12..12 + 16 * usize::from(tables)].chunks(16) {
+            let offset = u32::from_be_bytes(record[8..12].try_into().unwrap()) as usize;
+            let len = u32::from_be_bytes(record[12..16].try_into().unwrap()) as usize;
+            assert!(offset.is_multiple_of(4) && offset + len <= font.len());
+        }
+    }
+}
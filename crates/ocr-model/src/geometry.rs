@@ -0,0 +1,191 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Geometry primitives in image pixel coordinates.
+//!
+//! The origin is the top-left corner of the image, x grows to the
+//! right and y grows downwards.
+
+use serde::{Deserialize, Serialize};
+
+/// A point in image pixels. Serialized as `[x, y]`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Point(pub f64, pub f64);
+
+impl Point {
+    pub fn x(&self) -> f64 {
+        self.0
+    }
+
+    pub fn y(&self) -> f64 {
+        self.1
+    }
+
+    /// Linear interpolation towards `other` by `t` in `[0, 1]`.
+    pub fn lerp(&self, other: Point, t: f64) -> Point {
+        Point(
+            self.0 + (other.0 - self.0) * t,
+            self.1 + (other.1 - self.1) * t,
+        )
+    }
+
+    /// Euclidean distance to `other`.
+    pub fn distance(&self, other: Point) -> f64 {
+        (other.0 - self.0).hypot(other.1 - self.1)
+    }
+}
+
+/// An axis-aligned rectangle given by its minimum and maximum corners.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Rect {
+    pub fn width(&self) -> f64 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> f64 {
+        self.y1 - self.y0
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width().max(0.0) * self.height().max(0.0)
+    }
+
+    /// Smallest rectangle containing both `self` and `other`.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    /// Overlapping region, if any.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let rect = Rect {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+        };
+        (rect.x0 < rect.x1 && rect.y0 < rect.y1).then_some(rect)
+    }
+
+    /// Intersection over union, in `[0, 1]`.
+    pub fn iou(&self, other: &Rect) -> f64 {
+        let inter = self.intersection(other).map_or(0.0, |r| r.area());
+        let union = self.area() + other.area() - inter;
+        if union > 0.0 {
+            inter / union
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A quadrilateral around detected text.
+///
+/// Corners are in clockwise order starting from the top-left, exactly as
+/// PaddleOCR reports them. Serialized as `[[x, y], [x, y], [x, y], [x, y]]`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct BoundingBox(pub [Point; 4]);
+
+impl BoundingBox {
+    /// Build an axis-aligned box from a rectangle.
+    pub fn from_rect(rect: Rect) -> Self {
+        Self([
+            Point(rect.x0, rect.y0),
+            Point(rect.x1, rect.y0),
+            Point(rect.x1, rect.y1),
+            Point(rect.x0, rect.y1),
+        ])
+    }
+
+    pub fn top_left(&self) -> Point {
+        self.0[0]
+    }
+
+    pub fn top_right(&self) -> Point {
+        self.0[1]
+    }
+
+    pub fn bottom_right(&self) -> Point {
+        self.0[2]
+    }
+
+    pub fn bottom_left(&self) -> Point {
+        self.0[3]
+    }
+
+    /// Axis-aligned bounds of the quad.
+    pub fn bounds(&self) -> Rect {
+        let mut rect = Rect {
+            x0: f64::INFINITY,
+            y0: f64::INFINITY,
+            x1: f64::NEG_INFINITY,
+            y1: f64::NEG_INFINITY,
+        };
+        for p in &self.0 {
+            rect.x0 = rect.x0.min(p.0);
+            rect.y0 = rect.y0.min(p.1);
+            rect.x1 = rect.x1.max(p.0);
+            rect.y1 = rect.y1.max(p.1);
+        }
+        rect
+    }
+
+    /// Mean of the four corners.
+    pub fn center(&self) -> Point {
+        let (x, y) = self
+            .0
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.0, y + p.1));
+        Point(x / 4.0, y / 4.0)
+    }
+
+    /// Length along the text baseline (mean of top and bottom edges).
+    pub fn width(&self) -> f64 {
+        (self.top_left().distance(self.top_right())
+            + self.bottom_left().distance(self.bottom_right()))
+            / 2.0
+    }
+
+    /// Extent across the text (mean of left and right edges).
+    pub fn height(&self) -> f64 {
+        (self.top_left().distance(self.bottom_left())
+            + self.top_right().distance(self.bottom_right()))
+            / 2.0
+    }
+
+    /// Rotation of the baseline in radians, clockwise in image space.
+    pub fn angle(&self) -> f64 {
+        let (tl, tr) = (self.top_left(), self.top_right());
+        let (bl, br) = (self.bottom_left(), self.bottom_right());
+        let dx = (tr.0 - tl.0) + (br.0 - bl.0);
+        let dy = (tr.1 - tl.1) + (br.1 - bl.1);
+        dy.atan2(dx)
+    }
+
+    /// The sub-quad spanning fractions `start..end` along the baseline.
+    pub fn slice(&self, start: f64, end: f64) -> BoundingBox {
+        let [tl, tr, br, bl] = self.0;
+        BoundingBox([
+            tl.lerp(tr, start),
+            tl.lerp(tr, end),
+            bl.lerp(br, end),
+            bl.lerp(br, start),
+        ])
+    }
+
+    /// Apply `f` to every corner.
+    pub fn map(&self, mut f: impl FnMut(Point) -> Point) -> BoundingBox {
+        BoundingBox(self.0.map(&mut f))
+    }
+}
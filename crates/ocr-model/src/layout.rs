@@ -0,0 +1,441 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reading-order reconstruction.
+//!
+//! The engine reports lines in detector order, which interleaves columns
+//! on multi-column pages and sidebars on screenshots. [`analyze`] works
+//! on the line quads instead: fragments are merged into rows, rows into
+//! blocks, and blocks are ordered the way a left-to-right reader would
+//! go through them (down a column, then on to the next one).
+//!
+//! All geometry is measured after undoing the page's dominant text
+//! rotation, so slightly skewed scans group the same way straight ones
+//! do.
+
+use std::cmp::Ordering;
+
+use crate::geometry::{Point, Rect};
+use crate::result::Line;
+
+/// Fragments share a row when they overlap vertically by at least this
+/// fraction of the smaller height.
+const ROW_OVERLAP: f64 = 0.5;
+
+/// Widest horizontal gap between fragments of one row, in line heights.
+/// Anything wider is treated as a column gutter.
+const ROW_GAP: f64 = 1.0;
+
+/// Widest vertical gap between consecutive rows of one block, in line
+/// heights.
+const BLOCK_GAP: f64 = 1.0;
+
+/// Largest ratio between the heights of consecutive rows of one block.
+/// Headings next to body text start a new block.
+const BLOCK_HEIGHT_RATIO: f64 = 1.6;
+
+/// A group of rows read together, such as a paragraph or a caption.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// Axis-aligned bounds of the block's lines in page coordinates.
+    pub bounds: Rect,
+    /// Rows from top to bottom. Each row lists indices into the analyzed
+    /// lines from left to right.
+    pub rows: Vec<Vec<usize>>,
+}
+
+impl Block {
+    /// Indices of the block's lines in reading order.
+    pub fn lines(&self) -> impl Iterator<Item = usize> + '_ {
+        self.rows.iter().flatten().copied()
+    }
+}
+
+/// Group `lines` into blocks and return them in reading order.
+///
+/// Every line index appears in exactly one block.
+pub fn analyze(lines: &[Line]) -> Vec<Block> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+
+    let angle = dominant_angle(lines);
+    let (sin, cos) = (-angle).sin_cos();
+    let unrotate = |p: Point| Point(p.0 * cos - p.1 * sin, p.0 * sin + p.1 * cos);
+
+    let fragments: Vec<Fragment> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| Fragment {
+            index,
+            rect: line.bbox.map(unrotate).bounds(),
+            height: line.bbox.height().max(1.0),
+        })
+        .collect();
+
+    let rows = group_rows(&fragments);
+    let groups = group_blocks(rows);
+    let order = reading_order(&groups);
+
+    order
+        .into_iter()
+        .map(|g| {
+            let rows: Vec<Vec<usize>> = groups[g].rows.iter().map(|r| r.members.clone()).collect();
+            let bounds = rows
+                .iter()
+                .flatten()
+                .map(|&i| lines[i].bbox.bounds())
+                .reduce(|a, b| a.union(&b))
+                .expect("blocks are never empty");
+            Block { bounds, rows }
+        })
+        .collect()
+}
+
+/// A line's bounds in unrotated space.
+struct Fragment {
+    index: usize,
+    rect: Rect,
+    height: f64,
+}
+
+struct Row {
+    rect: Rect,
+    height: f64,
+    members: Vec<usize>,
+}
+
+struct Group {
+    rect: Rect,
+    rows: Vec<Row>,
+}
+
+/// Median baseline angle of lines that are wider than tall.
+fn dominant_angle(lines: &[Line]) -> f64 {
+    let mut angles: Vec<f64> = lines
+        .iter()
+        .filter(|l| l.bbox.width() > l.bbox.height())
+        .map(|l| l.bbox.angle())
+        .collect();
+    if angles.is_empty() {
+        return 0.0;
+    }
+    angles.sort_by(f64::total_cmp);
+    angles[angles.len() / 2]
+}
+
+/// Merge fragments that sit side by side on the same text row.
+fn group_rows(fragments: &[Fragment]) -> Vec<Row> {
+    let mut sorted: Vec<&Fragment> = fragments.iter().collect();
+    sorted.sort_by(|a, b| by_position(&a.rect, &b.rect));
+
+    let mut rows: Vec<Row> = Vec::new();
+    for f in sorted {
+        let best = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                let limit = f.height.min(row.height);
+                overlap(row.rect.y0, row.rect.y1, f.rect.y0, f.rect.y1) >= ROW_OVERLAP * limit
+                    && gap(row.rect.x0, row.rect.x1, f.rect.x0, f.rect.x1)
+                        <= ROW_GAP * f.height.max(row.height)
+            })
+            .min_by(|(_, a), (_, b)| {
+                let ga = gap(a.rect.x0, a.rect.x1, f.rect.x0, f.rect.x1);
+                let gb = gap(b.rect.x0, b.rect.x1, f.rect.x0, f.rect.x1);
+                ga.total_cmp(&gb)
+            })
+            .map(|(i, _)| i);
+
+        match best {
+            Some(i) => {
+                let row = &mut rows[i];
+                row.rect = row.rect.union(&f.rect);
+                row.height = row.height.max(f.height);
+                row.members.push(f.index);
+            }
+            None => rows.push(Row {
+                rect: f.rect,
+                height: f.height,
+                members: vec![f.index],
+            }),
+        }
+    }
+
+    for row in &mut rows {
+        row.members
+            .sort_by(|&a, &b| fragments[a].rect.x0.total_cmp(&fragments[b].rect.x0));
+    }
+    rows
+}
+
+/// Stack rows that continue each other into blocks.
+fn group_blocks(mut rows: Vec<Row>) -> Vec<Group> {
+    rows.sort_by(|a, b| by_position(&a.rect, &b.rect));
+
+    let mut groups: Vec<Group> = Vec::new();
+    for row in rows {
+        let best = groups
+            .iter()
+            .enumerate()
+            .filter_map(|(i, group)| {
+                let last = group.rows.last()?;
+                let dy = row.rect.y0 - last.rect.y1;
+                let (lo, hi) = if row.height < last.height {
+                    (row.height, last.height)
+                } else {
+                    (last.height, row.height)
+                };
+                let fits = dy <= BLOCK_GAP * hi
+                    && dy >= -ROW_OVERLAP * lo
+                    && hi <= BLOCK_HEIGHT_RATIO * lo
+                    && overlap(last.rect.x0, last.rect.x1, row.rect.x0, row.rect.x1) > 0.0;
+                fits.then_some((i, dy))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+
+        match best {
+            Some(i) => {
+                let group = &mut groups[i];
+                group.rect = group.rect.union(&row.rect);
+                group.rows.push(row);
+            }
+            None => groups.push(Group {
+                rect: row.rect,
+                rows: vec![row],
+            }),
+        }
+    }
+    groups
+}
+
+/// Order blocks with a topological sort over two rules:
+///
+/// 1. a block comes before any block below it that it overlaps
+///    horizontally;
+/// 2. a block comes before any block entirely to its right, unless a
+///    third block lying vertically between them overlaps both, as a
+///    full-width heading separating two sets of columns does.
+///
+/// Ties, and any cycles skewed input might produce, fall back to top to
+/// bottom, left to right.
+fn reading_order(groups: &[Group]) -> Vec<usize> {
+    let n = groups.len();
+    let rects: Vec<Rect> = groups.iter().map(|g| g.rect).collect();
+    let center = |r: &Rect| (r.y0 + r.y1) / 2.0;
+    let x_overlap = |a: &Rect, b: &Rect| overlap(a.x0, a.x1, b.x0, b.x1) > 0.0;
+
+    // With `a` entirely left of `b`, a block overlaps both exactly when
+    // it starts left of `a`'s right edge and ends right of `b`'s left
+    // edge. So for each `a`, the right edges of the blocks starting left
+    // of it, ordered by center, answer every rule 2 check for `a` with
+    // one range maximum.
+    let mut by_center: Vec<usize> = (0..n).collect();
+    by_center.sort_by(|&a, &b| center(&rects[a]).total_cmp(&center(&rects[b])));
+    let centers: Vec<f64> = by_center.iter().map(|&c| center(&rects[c])).collect();
+
+    let mut before = vec![vec![false; n]; n];
+    for a in 0..n {
+        let ra = &rects[a];
+        let reach = RangeMax::new(by_center.iter().map(|&c| {
+            if c != a && rects[c].x0 < ra.x1 {
+                rects[c].x1
+            } else {
+                f64::NEG_INFINITY
+            }
+        }));
+        for b in 0..n {
+            if a == b {
+                continue;
+            }
+            let rb = &rects[b];
+            before[a][b] = if x_overlap(ra, rb) {
+                center(ra) < center(rb)
+            } else if ra.x1 <= rb.x0 {
+                let (lo, hi) = min_max(center(ra), center(rb));
+                let start = centers.partition_point(|&y| y <= lo);
+                let end = centers.partition_point(|&y| y < hi);
+                reach.max(start, end) <= rb.x0
+            } else {
+                false
+            };
+        }
+    }
+
+    let mut pending: Vec<usize> = (0..n)
+        .map(|b| (0..n).filter(|&a| before[a][b]).count())
+        .collect();
+    let mut done = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    while order.len() < n {
+        let candidates = (0..n).filter(|&i| !done[i]);
+        let next = candidates
+            .clone()
+            .filter(|&i| pending[i] == 0)
+            .min_by(|&a, &b| by_position(&rects[a], &rects[b]))
+            .or_else(|| candidates.min_by(|&a, &b| by_position(&rects[a], &rects[b])))
+            .expect("at least one block is left");
+
+        done[next] = true;
+        order.push(next);
+        for (b, count) in pending.iter_mut().enumerate() {
+            if before[next][b] && !done[b] {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+    order
+}
+
+/// Maximum over ranges of a fixed list, each answered in constant time
+/// from the maxima of every power-of-two long run.
+struct RangeMax {
+    levels: Vec<Vec<f64>>,
+}
+
+impl RangeMax {
+    fn new(values: impl Iterator<Item = f64>) -> Self {
+        let mut levels = vec![values.collect::<Vec<f64>>()];
+        let mut width = 1;
+        while width * 2 <= levels[0].len() {
+            let prev = levels.last().expect("starts with one level");
+            let next = (0..prev.len() - width)
+                .map(|i| prev[i].max(prev[i + width]))
+                .collect();
+            levels.push(next);
+            width *= 2;
+        }
+        Self { levels }
+    }
+
+    /// Largest value in `start..end`, or negative infinity when empty.
+    fn max(&self, start: usize, end: usize) -> f64 {
+        if start >= end {
+            return f64::NEG_INFINITY;
+        }
+        let level = (end - start).ilog2() as usize;
+        let row = &self.levels[level];
+        row[start].max(row[end - (1 << level)])
+    }
+}
+
+/// Top to bottom, then left to right.
+fn by_position(a: &Rect, b: &Rect) -> Ordering {
+    a.y0.total_cmp(&b.y0).then(a.x0.total_cmp(&b.x0))
+}
+
+/// Length of the overlap of two intervals; negative when they are apart.
+fn overlap(a0: f64, a1: f64, b0: f64, b1: f64) -> f64 {
+    a1.min(b1) - a0.max(b0)
+}
+
+/// Distance between two intervals; zero when they overlap.
+fn gap(a0: f64, a1: f64, b0: f64, b1: f64) -> f64 {
+    (-overlap(a0, a1, b0, b1)).max(0.0)
+}
+
+fn min_max(a: f64, b: f64) -> (f64, f64) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::BoundingBox;
+    use crate::result::Page;
+
+    use super::*;
+
+    fn line(text: &str, x0: f64, y0: f64, x1: f64, y1: f64) -> Line {
+        Line {
+            text: text.into(),
+            bbox: BoundingBox::from_rect(Rect { x0, y0, x1, y1 }),
+            confidence: 0.9,
+            words: Vec::new(),
+        }
+    }
+
+    /// Two columns of 30 px rows starting at `top`, listed the way a
+    /// detector interleaves them: row by row across the page.
+    fn columns(left: &str, right: &str, top: f64, rows: usize) -> Vec<Line> {
+        (0..rows)
+            .flat_map(|i| {
+                let y = top + i as f64 * 40.0;
+                [
+                    line(&format!("{left}{}", i + 1), 50.0, y, 450.0, y + 30.0),
+                    line(&format!("{right}{}", i + 1), 550.0, y, 950.0, y + 30.0),
+                ]
+            })
+            .collect()
+    }
+
+    fn text(lines: Vec<Line>) -> String {
+        Page {
+            width: 1000,
+            height: 1000,
+            lines,
+            ..Default::default()
+        }
+        .text()
+    }
+
+    #[test]
+    fn reads_down_each_column() {
+        assert_eq!(
+            text(columns("L", "R", 100.0, 3)),
+            "L1\nL2\nL3\n\nR1\nR2\nR3"
+        );
+    }
+
+    #[test]
+    fn reads_a_sidebar_before_the_content() {
+        // A screenshot whose content starts above the first sidebar item.
+        let mut lines = vec![
+            line("Inbox", 300.0, 40.0, 700.0, 80.0),
+            line("Home", 20.0, 100.0, 120.0, 124.0),
+            line("Your messages are up to date.", 300.0, 110.0, 1000.0, 134.0),
+            line("Sent", 20.0, 140.0, 120.0, 164.0),
+            line("Nothing new since yesterday.", 300.0, 144.0, 980.0, 168.0),
+            line("Settings", 20.0, 180.0, 140.0, 204.0),
+        ];
+        lines.reverse();
+        assert_eq!(
+            text(lines),
+            "Home\nSent\nSettings\n\n\
+             Inbox\n\n\
+             Your messages are up to date.\nNothing new since yesterday."
+        );
+    }
+
+    #[test]
+    fn full_width_headings_separate_column_sets() {
+        let mut lines = vec![line("Heading", 50.0, 20.0, 950.0, 70.0)];
+        lines.extend(columns("A", "B", 110.0, 2));
+        lines.push(line("Subheading", 50.0, 220.0, 950.0, 270.0));
+        lines.extend(columns("C", "D", 310.0, 2));
+        assert_eq!(
+            text(lines),
+            "Heading\n\nA1\nA2\n\nB1\nB2\n\nSubheading\n\nC1\nC2\n\nD1\nD2"
+        );
+    }
+
+    #[test]
+    fn groups_a_skewed_page_like_a_straight_one() {
+        // The two-column page turned 3° clockwise about the origin.
+        let (sin, cos) = 3f64.to_radians().sin_cos();
+        let lines = columns("L", "R", 100.0, 3)
+            .into_iter()
+            .map(|l| Line {
+                bbox: l
+                    .bbox
+                    .map(|p| Point(p.0 * cos - p.1 * sin, p.0 * sin + p.1 * cos)),
+                ..l
+            })
+            .collect();
+        assert_eq!(text(lines), "L1\nL2\nL3\n\nR1\nR2\nR3");
+    }
+}
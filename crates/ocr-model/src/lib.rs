@@ -0,0 +1,20 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shared OCR result model.
+//!
+//! Typed representation of what the OCR engine produces: quad bounding
+//! boxes, words and lines with confidences, and pages with their size
+//! and engine metadata. Also reconstructs reading order from the line
+//! geometry and merges results read tile by tile. Used by the app and
+//! the exporters.
+
+mod geometry;
+mod layout;
+mod merge;
+mod result;
+
+pub use geometry::{BoundingBox, Point, Rect};
+pub use layout::{analyze as analyze_layout, Block};
+pub use merge::{merge_tiles, text_similarity};
+pub use result::{Document, EngineInfo, Line, Page, Word};
@@ -0,0 +1,399 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Merging results of overlapping tiles into one page.
+//!
+//! Text in the overlap between two tiles is read twice, once by each,
+//! and a line cut by a tile edge comes back as a truncated copy. Lines
+//! from different tiles are duplicates when their boxes overlap and
+//! their texts agree: either similar boxes with similar text, or one box
+//! mostly covered by the other with its text found in the other's. The
+//! longest reading of each duplicate group is kept.
+//!
+//! A line too long to fit in the overlap is never seen whole; each tile
+//! returns the part on its side of the seam. Such fragments share a row
+//! and overlap where both tiles read the same stretch, and are joined
+//! back into one line.
+
+use crate::geometry::{BoundingBox, Point, Rect};
+use crate::result::{Line, Page};
+
+/// Boxes at least this similar are compared as whole lines.
+const IOU_DUPLICATE: f64 = 0.5;
+
+/// A box with at least this fraction of its area inside another is
+/// compared as a possibly truncated copy.
+const COVER_DUPLICATE: f64 = 0.7;
+
+/// Texts at least this similar are the same reading.
+const TEXT_DUPLICATE: f64 = 0.7;
+
+/// Fragments whose vertical extents overlap by at least this fraction
+/// of the shorter one are on the same row.
+const SAME_ROW: f64 = 0.6;
+
+/// Shortest stretch of text two fragments must both have read to be
+/// joined.
+const MIN_SHARED_CHARS: usize = 2;
+
+/// Combine the pages read from tiles of a `width` × `height` image.
+///
+/// Each tile comes with the position of its top-left corner on the
+/// image. Boxes are moved into image coordinates, duplicates found in
+/// the overlaps are dropped and fragments of lines cut by a seam are
+/// joined. Lines come out in no particular order.
+pub fn merge_tiles(tiles: Vec<(Point, Page)>, width: u32, height: u32) -> Page {
+    let engine = tiles
+        .first()
+        .map(|(_, page)| page.engine.clone())
+        .unwrap_or_default();
+
+    let mut candidates: Vec<(usize, Line)> = Vec::new();
+    for (tile, (offset, mut page)) in tiles.into_iter().enumerate() {
+        page.map_boxes(|p| Point(p.0 + offset.0, p.1 + offset.1));
+        candidates.extend(page.lines.into_iter().map(|line| (tile, line)));
+    }
+    // Longest and most confident readings first, so they win.
+    candidates.sort_by(|(_, a), (_, b)| {
+        let chars = |l: &Line| l.text.trim().chars().count();
+        chars(b)
+            .cmp(&chars(a))
+            .then(b.confidence.total_cmp(&a.confidence))
+    });
+
+    let mut kept: Vec<(usize, Rect, Line)> = Vec::new();
+    for (tile, line) in candidates {
+        let bounds = line.bbox.bounds();
+        let duplicate = kept.iter().any(|(other_tile, other_bounds, other)| {
+            *other_tile != tile && is_duplicate(&bounds, &line.text, other_bounds, &other.text)
+        });
+        if !duplicate {
+            kept.push((tile, bounds, line));
+        }
+    }
+
+    Page {
+        width,
+        height,
+        lines: join_fragments(kept),
+        engine,
+    }
+}
+
+/// Join lines from different tiles that continue each other across a
+/// seam. Lines are taken from left to right, so a line crossing several
+/// seams has grown from all fragments on its left by the time the next
+/// one is reached.
+fn join_fragments(mut lines: Vec<(usize, Rect, Line)>) -> Vec<Line> {
+    lines.sort_by(|(_, a, _), (_, b, _)| a.x0.total_cmp(&b.x0));
+    let mut joined: Vec<(Vec<usize>, Rect, Line)> = Vec::new();
+    for (tile, bounds, line) in lines {
+        let continued = joined
+            .iter()
+            .enumerate()
+            .find_map(|(i, (tiles, left, left_line))| {
+                let continues = left.x0 < bounds.x0
+                    && left.x1 > bounds.x0
+                    && left.x1 < bounds.x1
+                    && same_row(left, &bounds)
+                    && !tiles.contains(&tile);
+                continues
+                    .then(|| stitch(&left_line.text, &line.text))
+                    .flatten()
+                    .map(|text| (i, text))
+            });
+        match continued {
+            Some((i, text)) => {
+                let left = joined.swap_remove(i);
+                joined.push(join(left, (tile, bounds, line), text));
+            }
+            None => joined.push((vec![tile], bounds, line)),
+        }
+    }
+    joined.into_iter().map(|(_, _, line)| line).collect()
+}
+
+fn same_row(a: &Rect, b: &Rect) -> bool {
+    let overlap = a.y1.min(b.y1) - a.y0.max(b.y0);
+    overlap >= SAME_ROW * a.height().min(b.height())
+}
+
+/// One line with the `text` [`stitch`] made from a fragment and the
+/// fragment continuing it to the right. Words are split at the middle
+/// of the stretch both read.
+fn join(
+    (mut tiles, left_bounds, left): (Vec<usize>, Rect, Line),
+    (right_tile, right_bounds, right): (usize, Rect, Line),
+    text: String,
+) -> (Vec<usize>, Rect, Line) {
+    tiles.push(right_tile);
+    let seam = (left_bounds.x1 + right_bounds.x0) / 2.0;
+    let chars = |l: &Line| l.text.trim().chars().count() as f32;
+    let (left_chars, right_chars) = (chars(&left), chars(&right));
+    let confidence = if left_chars + right_chars > 0.0 {
+        (left.confidence * left_chars + right.confidence * right_chars) / (left_chars + right_chars)
+    } else {
+        left.confidence.min(right.confidence)
+    };
+
+    let line = Line {
+        text,
+        bbox: BoundingBox([
+            left.bbox.top_left(),
+            right.bbox.top_right(),
+            right.bbox.bottom_right(),
+            left.bbox.bottom_left(),
+        ]),
+        confidence,
+        words: (left.words.into_iter())
+            .filter(|w| w.bbox.center().0 < seam)
+            .chain(
+                right
+                    .words
+                    .into_iter()
+                    .filter(|w| w.bbox.center().0 >= seam),
+            )
+            .collect(),
+    };
+    (tiles, left_bounds.union(&right_bounds), line)
+}
+
+/// Join two readings of a line where the end of `left` and the start of
+/// `right` are the same stretch of text, at least [`MIN_SHARED_CHARS`]
+/// long. Returns `None` when there is no such stretch: the two are
+/// different lines that happen to overlap.
+fn stitch(left: &str, right: &str) -> Option<String> {
+    let (left, right) = (left.trim(), right.trim());
+    let a: Vec<char> = left.to_lowercase().chars().collect();
+    let b: Vec<char> = right.to_lowercase().chars().collect();
+    // Prefer the stretch with the most characters in agreement, so an
+    // exact match is not lost to a longer near-match.
+    let (_, shared) = (MIN_SHARED_CHARS..=a.len().min(b.len()))
+        .filter_map(|k| {
+            let distance = edit_distance(&a[a.len() - k..], &b[..k]);
+            (1.0 - distance as f64 / k as f64 >= TEXT_DUPLICATE).then(|| (k - distance, k))
+        })
+        .max()?;
+    let rest: String = right.chars().skip(shared).collect();
+    Some(format!("{left}{rest}"))
+}
+
+/// Whether a line is another reading of a line that is at least as
+/// long.
+fn is_duplicate(bounds: &Rect, text: &str, other_bounds: &Rect, other_text: &str) -> bool {
+    let Some(overlap) = bounds.intersection(other_bounds) else {
+        return false;
+    };
+    if bounds.iou(other_bounds) >= IOU_DUPLICATE
+        && text_similarity(text, other_text) >= TEXT_DUPLICATE
+    {
+        return true;
+    }
+    let area = bounds.area();
+    area > 0.0
+        && overlap.area() / area >= COVER_DUPLICATE
+        && partial_similarity(text, other_text) >= TEXT_DUPLICATE
+}
+
+/// Similarity of two strings in `[0, 1]`: one minus the edit distance
+/// over the length of the longer one. Whitespace at the ends and letter
+/// case are ignored.
+pub fn text_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.trim().to_lowercase().chars().collect();
+    let b: Vec<char> = b.trim().to_lowercase().chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+/// Best [`text_similarity`] of the shorter string against any stretch of
+/// the longer one of the same length, so a truncated reading matches the
+/// full one.
+fn partial_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.trim().to_lowercase().chars().collect();
+    let b: Vec<char> = b.trim().to_lowercase().chars().collect();
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if short.is_empty() {
+        return if long.is_empty() { 1.0 } else { 0.0 };
+    }
+    long.windows(short.len())
+        .map(|window| 1.0 - edit_distance(&short, window) as f64 / short.len() as f64)
+        .fold(0.0, f64::max)
+}
+
+/// Levenshtein distance between two character sequences.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::Word;
+
+    /// A line of 80 px wide words starting at the given image x, read by
+    /// a tile whose left edge is at `offset`.
+    fn line(words: &[(&str, f64)], offset: f64) -> Line {
+        let rect = |x0: f64, x1: f64| Rect {
+            x0: x0 - offset,
+            y0: 100.0,
+            x1: x1 - offset,
+            y1: 130.0,
+        };
+        let (first, last) = (words[0].1, words[words.len() - 1].1);
+        Line {
+            text: words.iter().map(|(w, _)| *w).collect::<Vec<_>>().join(" "),
+            bbox: BoundingBox::from_rect(rect(first, last + 80.0)),
+            confidence: 0.9,
+            words: words
+                .iter()
+                .map(|&(text, x)| Word {
+                    text: text.into(),
+                    bbox: BoundingBox::from_rect(rect(x, x + 80.0)),
+                    confidence: 0.9,
+                })
+                .collect(),
+        }
+    }
+
+    fn page(lines: Vec<Line>) -> Page {
+        Page {
+            width: 1600,
+            height: 1600,
+            lines,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn joins_a_line_crossing_a_seam() {
+        // Tiles at x = 0 and 1344 overlap in 1344..1600. The line runs
+        // from 1000 to 1930, so each tile reads only its side of it;
+        // both read "gamma delta" in the overlap.
+        let left = line(
+            &[
+                ("alpha", 1000.0),
+                ("beta", 1100.0),
+                ("gamma", 1360.0),
+                ("delta", 1460.0),
+            ],
+            0.0,
+        );
+        let right = line(
+            &[
+                ("gamma", 1360.0),
+                ("delta", 1460.0),
+                ("epsilon", 1700.0),
+                ("zeta", 1850.0),
+            ],
+            1344.0,
+        );
+        let merged = merge_tiles(
+            vec![
+                (Point(0.0, 0.0), page(vec![left])),
+                (Point(1344.0, 0.0), page(vec![right])),
+            ],
+            2944,
+            1600,
+        );
+
+        assert_eq!(merged.lines.len(), 1);
+        let joined = &merged.lines[0];
+        assert_eq!(joined.text, "alpha beta gamma delta epsilon zeta");
+        let bounds = joined.bbox.bounds();
+        assert_eq!((bounds.x0, bounds.x1), (1000.0, 1930.0));
+        let words: Vec<&str> = joined.words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(
+            words,
+            ["alpha", "beta", "gamma", "delta", "epsilon", "zeta"]
+        );
+    }
+
+    #[test]
+    fn keeps_unrelated_overlapping_lines_apart() {
+        // Same row and overlapping boxes, but nothing read by both.
+        let left = line(&[("total", 1300.0), ("due", 1400.0)], 0.0);
+        let right = line(
+            &[("paid", 1450.0), ("in", 1550.0), ("full", 1650.0)],
+            1344.0,
+        );
+        let merged = merge_tiles(
+            vec![
+                (Point(0.0, 0.0), page(vec![left])),
+                (Point(1344.0, 0.0), page(vec![right])),
+            ],
+            2944,
+            1600,
+        );
+        let mut texts: Vec<&str> = merged.lines.iter().map(|l| l.text.as_str()).collect();
+        texts.sort();
+        assert_eq!(texts, ["paid in full", "total due"]);
+    }
+
+    #[test]
+    fn stitches_only_at_a_shared_stretch() {
+        assert_eq!(
+            stitch("the quick brown", "Brown fox").as_deref(),
+            Some("the quick brown fox")
+        );
+        assert_eq!(stitch("the quick", "brown fox"), None);
+        // One shared letter is a coincidence, not an overlap.
+        assert_eq!(stitch("alpha", "apple"), None);
+    }
+
+    #[test]
+    fn joins_a_line_crossing_two_seams() {
+        let words = [
+            ("one", 1000.0),
+            ("two", 1400.0),
+            ("three", 1800.0),
+            ("four", 2200.0),
+            ("five", 2600.0),
+            ("six", 3000.0),
+        ];
+        // Tiles at 0, 1344 and 2688 each read their side of the line.
+        let first = line(&words[..3], 0.0);
+        let second = line(&words[1..5], 1344.0);
+        let third = line(&words[4..], 2688.0);
+        let merged = merge_tiles(
+            vec![
+                (Point(2688.0, 0.0), page(vec![third])),
+                (Point(0.0, 0.0), page(vec![first])),
+                (Point(1344.0, 0.0), page(vec![second])),
+            ],
+            4288,
+            1600,
+        );
+        assert_eq!(merged.lines.len(), 1);
+        assert_eq!(merged.lines[0].text, "one two three four five six");
+        assert_eq!(merged.lines[0].words.len(), 6);
+    }
+
+    #[test]
+    fn keeps_separate_rows_apart() {
+        let upper = line(&[("first", 1400.0), ("row", 1500.0)], 0.0);
+        let mut lower = line(&[("second", 1500.0), ("row", 1620.0)], 1344.0);
+        lower.bbox = lower.bbox.map(|p| Point(p.0, p.1 + 40.0));
+        let merged = merge_tiles(
+            vec![
+                (Point(0.0, 0.0), page(vec![upper])),
+                (Point(1344.0, 0.0), page(vec![lower])),
+            ],
+            2944,
+            1600,
+        );
+        assert_eq!(merged.lines.len(), 2);
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! OCR results: documents, pages, lines and words.
+//!
+//! These types mirror the JSON written by the `ocr-engine` sidecar and
+//! are what the app, the frontend and the exporters pass around.
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::geometry::{BoundingBox, Point};
+use crate::layout::{self, Block};
+
+/// A word inside a [`Line`].
+///
+/// PaddleOCR recognizes whole lines, so word boxes are estimated by
+/// splitting the line quad proportionally to character counts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    #[serde(rename = "box")]
+    pub bbox: BoundingBox,
+    pub confidence: f32,
+}
+
+/// A line of text as detected by the engine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Line {
+    pub text: String,
+    #[serde(rename = "box")]
+    pub bbox: BoundingBox,
+    pub confidence: f32,
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+/// Which engine and models produced a result.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct EngineInfo {
+    pub name: String,
+    pub version: String,
+    pub lang: String,
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+/// OCR result for a single image.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Page {
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    pub lines: Vec<Line>,
+    /// Empty when the engine did not say, whether by leaving the field
+    /// out or by writing `null` as older sidecars do.
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub engine: EngineInfo,
+}
+
+impl Page {
+    /// Mean line confidence, or `None` for an empty page.
+    pub fn confidence(&self) -> Option<f32> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        let sum: f32 = self.lines.iter().map(|l| l.confidence).sum();
+        Some(sum / self.lines.len() as f32)
+    }
+
+    /// Iterate over every word on the page.
+    pub fn words(&self) -> impl Iterator<Item = &Word> {
+        self.lines.iter().flat_map(|l| l.words.iter())
+    }
+
+    /// Apply `f` to every line and word box, e.g. to move results from
+    /// a processed image back onto the original.
+    pub fn map_boxes(&mut self, mut f: impl FnMut(Point) -> Point) {
+        for line in &mut self.lines {
+            line.bbox = line.bbox.map(&mut f);
+            for word in &mut line.words {
+                word.bbox = word.bbox.map(&mut f);
+            }
+        }
+    }
+
+    /// Lines grouped into blocks, in reading order.
+    ///
+    /// Block rows hold indices into [`Page::lines`].
+    pub fn blocks(&self) -> Vec<Block> {
+        layout::analyze(&self.lines)
+    }
+
+    /// Reorder [`Page::lines`] into reading order.
+    ///
+    /// Returns the blocks, with indices into the reordered lines, so
+    /// callers that also want them need not analyze the page again.
+    pub fn sort_reading_order(&mut self) -> Vec<Block> {
+        let mut blocks = self.blocks();
+        let mut lines: Vec<Option<Line>> = self.lines.drain(..).map(Some).collect();
+        let indices = blocks.iter_mut().flat_map(|b| b.rows.iter_mut().flatten());
+        for (next, index) in indices.enumerate() {
+            self.lines.extend(lines[*index].take());
+            *index = next;
+        }
+        blocks
+    }
+
+    /// Plain text in reading order.
+    ///
+    /// Lines on the same row are joined with spaces, rows with newlines
+    /// and blocks with blank lines.
+    pub fn text(&self) -> String {
+        self.text_of(&self.blocks())
+    }
+
+    /// Plain text of `blocks`, as returned by [`Page::blocks`] or
+    /// [`Page::sort_reading_order`].
+    pub fn text_of(&self, blocks: &[Block]) -> String {
+        blocks
+            .iter()
+            .map(|block| {
+                block
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|&i| self.lines[i].text.trim())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+/// OCR results for one or more pages.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Document {
+    pub pages: Vec<Page>,
+}
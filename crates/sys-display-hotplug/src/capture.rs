@@ -0,0 +1,325 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Supervising the capture process.
+//!
+//! The capture child runs in a process group of its own, so signals
+//! reach whatever helpers it starts as well. A reaper thread waits on
+//! it from the moment it is spawned, which leaves no zombie behind
+//! however it ends, and lets [`CaptureSupervisor::terminate`] wait for
+//! it to exit instead of polling. The running capture is registered as
+//! a guard, so a SIGINT or SIGTERM to this process stops it too.
+//!
+//! The group id is the child's pid, and the pid is only free for reuse
+//! once the child is reaped. So the reaper kills whatever helpers are
+//! left in the group as soon as the child exits but before reaping it,
+//! and nothing signals the group after that.
+
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitStatus};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use sys_guard_registry::Registration;
+
+/// How long the capture gets to exit after SIGTERM before it is killed.
+const DEFAULT_GRACE: Duration = Duration::from_millis(200);
+
+/// One spawned capture process.
+struct Child {
+    pid: u32,
+    /// Set by the reaper once the process has exited.
+    status: Mutex<Option<ExitStatus>>,
+    exited: Condvar,
+}
+
+impl Child {
+    fn status(&self) -> MutexGuard<'_, Option<ExitStatus>> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait(&self) -> ExitStatus {
+        let status = self
+            .exited
+            .wait_while(self.status(), |s| s.is_none())
+            .unwrap_or_else(|e| e.into_inner());
+        status.expect("woken with a status")
+    }
+
+    /// Wait up to `timeout` for the process to exit.
+    fn wait_timeout(&self, timeout: Duration) -> Option<ExitStatus> {
+        let (status, _) = self
+            .exited
+            .wait_timeout_while(self.status(), timeout, |s| s.is_none())
+            .unwrap_or_else(|e| e.into_inner());
+        *status
+    }
+
+    /// Send `signal` to the process group, unless the child has been
+    /// reaped and the group is gone.
+    fn signal(&self, signal: libc::c_int) -> io::Result<()> {
+        // The reaper reaps while holding this lock.
+        let status = self.status();
+        if status.is_some() {
+            return Ok(());
+        }
+        kill_group(self.pid, signal)
+    }
+
+    /// SIGTERM to the group, then SIGKILL once `grace` is over. Helpers
+    /// still running when the child exits are killed by the reaper.
+    fn stop(&self, grace: Duration) -> io::Result<ExitStatus> {
+        if let Some(status) = *self.status() {
+            return Ok(status);
+        }
+
+        self.signal(libc::SIGTERM)?;
+        match self.wait_timeout(grace) {
+            Some(status) => Ok(status),
+            None => {
+                self.signal(libc::SIGKILL)?;
+                Ok(self.wait())
+            }
+        }
+    }
+
+    /// Wait for the child to exit, kill what is left of its group and
+    /// reap it.
+    fn reap(&self, mut process: std::process::Child) {
+        if let Err(e) = wait_exited(self.pid) {
+            log::warn!("Failed to wait for capture process {}: {e}", self.pid);
+        }
+        let mut status = self.status();
+        // The unreaped child still holds the group id.
+        let _ = kill_group(self.pid, libc::SIGKILL);
+        // Only an invalid pid makes `wait` fail, and this is the
+        // process's only waiter.
+        *status = Some(process.wait().unwrap_or(ExitStatus::from_raw(0)));
+        drop(status);
+        self.exited.notify_all();
+    }
+}
+
+/// Send `signal` to the process group `pgid`.
+fn kill_group(pgid: u32, signal: libc::c_int) -> io::Result<()> {
+    // SAFETY: `kill` has no memory safety requirements. Callers only
+    // pass the id of a group whose leader has not been reaped.
+    if unsafe { libc::kill(-(pgid as libc::pid_t), signal) } < 0 {
+        let e = io::Error::last_os_error();
+        // The whole group is already gone.
+        if e.raw_os_error() != Some(libc::ESRCH) {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Block until process `pid` has exited, leaving it unreaped.
+fn wait_exited(pid: u32) -> io::Result<()> {
+    loop {
+        // SAFETY: `info` is a valid `siginfo_t` for the kernel to fill.
+        let result = unsafe {
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+/// Runs one capture process at a time and stops it on demand.
+pub struct CaptureSupervisor {
+    current: Mutex<Option<Arc<Child>>>,
+    /// Stops the current capture if this process is killed.
+    registration: Mutex<Option<Registration>>,
+    grace: Duration,
+}
+
+impl Default for CaptureSupervisor {
+    fn default() -> Self {
+        Self::new(DEFAULT_GRACE)
+    }
+}
+
+impl CaptureSupervisor {
+    /// A supervisor that gives the capture `grace` to exit after
+    /// SIGTERM.
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            current: Mutex::new(None),
+            registration: Mutex::new(None),
+            grace,
+        }
+    }
+
+    fn current(&self) -> Option<Arc<Child>> {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Start `command` in a new process group. Fails while a previous
+    /// capture is still running.
+    pub fn spawn(&self, mut command: Command) -> io::Result<u32> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if current.as_ref().is_some_and(|c| c.status().is_none()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "a capture is already running",
+            ));
+        }
+
+        let process = command.process_group(0).spawn()?;
+        let child = Arc::new(Child {
+            pid: process.id(),
+            status: Mutex::new(None),
+            exited: Condvar::new(),
+        });
+        let reaped = child.clone();
+        let reaper = thread::Builder::new()
+            .name("capture-reaper".into())
+            .spawn(move || reaped.reap(process));
+        if let Err(e) = reaper {
+            // Without a reaper nothing could wait for it; take it down
+            // now rather than leave it unsupervised.
+            let _ = child.signal(libc::SIGKILL);
+            return Err(e);
+        }
+
+        let pid = child.pid;
+        let stopped = child.clone();
+        let grace = self.grace;
+        let registration = sys_guard_registry::register("capture", move || {
+            let _ = stopped.stop(grace);
+        });
+        *current = Some(child);
+        *self.registration.lock().unwrap_or_else(|e| e.into_inner()) = Some(registration);
+        Ok(pid)
+    }
+
+    /// Pid of the running capture, if any.
+    pub fn pid(&self) -> Option<u32> {
+        self.current()
+            .filter(|c| c.status().is_none())
+            .map(|c| c.pid)
+    }
+
+    /// Wait for the current capture to exit. `None` when none was
+    /// spawned.
+    pub fn wait(&self) -> Option<ExitStatus> {
+        Some(self.current()?.wait())
+    }
+
+    /// Stop the current capture: SIGTERM to its group, then SIGKILL once
+    /// the grace period is over. Returns how it exited, or `None` when
+    /// none was spawned.
+    pub fn terminate(&self) -> io::Result<Option<ExitStatus>> {
+        let Some(child) = self.current() else {
+            return Ok(None);
+        };
+        child.stop(self.grace).map(Some)
+    }
+}
+
+impl Drop for CaptureSupervisor {
+    fn drop(&mut self) {
+        let _ = self.terminate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::Instant;
+
+    use super::*;
+
+    /// Run `script` under `sh`, which writes the pid of its background
+    /// helper to a file and returns the path of that file.
+    fn spawn(supervisor: &CaptureSupervisor, test: &str, script: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ocrmyimg-capture-test-{}-{test}",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script).arg("sh").arg(&path);
+        supervisor.spawn(command).unwrap();
+        path
+    }
+
+    fn helper_pid(path: &Path) -> libc::pid_t {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(pid) = fs::read_to_string(path)
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+            {
+                let _ = fs::remove_file(path);
+                return pid;
+            }
+            assert!(Instant::now() < deadline, "helper never started");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Whether `pid` is gone, waiting a little for init to reap it.
+    fn gone(pid: libc::pid_t) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            // A killed orphan lingers as a zombie until init reaps it.
+            let state = fs::read_to_string(format!("/proc/{pid}/stat")).unwrap_or_default();
+            let zombie = state
+                .rsplit(')')
+                .next()
+                .is_some_and(|s| s.trim_start().starts_with('Z'));
+            if state.is_empty() || zombie {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn kills_helpers_left_behind_by_a_normal_exit() {
+        let supervisor = CaptureSupervisor::default();
+        let path = spawn(
+            &supervisor,
+            "exit",
+            r#"sleep 30 & echo $! > "$1.tmp" && mv "$1.tmp" "$1"; sleep 0.2"#,
+        );
+        let helper = helper_pid(&path);
+        assert!(supervisor.wait().unwrap().success());
+        assert!(gone(helper));
+    }
+
+    #[test]
+    fn terminate_stops_the_whole_group() {
+        let supervisor = CaptureSupervisor::new(Duration::from_millis(100));
+        let path = spawn(
+            &supervisor,
+            "terminate",
+            r#"trap '' TERM; sleep 30 & echo $! > "$1.tmp" && mv "$1.tmp" "$1"; wait"#,
+        );
+        let helper = helper_pid(&path);
+        let status = supervisor.terminate().unwrap().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        assert!(gone(helper));
+        assert_eq!(supervisor.pid(), None);
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Identifying monitors from their EDID.
+
+/// Fixed header every EDID base block starts with.
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+const BLOCK_SIZE: usize = 128;
+
+/// Display descriptor tags.
+const SERIAL_TAG: u8 = 0xff;
+const NAME_TAG: u8 = 0xfc;
+
+/// Identity of a display, from the base block of its EDID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edid {
+    /// Three-letter PNP vendor id, e.g. `DEL` or `SAM`.
+    pub manufacturer: String,
+    /// Vendor-assigned product code.
+    pub product: u16,
+    /// Numeric serial number; 0 when the vendor does not set one.
+    pub serial: u32,
+    /// Model name from the display descriptors, e.g. `DELL U2720Q`.
+    pub model: Option<String>,
+    /// Serial number string from the display descriptors.
+    pub serial_string: Option<String>,
+}
+
+impl Edid {
+    /// Parse the base block of `data`, or `None` when it is not an EDID.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let block = data.get(..BLOCK_SIZE)?;
+        if block[..8] != HEADER {
+            return None;
+        }
+
+        // Three letters of five bits each, 1 meaning 'A'.
+        let packed = u16::from_be_bytes([block[8], block[9]]);
+        let manufacturer: String = [10, 5, 0]
+            .iter()
+            .map(|shift| {
+                let letter = ((packed >> shift) & 0x1f) as u8;
+                if (1..=26).contains(&letter) {
+                    char::from(b'A' + letter - 1)
+                } else {
+                    '?'
+                }
+            })
+            .collect();
+
+        let mut model = None;
+        let mut serial_string = None;
+        for descriptor in block[54..126].chunks_exact(18) {
+            // Detailed timings start with a non-zero pixel clock.
+            if descriptor[..3] != [0, 0, 0] {
+                continue;
+            }
+            match descriptor[3] {
+                NAME_TAG => model = descriptor_text(&descriptor[5..]),
+                SERIAL_TAG => serial_string = descriptor_text(&descriptor[5..]),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            manufacturer,
+            product: u16::from_le_bytes([block[10], block[11]]),
+            serial: u32::from_le_bytes([block[12], block[13], block[14], block[15]]),
+            model,
+            serial_string,
+        })
+    }
+}
+
+/// Descriptor text: up to 13 bytes ended by a newline and padded with
+/// spaces.
+fn descriptor_text(bytes: &[u8]) -> Option<String> {
+    let end = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .unwrap_or(bytes.len());
+    let text: String = bytes[..end]
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() {
+                char::from(b)
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A base block with a detailed timing, a name descriptor and a
+    /// serial number descriptor.
+    pub(crate) fn edid(manufacturer: &str, product: u16, serial: u32, model: &str) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        block[..8].copy_from_slice(&HEADER);
+        let packed = manufacturer
+            .bytes()
+            .fold(0u16, |acc, b| acc << 5 | u16::from(b - b'A' + 1));
+        block[8..10].copy_from_slice(&packed.to_be_bytes());
+        block[10..12].copy_from_slice(&product.to_le_bytes());
+        block[12..16].copy_from_slice(&serial.to_le_bytes());
+        // A 148.5 MHz timing whose bytes would read as a name tag.
+        block[54..56].copy_from_slice(&14850u16.to_le_bytes());
+        block[57] = NAME_TAG;
+        descriptor(&mut block[72..90], NAME_TAG, model);
+        descriptor(&mut block[90..108], SERIAL_TAG, "ABC123");
+        block
+    }
+
+    fn descriptor(slot: &mut [u8], tag: u8, text: &str) {
+        slot[3] = tag;
+        let field = &mut slot[5..];
+        field.fill(b' ');
+        let text = format!("{text}\n");
+        let len = text.len().min(field.len());
+        field[..len].copy_from_slice(&text.as_bytes()[..len]);
+    }
+
+    #[test]
+    fn parses_identity() {
+        let mut data = edid("DEL", 0x41a3, 0x3032_4c4b, "DELL U2720Q");
+        // Extension blocks are ignored.
+        data.extend([0u8; BLOCK_SIZE]);
+        assert_eq!(
+            Edid::parse(&data),
+            Some(Edid {
+                manufacturer: "DEL".into(),
+                product: 0x41a3,
+                serial: 0x3032_4c4b,
+                model: Some("DELL U2720Q".into()),
+                serial_string: Some("ABC123".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn truncates_descriptor_text_at_thirteen_bytes() {
+        let edid = Edid::parse(&edid("SAM", 1, 0, "Samsung Odyssey G9")).unwrap();
+        assert_eq!(edid.model.as_deref(), Some("Samsung Odyss"));
+    }
+
+    #[test]
+    fn marks_invalid_manufacturer_letters() {
+        let mut data = edid("AAA", 1, 0, "X");
+        data[8..10].copy_from_slice(&0x7fffu16.to_be_bytes());
+        assert_eq!(Edid::parse(&data).unwrap().manufacturer, "???");
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        let mut data = edid("DEL", 1, 0, "X");
+        assert!(Edid::parse(&data[..BLOCK_SIZE - 1]).is_none());
+        data[0] = 0xff;
+        assert!(Edid::parse(&data).is_none());
+        assert!(Edid::parse(&[]).is_none());
+    }
+}
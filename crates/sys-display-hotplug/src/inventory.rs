@@ -0,0 +1,345 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! The set of connected monitors and how it changes.
+//!
+//! The kernel lists every connector under `/sys/class/drm` with its
+//! status and the EDID of whatever is plugged in. What the display
+//! server does with each monitor (mode, position in the desktop, scale)
+//! comes from X11 RandR when an X server is reachable. Wayland
+//! compositors do not share this, so under Wayland those fields stay
+//! empty and only connectors and EDIDs are compared.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::edid::Edid;
+use crate::randr;
+
+const SYSFS_DRM: &str = "/sys/class/drm";
+
+/// A display mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mode {
+    pub width: u32,
+    pub height: u32,
+    /// Refresh rate in Hz.
+    pub refresh: f64,
+}
+
+/// A connected monitor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    /// Card the connector belongs to, e.g. `card0`.
+    pub card: String,
+    /// Kernel connector name, e.g. `HDMI-A-1` or `eDP-1`.
+    pub connector: String,
+    /// `None` when the monitor provides no readable EDID.
+    pub edid: Option<Edid>,
+    /// Current mode, from RandR.
+    pub mode: Option<Mode>,
+    /// Top-left corner in the desktop, from RandR.
+    pub position: Option<(i32, i32)>,
+    /// Mode pixels per desktop pixel, from RandR; 2 when a 4K panel
+    /// shows a 1920 pixel wide area of the desktop.
+    pub scale: Option<f64>,
+}
+
+/// How a monitor changed between two inventories.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonitorChange {
+    Connected(Monitor),
+    Disconnected(Monitor),
+    /// A different display was plugged into the same connector.
+    Replaced {
+        old: Monitor,
+        new: Monitor,
+    },
+    /// The same display changed mode, position or scale.
+    Reconfigured {
+        old: Monitor,
+        new: Monitor,
+    },
+}
+
+impl fmt::Display for MonitorChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connected(m) => write!(f, "{} connected", m.connector),
+            Self::Disconnected(m) => write!(f, "{} disconnected", m.connector),
+            Self::Replaced { new, .. } => write!(f, "{} replaced", new.connector),
+            Self::Reconfigured { new, .. } => write!(f, "{} reconfigured", new.connector),
+        }
+    }
+}
+
+/// Every connected monitor, ordered by card and connector.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Inventory {
+    pub monitors: Vec<Monitor>,
+}
+
+impl Inventory {
+    /// Read the monitors from the kernel and, when available, RandR.
+    pub fn read() -> Self {
+        let mut inventory = Self::from_sysfs(Path::new(SYSFS_DRM));
+        if let Some(outputs) = randr::outputs() {
+            inventory.apply_randr(outputs);
+        }
+        inventory
+    }
+
+    /// Read the connected connectors listed in `dir`, laid out like
+    /// `/sys/class/drm`. Only the kernel's fields are filled in.
+    pub fn from_sysfs(dir: &Path) -> Self {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Self::default();
+        };
+        let mut monitors: Vec<Monitor> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|entry| {
+                // Connectors are named after their card: `card0-HDMI-A-1`.
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let (card, connector) = name.split_once('-')?;
+                if !card.starts_with("card") {
+                    return None;
+                }
+                let path = entry.path();
+                let status = fs::read_to_string(path.join("status")).ok()?;
+                if status.trim() != "connected" {
+                    return None;
+                }
+                Some(Monitor {
+                    card: card.to_string(),
+                    connector: connector.to_string(),
+                    edid: fs::read(path.join("edid"))
+                        .ok()
+                        .and_then(|d| Edid::parse(&d)),
+                    mode: None,
+                    position: None,
+                    scale: None,
+                })
+            })
+            .collect();
+        monitors.sort_by(|a, b| (&a.card, &a.connector).cmp(&(&b.card, &b.connector)));
+        Self { monitors }
+    }
+
+    /// Fill in mode, position and scale from the RandR outputs that
+    /// belong to these monitors.
+    fn apply_randr(&mut self, mut outputs: Vec<randr::Output>) {
+        // Drivers name outputs their own way (`HDMI-1`, `HDMI-A-0`,
+        // `HDMI1` for the kernel's `HDMI-A-1`), so outputs are matched
+        // by EDID first and by name only when that fails. Two monitors
+        // of the same model without serial numbers share an EDID; the
+        // name decides between them, and once one of them is matched by
+        // name the EDID of the other is unique again.
+        for by_name in [false, true, false] {
+            for monitor in self.monitors.iter_mut().filter(|m| m.mode.is_none()) {
+                let found = if by_name {
+                    outputs.iter().position(|o| o.name == monitor.connector)
+                } else {
+                    let same: Vec<usize> = (0..outputs.len())
+                        .filter(|&i| outputs[i].edid.is_some() && outputs[i].edid == monitor.edid)
+                        .collect();
+                    match same[..] {
+                        [i] => Some(i),
+                        _ => same
+                            .into_iter()
+                            .find(|&i| outputs[i].name == monitor.connector),
+                    }
+                };
+                if let Some(i) = found {
+                    let output = outputs.swap_remove(i);
+                    monitor.mode = Some(output.mode);
+                    monitor.position = Some(output.position);
+                    monitor.scale = Some(output.scale);
+                }
+            }
+        }
+    }
+
+    /// What changed from this inventory to `new`.
+    pub fn changes(&self, new: &Inventory) -> Vec<MonitorChange> {
+        let key = |m: &Monitor| (m.card.clone(), m.connector.clone());
+        let mut changes = Vec::new();
+        for old in &self.monitors {
+            match new.monitors.iter().find(|m| key(m) == key(old)) {
+                None => changes.push(MonitorChange::Disconnected(old.clone())),
+                Some(m) if m.edid != old.edid => changes.push(MonitorChange::Replaced {
+                    old: old.clone(),
+                    new: m.clone(),
+                }),
+                Some(m) if m != old => changes.push(MonitorChange::Reconfigured {
+                    old: old.clone(),
+                    new: m.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for m in &new.monitors {
+            if !self.monitors.iter().any(|old| key(old) == key(m)) {
+                changes.push(MonitorChange::Connected(m.clone()));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::edid::tests::edid;
+
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ocrmyimg-drm-test-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn connector(dir: &Path, name: &str, status: &str, edid: &[u8]) {
+        let path = dir.join(name);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("status"), format!("{status}\n")).unwrap();
+        fs::write(path.join("edid"), edid).unwrap();
+    }
+
+    fn monitor(connector: &str, edid: Option<Edid>) -> Monitor {
+        Monitor {
+            card: "card0".into(),
+            connector: connector.into(),
+            edid,
+            mode: None,
+            position: None,
+            scale: None,
+        }
+    }
+
+    fn output(name: &str, edid: Option<Edid>, x: i32) -> randr::Output {
+        randr::Output {
+            name: name.into(),
+            edid,
+            mode: Mode {
+                width: 1920,
+                height: 1080,
+                refresh: 60.0,
+            },
+            position: (x, 0),
+            scale: 1.0,
+        }
+    }
+
+    fn dell() -> Option<Edid> {
+        Edid::parse(&edid("DEL", 0xa0c4, 0, "DELL P2419H"))
+    }
+
+    #[test]
+    fn reads_connected_connectors() {
+        let dir = temp_dir("sysfs");
+        let data = edid("DEL", 0xa0c4, 7, "DELL P2419H");
+        connector(&dir, "card1-DP-2", "connected", &data);
+        connector(&dir, "card0-HDMI-A-1", "connected", &data);
+        // No EDID to read, e.g. a projector behind a cheap adapter.
+        connector(&dir, "card0-eDP-1", "connected", &[]);
+        connector(&dir, "card0-DP-1", "disconnected", &[]);
+        // Cards, render nodes and other entries are not connectors.
+        fs::create_dir_all(dir.join("card0")).unwrap();
+        fs::create_dir_all(dir.join("renderD128")).unwrap();
+        fs::write(dir.join("version"), "drm 1.1.0").unwrap();
+
+        let inventory = Inventory::from_sysfs(&dir);
+        let _ = fs::remove_dir_all(&dir);
+
+        let names: Vec<(&str, &str)> = inventory
+            .monitors
+            .iter()
+            .map(|m| (m.card.as_str(), m.connector.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [("card0", "HDMI-A-1"), ("card0", "eDP-1"), ("card1", "DP-2")]
+        );
+        let edid = inventory.monitors[0].edid.as_ref().unwrap();
+        assert_eq!((edid.manufacturer.as_str(), edid.serial), ("DEL", 7));
+        assert_eq!(inventory.monitors[1].edid, None);
+    }
+
+    #[test]
+    fn missing_directory_is_empty() {
+        let inventory = Inventory::from_sysfs(Path::new("/nonexistent/drm"));
+        assert!(inventory.monitors.is_empty());
+    }
+
+    #[test]
+    fn matches_outputs_by_edid_then_name() {
+        let samsung = Edid::parse(&edid("SAM", 0x0f00, 1, "S24"));
+        let mut inventory = Inventory {
+            monitors: vec![monitor("DP-1", samsung.clone()), monitor("eDP-1", None)],
+        };
+        inventory.apply_randr(vec![
+            output("eDP-1", None, 0),
+            output("DisplayPort-0", samsung, 1920),
+        ]);
+        assert_eq!(inventory.monitors[0].position, Some((1920, 0)));
+        assert_eq!(inventory.monitors[1].position, Some((0, 0)));
+    }
+
+    #[test]
+    fn tells_identical_monitors_apart_by_name() {
+        // Same model, serial 0: the EDIDs are identical.
+        let mut inventory = Inventory {
+            monitors: vec![monitor("DP-1", dell()), monitor("HDMI-A-1", dell())],
+        };
+        inventory.apply_randr(vec![
+            output("HDMI-1", dell(), 1920),
+            output("DP-1", dell(), 0),
+        ]);
+        assert_eq!(inventory.monitors[0].position, Some((0, 0)));
+        assert_eq!(inventory.monitors[1].position, Some((1920, 0)));
+    }
+
+    #[test]
+    fn reports_changes() {
+        let lg = Edid::parse(&edid("GSM", 0x5b08, 2, "LG ULTRAFINE"));
+        let old = Inventory {
+            monitors: vec![
+                monitor("DP-1", dell()),
+                monitor("DP-2", dell()),
+                monitor("HDMI-A-1", dell()),
+                monitor("eDP-1", None),
+            ],
+        };
+        let mut moved = monitor("DP-2", dell());
+        moved.position = Some((1920, 0));
+        let new = Inventory {
+            monitors: vec![
+                monitor("DP-1", lg.clone()),
+                moved.clone(),
+                monitor("eDP-1", None),
+                monitor("DP-3", lg.clone()),
+            ],
+        };
+
+        assert_eq!(
+            old.changes(&new),
+            [
+                MonitorChange::Replaced {
+                    old: monitor("DP-1", dell()),
+                    new: monitor("DP-1", lg.clone()),
+                },
+                MonitorChange::Reconfigured {
+                    old: monitor("DP-2", dell()),
+                    new: moved,
+                },
+                MonitorChange::Disconnected(monitor("HDMI-A-1", dell())),
+                MonitorChange::Connected(monitor("DP-3", lg)),
+            ]
+        );
+        assert!(new.changes(&new).is_empty());
+    }
+}
@@ -1,17 +1,34 @@
-use anyhow::Result;
-use image::DynamicImage;
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
 
-pub fn optimize_for_ai(image_data: &[u8]) -> Result<Vec<u8>> {
-    // 1. Load image
-    let img = image::load_from_memory(image_data)?;
-    
-    // 2. Resize if too large (saving tokens/bandwidth)
-    let resized = img.resize(1024, 1024, image::imageops::FilterType::Lanczos3);
-    
-    // 3. Convert back to bytes (e.g., JPEG)
-    let mut output = Vec::new();
-    let mut cursor = std::io::Cursor::new(&mut output);
-    resized.write_to(&mut cursor, image::ImageOutputFormat::Jpeg(80))?;
-    
-    Ok(output)
-}
+//! Display hotplug detection.
+//!
+//! The kernel announces every change to a DRM device (a monitor plugged
+//! in or unplugged, a card appearing or going away) as a uevent on a
+//! netlink socket, and the X server announces mode and layout changes
+//! through RandR. [`DisplayWatcher`] listens for both and reports them
+//! as [`TopologyEvent`]s, so nothing has to poll the display server.
+//! Events come from an [`EventSource`], which tests can replace with a
+//! synthetic one.
+//!
+//! What actually changed is found by diffing [`Inventory`] snapshots of
+//! the connected monitors taken before and after an event. A change
+//! during a capture stops the capture process, which runs under a
+//! [`CaptureSupervisor`].
+
+#![cfg(target_os = "linux")]
+
+mod capture;
+mod edid;
+mod inventory;
+mod monitors;
+mod randr;
+mod uevent;
+mod watcher;
+
+pub use capture::CaptureSupervisor;
+pub use edid::Edid;
+pub use inventory::{Inventory, Mode, Monitor, MonitorChange};
+pub use monitors::start_monitor;
+pub use uevent::UEvent;
+pub use watcher::{DisplayWatcher, EventSource, NetlinkSource, TopologyEvent};
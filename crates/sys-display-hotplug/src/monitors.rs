@@ -0,0 +1,116 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stopping a capture when the display topology changes.
+//!
+//! A capture taken across a monitor being plugged in or unplugged comes
+//! out with the wrong geometry, so the capture process is killed and the
+//! host reports the failure instead.
+
+use std::io;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::capture::CaptureSupervisor;
+use crate::inventory::Inventory;
+use crate::watcher::{DisplayWatcher, TopologyEvent};
+
+/// Hotplug events come in bursts; wait this long after the last one
+/// before taking stock of the monitors.
+const SETTLE: Duration = Duration::from_millis(800);
+
+/// Kill the capture process whenever a monitor is connected,
+/// disconnected, swapped or reconfigured while `capture` runs.
+pub fn start_monitor(capture: Arc<CaptureSupervisor>) -> io::Result<JoinHandle<()>> {
+    let (watcher, events) = DisplayWatcher::channel()?;
+    thread::Builder::new()
+        .name("display-monitor".into())
+        .spawn(move || {
+            let _watcher = watcher;
+            let mut last = Inventory::read();
+            while wait_settled(&events, SETTLE) {
+                let current = Inventory::read();
+                let changes = last.changes(&current);
+                if changes.is_empty() {
+                    continue;
+                }
+                if capture.pid().is_none() {
+                    last = current;
+                    continue;
+                }
+                let changes: Vec<String> = changes.iter().map(ToString::to_string).collect();
+                log::warn!(
+                    "Display topology changed ({}). Emergency Shutdown.",
+                    changes.join(", ")
+                );
+                emergency_shutdown(&capture);
+                return;
+            }
+        })
+}
+
+/// Wait for a burst of events and for `settle` to pass after its last
+/// one. Returns false once the watcher has stopped.
+fn wait_settled(events: &Receiver<TopologyEvent>, settle: Duration) -> bool {
+    if events.recv().is_err() {
+        return false;
+    }
+    while events.recv_timeout(settle).is_ok() {}
+    true
+}
+
+fn emergency_shutdown(capture: &CaptureSupervisor) {
+    // Only the child is stopped: the host wakes up from waiting on it,
+    // handles the error and drops its guards normally.
+    match capture.terminate() {
+        Ok(_) => log::warn!("Killed capture process due to monitor topology change."),
+        Err(e) => log::error!("Failed to kill capture process: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::uevent::UEvent;
+
+    #[test]
+    fn waits_for_a_burst_to_settle() {
+        let (uevents, source) = mpsc::channel();
+        let (sender, events) = mpsc::channel();
+        let watcher = DisplayWatcher::with_source(source, move |event| {
+            let _ = sender.send(event);
+        })
+        .unwrap();
+
+        let (settle, gap) = (Duration::from_millis(300), Duration::from_millis(50));
+        let hotplug = || {
+            UEvent::new(
+                "change",
+                "/devices/pci0000:00/drm/card0",
+                &[("SUBSYSTEM", "drm"), ("HOTPLUG", "1")],
+            )
+        };
+        let started = Instant::now();
+        let burst = thread::spawn(move || {
+            for _ in 0..5 {
+                uevents.send(hotplug()).unwrap();
+                thread::sleep(gap);
+            }
+            uevents
+        });
+
+        // One wake-up for the whole burst, only after it has ended.
+        assert!(wait_settled(&events, settle));
+        assert!(started.elapsed() >= gap * 4 + settle);
+        assert!(events.try_recv().is_err());
+
+        drop(burst.join().unwrap());
+        drop(watcher);
+        assert!(!wait_settled(&events, settle));
+    }
+}
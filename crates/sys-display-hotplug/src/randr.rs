@@ -0,0 +1,179 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Output configuration from the X server's RandR extension.
+//!
+//! Mode switches, moves and scaling done by the display server never
+//! reach the kernel as uevents, so they are watched here as well.
+
+use std::error::Error;
+use std::io;
+use std::os::fd::AsFd;
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::{self, ConnectionExt as _, ModeFlag, ModeInfo, Notify, NotifyMask};
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::edid::Edid;
+use crate::inventory::Mode;
+use crate::watcher::{wait_readable, TopologyEvent};
+
+/// Oldest RandR with `GetScreenResourcesCurrent`.
+const MIN_VERSION: (u32, u32) = (1, 3);
+
+/// EDID property length to fetch, in 32-bit units: the base block and
+/// one extension.
+const EDID_LENGTH: u32 = 64;
+
+/// A connected, enabled RandR output.
+pub(crate) struct Output {
+    pub name: String,
+    pub edid: Option<Edid>,
+    pub mode: Mode,
+    pub position: (i32, i32),
+    pub scale: f64,
+}
+
+/// The enabled outputs of the default screen, or `None` when there is
+/// no X server or it lacks RandR.
+pub(crate) fn outputs() -> Option<Vec<Output>> {
+    let result = connect().and_then(|(conn, root)| query(&conn, root));
+    result
+        .inspect_err(|e| log::debug!("RandR unavailable: {e}"))
+        .ok()
+}
+
+fn connect() -> Result<(RustConnection, Window), Box<dyn Error>> {
+    let (conn, screen) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen].root;
+    let version = conn
+        .randr_query_version(MIN_VERSION.0, MIN_VERSION.1)?
+        .reply()?;
+    if (version.major_version, version.minor_version) < MIN_VERSION {
+        return Err("RandR is too old".into());
+    }
+    Ok((conn, root))
+}
+
+fn query(conn: &RustConnection, root: Window) -> Result<Vec<Output>, Box<dyn Error>> {
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+    let edid_atom = conn.intern_atom(true, b"EDID")?.reply()?.atom;
+
+    let mut outputs = Vec::new();
+    for &output in &resources.outputs {
+        let info = conn
+            .randr_get_output_info(output, resources.config_timestamp)?
+            .reply()?;
+        if info.connection != randr::Connection::CONNECTED || info.crtc == x11rb::NONE {
+            continue;
+        }
+        let crtc = conn
+            .randr_get_crtc_info(info.crtc, resources.config_timestamp)?
+            .reply()?;
+        let Some(mode) = resources.modes.iter().find(|m| m.id == crtc.mode) else {
+            continue;
+        };
+
+        let edid = if edid_atom == x11rb::NONE {
+            None
+        } else {
+            let property = conn
+                .randr_get_output_property(
+                    output,
+                    edid_atom,
+                    AtomEnum::ANY,
+                    0,
+                    EDID_LENGTH,
+                    false,
+                    false,
+                )?
+                .reply()?;
+            Edid::parse(&property.data)
+        };
+
+        // The CRTC spans the desktop area the mode shows, turned with
+        // the output.
+        let turned = crtc
+            .rotation
+            .intersects(randr::Rotation::ROTATE90 | randr::Rotation::ROTATE270);
+        let shown_width = if turned { crtc.height } else { crtc.width };
+        let scale = if shown_width == 0 {
+            1.0
+        } else {
+            f64::from(mode.width) / f64::from(shown_width)
+        };
+
+        outputs.push(Output {
+            name: String::from_utf8_lossy(&info.name).into_owned(),
+            edid,
+            mode: to_mode(mode),
+            position: (i32::from(crtc.x), i32::from(crtc.y)),
+            scale,
+        });
+    }
+    Ok(outputs)
+}
+
+fn to_mode(info: &ModeInfo) -> Mode {
+    let mut lines = f64::from(info.vtotal);
+    if info.mode_flags.contains(ModeFlag::DOUBLE_SCAN) {
+        lines *= 2.0;
+    }
+    if info.mode_flags.contains(ModeFlag::INTERLACE) {
+        lines /= 2.0;
+    }
+    let dots = f64::from(info.htotal) * lines;
+    Mode {
+        width: u32::from(info.width),
+        height: u32::from(info.height),
+        refresh: if dots > 0.0 {
+            f64::from(info.dot_clock) / dots
+        } else {
+            0.0
+        },
+    }
+}
+
+/// RandR configuration changes on the default screen.
+pub(crate) struct RandrEvents {
+    conn: RustConnection,
+}
+
+impl RandrEvents {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        let (conn, root) = connect()?;
+        conn.randr_select_input(
+            root,
+            NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE,
+        )?
+        .check()?;
+        Ok(Self { conn })
+    }
+
+    /// Wait up to `timeout` for the next configuration change.
+    pub fn next_event(&mut self, timeout: Duration) -> io::Result<Option<TopologyEvent>> {
+        let event = match self.poll()? {
+            Some(event) => event,
+            None if wait_readable(self.conn.stream().as_fd(), timeout)? => match self.poll()? {
+                Some(event) => event,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let changed = match event {
+            Event::RandrScreenChangeNotify(_) => true,
+            Event::RandrNotify(notify) => {
+                matches!(notify.sub_code, Notify::CRTC_CHANGE | Notify::OUTPUT_CHANGE)
+            }
+            _ => false,
+        };
+        Ok(changed.then_some(TopologyEvent::Reconfigured))
+    }
+
+    fn poll(&self) -> io::Result<Option<Event>> {
+        self.conn.poll_for_event().map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kernel uevent messages.
+//!
+//! The kernel broadcasts a datagram for every device event: a header of
+//! the form `ACTION@DEVPATH`, then `KEY=VALUE` pairs, all NUL-separated.
+
+use std::collections::HashMap;
+
+/// One kernel device event.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UEvent {
+    /// `add`, `remove`, `change`, ...
+    pub action: String,
+    /// Device path under `/sys`, e.g. `/devices/pci0000:00/.../drm/card0`.
+    pub devpath: String,
+    /// Every `KEY=VALUE` pair, including `ACTION` and `DEVPATH`.
+    pub vars: HashMap<String, String>,
+}
+
+impl UEvent {
+    /// Parse a kernel uevent datagram. Returns `None` for anything else,
+    /// including messages re-broadcast by udev.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut fields = data
+            .split(|&b| b == 0)
+            .filter(|f| !f.is_empty())
+            .map(String::from_utf8_lossy);
+        let header = fields.next()?;
+        let (action, devpath) = header.split_once('@')?;
+
+        let vars: HashMap<String, String> = fields
+            .filter_map(|f| {
+                f.split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+            })
+            .collect();
+        Some(Self {
+            action: vars.get("ACTION").cloned().unwrap_or_else(|| action.into()),
+            devpath: vars
+                .get("DEVPATH")
+                .cloned()
+                .unwrap_or_else(|| devpath.into()),
+            vars,
+        })
+    }
+
+    /// Build an event from its parts, for synthetic event sources.
+    pub fn new(action: &str, devpath: &str, vars: &[(&str, &str)]) -> Self {
+        Self {
+            action: action.into(),
+            devpath: devpath.into(),
+            vars: vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.vars.get(key).map(String::as_str)
+    }
+
+    pub fn subsystem(&self) -> Option<&str> {
+        self.get("SUBSYSTEM")
+    }
+}
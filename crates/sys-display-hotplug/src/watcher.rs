@@ -0,0 +1,402 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Watching DRM uevents.
+
+use std::io;
+use std::mem;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::randr::RandrEvents;
+use crate::uevent::UEvent;
+
+/// How often the watcher thread checks whether it should stop.
+const STOP_CHECK: Duration = Duration::from_millis(250);
+
+/// Multicast group the kernel sends uevents to. Group 2 carries the
+/// copies re-broadcast by udev.
+const KERNEL_GROUP: u32 = 1;
+
+/// Large enough for any uevent; the kernel caps them at 2 KiB of
+/// variables.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A change to the display topology.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopologyEvent {
+    /// A connector on `card` changed state: a monitor was plugged in or
+    /// unplugged, or its modes changed. The kernel names the connector
+    /// by its id when it knows which one it was.
+    Hotplug {
+        card: String,
+        connector: Option<u32>,
+    },
+    CardAdded {
+        card: String,
+    },
+    CardRemoved {
+        card: String,
+    },
+    /// The X server changed the mode, position, rotation or scale of an
+    /// output.
+    Reconfigured,
+    /// The socket buffer overflowed and events were lost. Anything may
+    /// have changed.
+    Overflow,
+}
+
+impl TopologyEvent {
+    /// The topology event a uevent stands for, if any.
+    pub fn from_uevent(event: &UEvent) -> Option<Self> {
+        if event.subsystem() != Some("drm") {
+            return None;
+        }
+        // Connectors (`card0-HDMI-A-1`) and render nodes (`renderD128`)
+        // have devices of their own; changes are reported on the card.
+        let card = event.devpath.rsplit('/').next()?;
+        let is_card = card
+            .strip_prefix("card")
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if !is_card {
+            return None;
+        }
+        let card = card.to_string();
+        match event.action.as_str() {
+            "change" if event.get("HOTPLUG") == Some("1") => Some(Self::Hotplug {
+                card,
+                connector: event.get("CONNECTOR").and_then(|c| c.parse().ok()),
+            }),
+            "add" => Some(Self::CardAdded { card }),
+            "remove" => Some(Self::CardRemoved { card }),
+            _ => None,
+        }
+    }
+}
+
+/// Where a [`DisplayWatcher`] reads uevents from.
+pub trait EventSource: Send {
+    /// Wait up to `timeout` for the next event. `Ok(None)` means the
+    /// wait timed out. An error of kind `OutOfMemory` reports lost
+    /// events; any other error stops the watcher.
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<UEvent>>;
+}
+
+/// Synthetic events sent from another thread. The watcher stops when
+/// the sender is dropped.
+impl EventSource for Receiver<UEvent> {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<UEvent>> {
+        match self.recv_timeout(timeout) {
+            Ok(event) => Ok(Some(event)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+/// Kernel uevents from a `NETLINK_KOBJECT_UEVENT` socket.
+pub struct NetlinkSource {
+    fd: OwnedFd,
+    buffer: Vec<u8>,
+}
+
+impl NetlinkSource {
+    pub fn open() -> io::Result<Self> {
+        // SAFETY: plain socket calls; the descriptor is owned as soon as
+        // it is created and `addr` is a valid `sockaddr_nl`.
+        unsafe {
+            let fd = libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_KOBJECT_UEVENT,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let fd = OwnedFd::from_raw_fd(fd);
+
+            let mut addr: libc::sockaddr_nl = mem::zeroed();
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups = KERNEL_GROUP;
+            let bound = libc::bind(
+                fd.as_raw_fd(),
+                (&addr as *const libc::sockaddr_nl).cast(),
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            );
+            if bound < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                fd,
+                buffer: vec![0; BUFFER_SIZE],
+            })
+        }
+    }
+}
+
+impl EventSource for NetlinkSource {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<UEvent>> {
+        if !wait_readable(self.fd.as_fd(), timeout)? {
+            return Ok(None);
+        }
+        // SAFETY: `buffer` and `sender` are valid for the lengths given.
+        let (len, sender) = unsafe {
+            let mut sender: libc::sockaddr_nl = mem::zeroed();
+            let mut sender_len = mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+            let len = libc::recvfrom(
+                self.fd.as_raw_fd(),
+                self.buffer.as_mut_ptr().cast(),
+                self.buffer.len(),
+                libc::MSG_DONTWAIT,
+                (&mut sender as *mut libc::sockaddr_nl).cast(),
+                &mut sender_len,
+            );
+            (len, sender)
+        };
+        if len < 0 {
+            let e = io::Error::last_os_error();
+            return match e.raw_os_error() {
+                Some(libc::ENOBUFS) => Err(io::ErrorKind::OutOfMemory.into()),
+                Some(libc::EAGAIN | libc::EINTR) => Ok(None),
+                _ => Err(e),
+            };
+        }
+        // Only the kernel (port 0) is trusted; anything else on the
+        // group is ignored.
+        if sender.nl_pid != 0 {
+            return Ok(None);
+        }
+        Ok(UEvent::parse(&self.buffer[..len as usize]))
+    }
+}
+
+/// Wait up to `timeout` for `fd` to become readable; false on timeout.
+pub(crate) fn wait_readable(fd: BorrowedFd<'_>, timeout: Duration) -> io::Result<bool> {
+    let mut poll = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+    // SAFETY: `poll` points at one valid `pollfd`.
+    match unsafe { libc::poll(&mut poll, 1, millis) } {
+        n if n < 0 => {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                Ok(false)
+            } else {
+                Err(e)
+            }
+        }
+        n => Ok(n > 0),
+    }
+}
+
+type Callback = Arc<Mutex<dyn FnMut(TopologyEvent) + Send>>;
+
+/// Reports display topology changes on background threads until
+/// dropped.
+pub struct DisplayWatcher {
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+    /// Shared by the threads, so events arrive one at a time.
+    callback: Callback,
+}
+
+impl DisplayWatcher {
+    /// Watch kernel uevents, and RandR when an X server is reachable,
+    /// calling `on_event` for each topology change.
+    pub fn start<F>(on_event: F) -> io::Result<Self>
+    where
+        F: FnMut(TopologyEvent) + Send + 'static,
+    {
+        let mut watcher = Self::with_source(NetlinkSource::open()?, on_event)?;
+        match RandrEvents::open() {
+            Ok(mut randr) => {
+                let on_event = watcher.callback.clone();
+                watcher.spawn("randr-watcher", on_event, move |timeout| {
+                    randr.next_event(timeout)
+                })?;
+            }
+            Err(e) => log::debug!("Not watching RandR: {e}"),
+        }
+        Ok(watcher)
+    }
+
+    /// Like [`DisplayWatcher::start`], sending topology changes to the
+    /// returned receiver. It disconnects when the watcher stops.
+    pub fn channel() -> io::Result<(Self, Receiver<TopologyEvent>)> {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = Self::start(move |event| {
+            let _ = sender.send(event);
+        })?;
+        Ok((watcher, receiver))
+    }
+
+    /// Watch the events of `source`.
+    pub fn with_source<S, F>(mut source: S, on_event: F) -> io::Result<Self>
+    where
+        S: EventSource + 'static,
+        F: FnMut(TopologyEvent) + Send + 'static,
+    {
+        let mut watcher = Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            threads: Vec::new(),
+            callback: Arc::new(Mutex::new(on_event)),
+        };
+        let on_event = watcher.callback.clone();
+        watcher.spawn("display-watcher", on_event, move |timeout| {
+            Ok(source
+                .next_event(timeout)?
+                .as_ref()
+                .and_then(TopologyEvent::from_uevent))
+        })?;
+        Ok(watcher)
+    }
+
+    /// Run `next` on a thread of its own until the watcher stops or it
+    /// fails, passing on the events it returns.
+    fn spawn<N>(&mut self, name: &str, on_event: Callback, mut next: N) -> io::Result<()>
+    where
+        N: FnMut(Duration) -> io::Result<Option<TopologyEvent>> + Send + 'static,
+    {
+        let stopped = self.stop.clone();
+        let emit = move |event| {
+            let mut on_event = on_event.lock().unwrap_or_else(|e| e.into_inner());
+            on_event(event);
+        };
+        let thread = thread::Builder::new().name(name.into()).spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match next(STOP_CHECK) {
+                    Ok(Some(event)) => emit(event),
+                    Ok(None) => {}
+                    Err(e) if e.kind() == io::ErrorKind::OutOfMemory => {
+                        emit(TopologyEvent::Overflow);
+                    }
+                    Err(e) => {
+                        if e.kind() != io::ErrorKind::UnexpectedEof {
+                            log::warn!("Display watcher stopped: {e}");
+                        }
+                        break;
+                    }
+                }
+            }
+        })?;
+        self.threads.push(thread);
+        Ok(())
+    }
+}
+
+impl Drop for DisplayWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            // A callback dropping its own watcher cannot wait for itself.
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Sender;
+
+    use super::*;
+
+    const CARD: &str = "/devices/pci0000:00/0000:00:02.0/drm/card0";
+
+    fn watch() -> (DisplayWatcher, Sender<UEvent>, Receiver<TopologyEvent>) {
+        let (uevents, source) = mpsc::channel();
+        let (sender, events) = mpsc::channel();
+        let watcher = DisplayWatcher::with_source(source, move |event| {
+            let _ = sender.send(event);
+        })
+        .unwrap();
+        (watcher, uevents, events)
+    }
+
+    fn recv(events: &Receiver<TopologyEvent>) -> TopologyEvent {
+        events.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn reports_drm_card_events() {
+        let (_watcher, uevents, events) = watch();
+        let drm = [("SUBSYSTEM", "drm")];
+        uevents
+            .send(UEvent::new(
+                "change",
+                CARD,
+                &[("SUBSYSTEM", "drm"), ("HOTPLUG", "1"), ("CONNECTOR", "77")],
+            ))
+            .unwrap();
+        uevents.send(UEvent::new("add", CARD, &drm)).unwrap();
+        uevents.send(UEvent::new("remove", CARD, &drm)).unwrap();
+
+        let card = || "card0".to_string();
+        assert_eq!(
+            recv(&events),
+            TopologyEvent::Hotplug {
+                card: card(),
+                connector: Some(77)
+            }
+        );
+        assert_eq!(recv(&events), TopologyEvent::CardAdded { card: card() });
+        assert_eq!(recv(&events), TopologyEvent::CardRemoved { card: card() });
+    }
+
+    #[test]
+    fn ignores_other_devices_and_changes() {
+        let (_watcher, uevents, events) = watch();
+        let drm = [("SUBSYSTEM", "drm"), ("HOTPLUG", "1")];
+        // Another subsystem, a connector, a render node and a change
+        // that is not a hotplug.
+        uevents
+            .send(UEvent::new(
+                "change",
+                "/devices/virtual/net/lo",
+                &[("SUBSYSTEM", "net")],
+            ))
+            .unwrap();
+        uevents
+            .send(UEvent::new(
+                "change",
+                &format!("{CARD}/card0-HDMI-A-1"),
+                &drm,
+            ))
+            .unwrap();
+        uevents
+            .send(UEvent::new(
+                "change",
+                "/devices/pci0000:00/drm/renderD128",
+                &drm,
+            ))
+            .unwrap();
+        uevents
+            .send(UEvent::new("change", CARD, &[("SUBSYSTEM", "drm")]))
+            .unwrap();
+        uevents.send(UEvent::new("remove", CARD, &drm)).unwrap();
+
+        assert_eq!(
+            recv(&events),
+            TopologyEvent::CardRemoved {
+                card: "card0".into()
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn stops_when_the_source_ends() {
+        let (watcher, uevents, events) = watch();
+        drop(uevents);
+        drop(watcher);
+        assert!(events.recv_timeout(Duration::from_secs(5)).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Undoing system changes when the process is killed.
+//!
+//! Guards that change state outside the process (muted audio,
+//! suppressed notifications, a running capture child) undo it on drop,
+//! but SIGINT and SIGTERM end the process without running destructors.
+//! Such guards also [`register`] how to unwind themselves. The first
+//! registration installs handlers for whichever of the two signals
+//! still has its default action. They unwind every registered guard,
+//! newest first, and then let the signal take its course. A host that
+//! handles the signals itself keeps its handlers and calls
+//! [`unwind_all`] before it exits.
+//!
+//! The handlers only write the signal number to a pipe (the self-pipe
+//! trick); the unwinding runs on an ordinary thread reading it, where
+//! taking locks and running subprocesses is safe.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(unix)]
+mod signals;
+
+struct Entry {
+    id: u64,
+    name: &'static str,
+    unwind: Box<dyn FnOnce() + Send>,
+}
+
+struct Registry {
+    entries: Vec<Entry>,
+    /// Set once [`unwind_all`] has run; the process is on its way out.
+    exiting: bool,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    entries: Vec::new(),
+    exiting: false,
+});
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Keeps a guard registered. Dropping it unregisters the guard without
+/// unwinding it, for guards that have undone their change themselves.
+#[derive(Debug)]
+#[must_use = "dropping a registration unregisters the guard"]
+pub struct Registration {
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        registry().entries.retain(|e| e.id != self.id);
+    }
+}
+
+/// Run `unwind` if the process is ended by SIGINT or SIGTERM while the
+/// returned [`Registration`] is alive. `name` identifies the guard in
+/// logs.
+///
+/// `unwind` runs on the signal thread, possibly while the guard is
+/// being dropped elsewhere; the guard must make sure its change is only
+/// undone once.
+pub fn register(name: &'static str, unwind: impl FnOnce() + Send + 'static) -> Registration {
+    #[cfg(unix)]
+    signals::install();
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let entry = Entry {
+        id,
+        name,
+        unwind: Box::new(unwind),
+    };
+    let mut registry = registry();
+    if registry.exiting {
+        drop(registry);
+        // Too late to be unwound with the rest.
+        run(entry);
+    } else {
+        registry.entries.push(entry);
+    }
+    Registration { id }
+}
+
+/// Unwind every registered guard, newest first.
+///
+/// The signal handlers call this; exit paths that skip destructors,
+/// such as [`std::process::exit`], should call it first. Guards
+/// registered afterwards are unwound as soon as they register.
+pub fn unwind_all() {
+    let entries = {
+        let mut registry = registry();
+        registry.exiting = true;
+        std::mem::take(&mut registry.entries)
+    };
+    for entry in entries.into_iter().rev() {
+        run(entry);
+    }
+}
+
+fn run(entry: Entry) {
+    log::debug!("Unwinding {}", entry.name);
+    // One failing guard must not keep the others from unwinding.
+    if panic::catch_unwind(AssertUnwindSafe(entry.unwind)).is_err() {
+        log::warn!("Unwinding {} panicked", entry.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// Tests share the one registry, so they take turns, each starting
+    /// from an empty one.
+    fn fresh() -> MutexGuard<'static, ()> {
+        static SERIAL: Mutex<()> = Mutex::new(());
+        let serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        *registry() = Registry {
+            entries: Vec::new(),
+            exiting: false,
+        };
+        serial
+    }
+
+    /// Register a guard that logs `name` when unwound.
+    fn record(log: &Log, name: &'static str) -> Registration {
+        let log = Arc::clone(log);
+        register(name, move || log.lock().unwrap().push(name))
+    }
+
+    #[test]
+    fn unwinds_newest_first() {
+        let _serial = fresh();
+        let log = Log::default();
+        let _guards = [
+            record(&log, "first"),
+            record(&log, "second"),
+            record(&log, "third"),
+        ];
+        unwind_all();
+        assert_eq!(*log.lock().unwrap(), ["third", "second", "first"]);
+    }
+
+    #[test]
+    fn panicking_guard_does_not_stop_the_others() {
+        let _serial = fresh();
+        let log = Log::default();
+        let _first = record(&log, "first");
+        let _panics = register("panics", || panic!("guard failed"));
+        let _third = record(&log, "third");
+        unwind_all();
+        assert_eq!(*log.lock().unwrap(), ["third", "first"]);
+    }
+
+    #[test]
+    fn unwinds_late_registrations_at_once() {
+        let _serial = fresh();
+        let log = Log::default();
+        unwind_all();
+        let late = record(&log, "late");
+        assert_eq!(*log.lock().unwrap(), ["late"]);
+        drop(late);
+        unwind_all();
+        assert_eq!(*log.lock().unwrap(), ["late"]);
+    }
+
+    #[test]
+    fn dropped_registrations_are_not_unwound() {
+        let _serial = fresh();
+        let log = Log::default();
+        let first = record(&log, "first");
+        let _second = record(&log, "second");
+        drop(first);
+        assert!(log.lock().unwrap().is_empty());
+        unwind_all();
+        assert_eq!(*log.lock().unwrap(), ["second"]);
+    }
+}
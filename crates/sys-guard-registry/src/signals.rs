@@ -0,0 +1,179 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! SIGINT and SIGTERM handlers using the self-pipe trick.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::{FromRawFd, IntoRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Once;
+use std::{mem, ptr, thread};
+
+use libc::c_int;
+
+// Each libc names the pointer to the calling thread's errno its own way.
+#[cfg(target_os = "aix")]
+use libc::_Errno as errno_location;
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+use libc::___errno as errno_location;
+#[cfg(any(
+    target_os = "android",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "cygwin",
+    target_os = "nuttx",
+    target_env = "newlib",
+))]
+use libc::__errno as errno_location;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "l4re",
+    target_os = "emscripten",
+    target_os = "hurd",
+    target_os = "redox",
+    target_os = "dragonfly",
+))]
+use libc::__errno_location as errno_location;
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+use libc::__error as errno_location;
+#[cfg(target_os = "nto")]
+use libc::__get_errno_ptr as errno_location;
+#[cfg(target_os = "haiku")]
+use libc::_errnop as errno_location;
+
+/// Elsewhere errno cannot be saved. The handler's one write to a pipe
+/// with room in it does not fail, so it is left as it is.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "l4re",
+    target_os = "emscripten",
+    target_os = "hurd",
+    target_os = "redox",
+    target_os = "dragonfly",
+    target_os = "android",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "cygwin",
+    target_os = "nuttx",
+    target_env = "newlib",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "solaris",
+    target_os = "illumos",
+    target_os = "haiku",
+    target_os = "aix",
+    target_os = "nto",
+)))]
+unsafe fn errno_location() -> *mut c_int {
+    ptr::null_mut()
+}
+
+const SIGNALS: [c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+/// Write end of the pipe, for the handler.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+/// Set by the first signal caught.
+static CAUGHT: AtomicBool = AtomicBool::new(false);
+
+/// Install the handlers, once per process.
+pub(crate) fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        if let Err(e) = try_install() {
+            log::warn!("Failed to install signal handlers: {e}");
+        }
+    });
+}
+
+fn try_install() -> io::Result<()> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors.
+    cvt(unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+    // SAFETY: both descriptors were just created and nothing else owns
+    // them.
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    for fd in fds {
+        // SAFETY: `fd` is open.
+        cvt(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) })?;
+    }
+    // A full pipe must never block the handler.
+    // SAFETY: `fds[1]` is open.
+    cvt(unsafe { libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK) })?;
+
+    // The reader is running before any handler can write, so a caught
+    // signal is never left unanswered.
+    let mut read = File::from(read);
+    thread::Builder::new()
+        .name("signal-unwind".into())
+        .spawn(move || {
+            let mut signal = [0; 1];
+            if read.read_exact(&mut signal).is_ok() {
+                deliver(c_int::from(signal[0]));
+            }
+        })?;
+    PIPE.store(write.into_raw_fd(), Ordering::Release);
+
+    for signal in SIGNALS {
+        // SAFETY: zeroed is a valid `sigaction` to be filled in.
+        let mut old: libc::sigaction = unsafe { mem::zeroed() };
+        cvt(unsafe { libc::sigaction(signal, ptr::null(), &mut old) })?;
+        // Only a signal that would kill the process is taken over.
+        // Ignored on purpose, as under nohup, it stays ignored, and a
+        // host's own handler stays in charge: the process may go on
+        // after it, and the host unwinds with `unwind_all` if it exits.
+        if old.sa_sigaction != libc::SIG_DFL {
+            log::debug!("Signal {signal} is already handled, not unwinding on it");
+            continue;
+        }
+        // SAFETY: `handler` only does async-signal-safe work.
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            cvt(libc::sigaction(signal, &action, ptr::null_mut()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Unwind the guards, then deliver `signal` as if it had never been
+/// caught, which ends the process.
+fn deliver(signal: c_int) {
+    log::info!("Caught signal {signal}, unwinding guards");
+    crate::unwind_all();
+    // SAFETY: the default action was the one replaced, and `kill` has
+    // no memory safety requirements.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+    }
+}
+
+extern "C" fn handler(signal: c_int) {
+    // A second signal means unwinding is stuck or unwanted; leave now.
+    if CAUGHT.swap(true, Ordering::Relaxed) {
+        // SAFETY: `_exit` is async-signal-safe.
+        unsafe { libc::_exit(128 + signal) };
+    }
+    let byte = signal as u8;
+    // SAFETY: `write` is async-signal-safe and `byte` outlives the call.
+    // errno, where it can be reached, is put back for the code the
+    // signal interrupted.
+    unsafe {
+        let errno = errno_location();
+        let saved = errno.as_ref().copied();
+        libc::write(PIPE.load(Ordering::Acquire), ptr::from_ref(&byte).cast(), 1);
+        if let Some(saved) = saved {
+            *errno = saved;
+        }
+    }
+}
+
+fn cvt(ret: c_int) -> io::Result<()> {
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
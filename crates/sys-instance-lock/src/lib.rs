@@ -1,17 +1,397 @@
-use anyhow::Result;
-use image::DynamicImage;
-
-pub fn optimize_for_ai(image_data: &[u8]) -> Result<Vec<u8>> {
-    // 1. Load image
-    let img = image::load_from_memory(image_data)?;
-    
-    // 2. Resize if too large (saving tokens/bandwidth)
-    let resized = img.resize(1024, 1024, image::imageops::FilterType::Lanczos3);
-    
-    // 3. Convert back to bytes (e.g., JPEG)
-    let mut output = Vec::new();
-    let mut cursor = std::io::Cursor::new(&mut output);
-    resized.write_to(&mut cursor, image::ImageOutputFormat::Jpeg(80))?;
-    
-    Ok(output)
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Single-instance lock with argument forwarding.
+//!
+//! The first process to start takes an exclusive `flock` on
+//! `<name>.lock` in `$XDG_RUNTIME_DIR` and listens on the Unix domain
+//! socket `<name>.sock` next to it. Without a runtime dir both go in a
+//! `<name>-<uid>` directory in the temp dir, private to the user. Later launches find the lock held,
+//! send their working directory and arguments over the socket, wait for
+//! the running instance to acknowledge them and exit.
+//!
+//! The kernel releases a `flock` when its holder dies, so a crashed
+//! instance never leaves a held lock behind. The socket file it leaves
+//! is removed by the next instance to take the lock before it binds its
+//! own.
+
+#![cfg(unix)]
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How long a second launch keeps trying to reach an instance that
+/// holds the lock but has not started listening yet.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const CONNECT_RETRY: Duration = Duration::from_millis(50);
+
+/// How long either side waits on the other once connected.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Limits on a forwarded request, so a bad client cannot make the
+/// running instance allocate without bound.
+const MAX_FIELDS: u32 = 1024;
+const MAX_FIELD_LEN: u32 = 64 * 1024;
+
+/// Arguments passed on by a later launch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Working directory of the launching process.
+    pub cwd: PathBuf,
+    /// Arguments, without the program name.
+    pub args: Vec<OsString>,
+}
+
+/// Outcome of [`InstanceLock::acquire`].
+pub enum Instance {
+    /// This process holds the lock and should run.
+    Primary(InstanceLock),
+    /// Another instance is running and has taken our arguments; this
+    /// process should exit.
+    Secondary,
+}
+
+/// The held lock. Released when dropped or when the process exits.
+pub struct InstanceLock {
+    /// Holds the `flock`; closing it releases the lock.
+    _file: File,
+    socket: PathBuf,
+    listener: Option<UnixListener>,
+}
+
+impl InstanceLock {
+    /// Take the lock for `name` in `$XDG_RUNTIME_DIR`, or forward `args`
+    /// to the instance holding it.
+    pub fn acquire(name: &str, args: Vec<OsString>) -> io::Result<Instance> {
+        Self::acquire_in(&runtime_dir(name)?, name, args)
+    }
+
+    /// Like [`InstanceLock::acquire`], with the lock and socket in `dir`.
+    pub fn acquire_in(dir: &Path, name: &str, args: Vec<OsString>) -> io::Result<Instance> {
+        fs::create_dir_all(dir)?;
+        let lock_path = dir.join(format!("{name}.lock"));
+        let socket = dir.join(format!("{name}.sock"));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            // A symlink planted here would have us write the pid into
+            // whatever it points at.
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&lock_path)?;
+
+        if !try_lock(&file)? {
+            let request = Request {
+                cwd: std::env::current_dir()?,
+                args,
+            };
+            forward(&socket, &request)?;
+            return Ok(Instance::Secondary);
+        }
+
+        // Record the holder for anyone inspecting the runtime dir. The
+        // lock itself is what counts.
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+
+        // Whatever socket file is here belongs to a dead instance, since
+        // its lock was free.
+        match fs::remove_file(&socket) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let listener = UnixListener::bind(&socket)?;
+        fs::set_permissions(&socket, fs::Permissions::from_mode(0o600))?;
+
+        Ok(Instance::Primary(InstanceLock {
+            _file: file,
+            socket,
+            listener: Some(listener),
+        }))
+    }
+
+    /// Serve forwarded requests on a background thread, calling
+    /// `handler` for each one. Can only be called once.
+    pub fn listen<F>(&mut self, mut handler: F) -> io::Result<JoinHandle<()>>
+    where
+        F: FnMut(Request) + Send + 'static,
+    {
+        let listener = self
+            .listener
+            .take()
+            .ok_or_else(|| io::Error::other("already listening"))?;
+        thread::Builder::new()
+            .name("instance-lock".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { continue };
+                    // A client that hangs up or sends garbage only loses
+                    // its own request.
+                    if let Ok(request) = receive(&mut stream) {
+                        handler(request);
+                        let _ = stream.write_all(&[1]);
+                    }
+                }
+            })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // The lock file stays: unlinking it would let a new instance
+        // lock a fresh file while a late client still holds the old one.
+        let _ = fs::remove_file(&self.socket);
+    }
+}
+
+/// Take an exclusive `flock` on `file` without blocking. Returns false
+/// when another process holds it.
+fn try_lock(file: &File) -> io::Result<bool> {
+    loop {
+        // SAFETY: the descriptor is open for as long as `file` lives.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+            return Ok(true);
+        }
+        let e = io::Error::last_os_error();
+        match e.kind() {
+            io::ErrorKind::WouldBlock => return Ok(false),
+            io::ErrorKind::Interrupted => continue,
+            _ => return Err(e),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR`, or a `<name>-<uid>` directory of this user's in
+/// the temp dir when it is unset.
+fn runtime_dir(name: &str) -> io::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    let dir = std::env::temp_dir().join(format!("{name}-{uid}"));
+    private_dir(&dir, uid)?;
+    Ok(dir)
+}
+
+/// Create `dir` if needed, readable by this user only, and make sure it
+/// is not someone else's or open to everyone, as anyone can create it
+/// first in the temp dir.
+fn private_dir(dir: &Path, uid: u32) -> io::Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not private to this user", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Send `request` to the instance listening on `socket` and wait for
+/// its acknowledgement.
+fn forward(socket: &Path, request: &Request) -> io::Result<()> {
+    let mut waited = Duration::ZERO;
+    let mut stream = loop {
+        match UnixStream::connect(socket) {
+            Ok(stream) => break stream,
+            // The holder may still be starting up.
+            Err(e) if waited < CONNECT_TIMEOUT => {
+                if !matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) {
+                    return Err(e);
+                }
+                thread::sleep(CONNECT_RETRY);
+                waited += CONNECT_RETRY;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut message = Vec::new();
+    let fields: Vec<&[u8]> = std::iter::once(request.cwd.as_os_str())
+        .chain(request.args.iter().map(OsString::as_os_str))
+        .map(|field| field.as_bytes())
+        .collect();
+    message.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+    for field in fields {
+        message.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        message.extend_from_slice(field);
+    }
+    stream.write_all(&message)?;
+
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack)
+}
+
+/// Read one request: a field count, then each field as a length and its
+/// bytes, all lengths little-endian `u32`. The first field is the
+/// working directory.
+fn receive(stream: &mut UnixStream) -> io::Result<Request> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let read_u32 = |stream: &mut UnixStream| {
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).map(|_| u32::from_le_bytes(buf))
+    };
+
+    let count = read_u32(stream)?;
+    if count == 0 || count > MAX_FIELDS {
+        return Err(invalid("bad field count"));
+    }
+    let mut fields = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let len = read_u32(stream)?;
+        if len > MAX_FIELD_LEN {
+            return Err(invalid("field too long"));
+        }
+        let mut field = vec![0u8; len as usize];
+        stream.read_exact(&mut field)?;
+        fields.push(OsString::from_vec(field));
+    }
+    let mut fields = fields.into_iter();
+    let cwd = fields.next().map(PathBuf::from).unwrap_or_default();
+    Ok(Request {
+        cwd,
+        args: fields.collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ocrmyimg-instance-lock-test-{}-{test}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Take the lock in `dir` and pass forwarded requests to the
+    /// returned channel.
+    fn primary(dir: &Path) -> (InstanceLock, mpsc::Receiver<Request>) {
+        let Instance::Primary(mut lock) = InstanceLock::acquire_in(dir, "app", vec![]).unwrap()
+        else {
+            panic!("the lock was free");
+        };
+        let (sender, receiver) = mpsc::channel();
+        lock.listen(move |request| sender.send(request).unwrap())
+            .unwrap();
+        (lock, receiver)
+    }
+
+    #[test]
+    fn forwards_a_second_launch() {
+        let dir = temp_dir("forward");
+        let (_lock, requests) = primary(&dir);
+
+        let args = vec![
+            OsString::from("--open"),
+            OsString::from("scan 1.png"),
+            OsString::from_vec(vec![b'x', 0xff, b'y']),
+        ];
+        let second = InstanceLock::acquire_in(&dir, "app", args.clone()).unwrap();
+        assert!(matches!(second, Instance::Secondary));
+
+        let request = requests.recv_timeout(IO_TIMEOUT).unwrap();
+        assert_eq!(request.cwd, std::env::current_dir().unwrap());
+        assert_eq!(request.args, args);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recovers_from_a_stale_socket() {
+        let dir = temp_dir("stale");
+        // A crashed instance leaves its socket, with nobody listening.
+        drop(UnixListener::bind(dir.join("app.sock")).unwrap());
+        fs::write(dir.join("app.lock"), "1\n").unwrap();
+
+        let (_lock, requests) = primary(&dir);
+        let second = InstanceLock::acquire_in(&dir, "app", vec!["a".into()]).unwrap();
+        assert!(matches!(second, Instance::Secondary));
+        assert_eq!(requests.recv_timeout(IO_TIMEOUT).unwrap().args, ["a"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn refuses_a_symlinked_lock_file() {
+        let dir = temp_dir("symlink");
+        let target = dir.join("target");
+        fs::write(&target, "keep").unwrap();
+        std::os::unix::fs::symlink(&target, dir.join("app.lock")).unwrap();
+        assert!(InstanceLock::acquire_in(&dir, "app", vec![]).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_oversized_requests() {
+        let send = |message: Vec<u8>| {
+            let (mut client, mut server) = UnixStream::pair().unwrap();
+            // From another thread, as the message may not fit in the
+            // socket buffer.
+            let writer = thread::spawn(move || client.write_all(&message));
+            let request = receive(&mut server);
+            drop(server);
+            let _ = writer.join();
+            request
+        };
+        let header = |count: u32, len: u32| [count.to_le_bytes(), len.to_le_bytes()].concat();
+
+        let error = send(header(MAX_FIELDS + 1, 0)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = send(header(0, 0)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = send(header(1, MAX_FIELD_LEN + 1)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut message = header(2, 1);
+        message.extend_from_slice(b"/");
+        message.extend_from_slice(&MAX_FIELD_LEN.to_le_bytes());
+        message.extend(vec![b'a'; MAX_FIELD_LEN as usize]);
+        let request = send(message).unwrap();
+        assert_eq!(request.cwd, Path::new("/"));
+        assert_eq!(request.args[0].len(), MAX_FIELD_LEN as usize);
+    }
+
+    #[test]
+    fn checks_the_fallback_dir_is_private() {
+        let dir = temp_dir("private").join("app-uid");
+        // SAFETY: geteuid has no preconditions and cannot fail.
+        let uid = unsafe { libc::geteuid() };
+        private_dir(&dir, uid).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        let error = private_dir(&dir, uid).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(private_dir(&dir, uid.wrapping_add(1)).is_err());
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+    }
 }
@@ -0,0 +1,385 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Muting the output while a capture runs.
+//!
+//! The guard tries each [`AudioBackend`] in turn until one mutes. On
+//! Linux only event sounds are muted when the sound server allows it,
+//! so music or a call keeps playing. Muting the whole default sink over
+//! the native protocol is the next resort, and the command-line tools
+//! the fallback when no server speaks it: `pactl`, `wpctl`, then ALSA's
+//! `amixer`.
+//!
+//! SIGINT and SIGTERM restore the output through the guard registry
+//! before the process exits. For deaths nothing can catch, the state to
+//! put back is journaled before muting, so audio muted by a process
+//! that never got to restore it is recovered on the next launch. While
+//! the process that journaled it is alive, the output is left to that
+//! process's guard.
+
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use sys_guard_registry::Registration;
+
+use crate::backend::{default_backends, AudioBackend};
+use crate::journal::{Journal, Snapshot};
+
+/// Mutes the output while alive, and when the process is killed by
+/// SIGINT or SIGTERM, restores it before the process exits.
+pub struct AudioGuard {
+    /// Shared with the signal handler's unwind.
+    state: Arc<Mutex<State>>,
+    registration: Option<Registration>,
+}
+
+impl AudioGuard {
+    // Creating a guard mutes the output, which a `Default` would hide.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::with_backends(default_backends(), Journal::default())
+    }
+
+    /// Mute with the first of `backends` that works, most preferred
+    /// first, journaling to `journal`.
+    ///
+    /// When a guard in another running process has already muted the
+    /// output, this one does nothing: muting again would replace that
+    /// guard's journal, and the output is silent until it restores.
+    pub fn with_backends(backends: Vec<Box<dyn AudioBackend>>, journal: Journal) -> Self {
+        let mut state = State {
+            backends,
+            journal,
+            active: None,
+        };
+        if let Some(pid) = state.journal.live_owner() {
+            log::info!("Audio is already muted by process {pid}");
+        } else {
+            // A leftover journal means the output is still muted from
+            // last time; reading its state now would keep it muted for
+            // good.
+            if let Err(e) = state.recover() {
+                log::warn!("Failed to recover audio from the journal: {e}");
+            }
+            state.mute();
+        }
+
+        let muted = state.active.is_some();
+        let state = Arc::new(Mutex::new(state));
+        let registration = muted.then(|| {
+            let state = Arc::clone(&state);
+            sys_guard_registry::register("audio mute", move || lock(&state).restore())
+        });
+        AudioGuard {
+            state,
+            registration,
+        }
+    }
+
+    /// Restore the audio state left in the default journal by a run
+    /// that did not get to restore it. Returns whether there was one;
+    /// a journal whose process is still running is not.
+    pub fn recover() -> io::Result<bool> {
+        State {
+            backends: default_backends(),
+            journal: Journal::default(),
+            active: None,
+        }
+        .recover()
+    }
+
+    /// Whether the output is muted by this guard.
+    pub fn is_active(&self) -> bool {
+        lock(&self.state).active.is_some()
+    }
+}
+
+impl Drop for AudioGuard {
+    fn drop(&mut self) {
+        // Unregistered first; if a signal got there before, the output
+        // is already restored and `active` empty.
+        self.registration.take();
+        lock(&self.state).restore();
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct State {
+    backends: Vec<Box<dyn AudioBackend>>,
+    journal: Journal,
+    /// The backend that muted the output and what it saved.
+    active: Option<(usize, Snapshot)>,
+}
+
+impl State {
+    fn recover(&mut self) -> io::Result<bool> {
+        if self.journal.live_owner().is_some() {
+            return Ok(false);
+        }
+        let snapshot = match self.journal.read() {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                // Nothing can be done with it; don't trip over it again.
+                self.journal.remove()?;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+        let backend = self
+            .backends
+            .iter_mut()
+            .find(|b| b.name() == snapshot.backend)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unknown audio backend {}", snapshot.backend),
+                )
+            })?;
+        log::info!("Restoring audio left muted by a previous run");
+        backend.restore(&snapshot)?;
+        self.journal.remove()?;
+        Ok(true)
+    }
+
+    fn mute(&mut self) {
+        for (i, backend) in self.backends.iter_mut().enumerate() {
+            let snapshot = match backend.snapshot() {
+                Ok(Some(snapshot)) => snapshot,
+                // Already silent; nothing to do or undo.
+                Ok(None) => return,
+                Err(e) => {
+                    log::debug!("Audio backend {} unavailable: {e}", backend.name());
+                    continue;
+                }
+            };
+            if let Err(e) = self.journal.write(&snapshot) {
+                log::warn!("Failed to write the audio journal: {e}");
+            }
+            let Err(e) = backend.mute(&snapshot) else {
+                self.active = Some((i, snapshot));
+                return;
+            };
+            log::debug!("Audio backend {} failed to mute: {e}", backend.name());
+            // Undo whatever it changed before failing, so the next
+            // backend starts from the user's state.
+            if let Err(e) = backend.restore(&snapshot) {
+                // Trying another backend would overwrite the journal;
+                // keep what was changed and restore it on drop.
+                log::warn!("Failed to undo a partial mute: {e}");
+                self.active = Some((i, snapshot));
+                return;
+            }
+            let _ = self.journal.remove();
+        }
+    }
+
+    /// Put back the output this muted, if any.
+    fn restore(&mut self) {
+        let Some((i, snapshot)) = self.active.take() else {
+            return;
+        };
+        match self.backends[i].restore(&snapshot) {
+            Ok(()) => {
+                if let Err(e) = self.journal.remove() {
+                    log::warn!("Failed to remove the audio journal: {e}");
+                }
+            }
+            // The journal stays for the next launch to retry.
+            Err(e) => log::warn!("Failed to restore audio: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::backend::{FakeBackend, FakeOutput};
+
+    /// A journal in a fresh directory of its own.
+    fn journal(test: &str) -> (Journal, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("ocrmyimg-audio-test-{}-{test}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("journal");
+        (Journal::new(path.clone()), path)
+    }
+
+    type Outputs = Vec<Arc<Mutex<FakeOutput>>>;
+
+    /// Fake backends named `names`, with their outputs.
+    fn fakes(names: &[&'static str]) -> (Vec<Box<dyn AudioBackend>>, Outputs) {
+        let fakes: Vec<FakeBackend> = names.iter().map(|&name| FakeBackend::new(name)).collect();
+        let outputs = fakes.iter().map(FakeBackend::output).collect();
+        let backends = fakes
+            .into_iter()
+            .map(|fake| Box::new(fake) as Box<dyn AudioBackend>)
+            .collect();
+        (backends, outputs)
+    }
+
+    #[test]
+    fn mutes_and_restores() {
+        let (journal, path) = journal("mutes_and_restores");
+        let (backends, outputs) = fakes(&["first", "second"]);
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert!(guard.is_active());
+        assert!(outputs[0].lock().unwrap().mute);
+        assert!(path.exists());
+        // The first backend to work is the only one used.
+        assert!(outputs[1].lock().unwrap().calls.is_empty());
+
+        drop(guard);
+        let first = outputs[0].lock().unwrap();
+        assert!(!first.mute);
+        assert_eq!(first.calls, ["snapshot", "mute", "restore"]);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn skips_unavailable_backends() {
+        let (journal, _) = journal("skips_unavailable_backends");
+        let (backends, outputs) = fakes(&["first", "second"]);
+        outputs[0].lock().unwrap().fail_snapshot = true;
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert!(outputs[1].lock().unwrap().mute);
+        drop(guard);
+        assert_eq!(outputs[0].lock().unwrap().calls, ["snapshot"]);
+        assert!(!outputs[1].lock().unwrap().mute);
+    }
+
+    #[test]
+    fn leaves_muted_output_alone() {
+        let (journal, path) = journal("leaves_muted_output_alone");
+        let (backends, outputs) = fakes(&["first", "second"]);
+        outputs[0].lock().unwrap().mute = true;
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert!(!guard.is_active());
+        drop(guard);
+        // Still muted, as the user left it.
+        assert!(outputs[0].lock().unwrap().mute);
+        assert_eq!(outputs[0].lock().unwrap().calls, ["snapshot"]);
+        assert!(outputs[1].lock().unwrap().calls.is_empty());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn undoes_failed_mute_before_falling_back() {
+        let (journal, path) = journal("undoes_failed_mute_before_falling_back");
+        let (backends, outputs) = fakes(&["first", "second"]);
+        outputs[0].lock().unwrap().fail_mute = true;
+        let guard = AudioGuard::with_backends(backends, journal);
+        {
+            let first = outputs[0].lock().unwrap();
+            assert!(!first.mute);
+            assert_eq!(first.calls, ["snapshot", "mute", "restore"]);
+        }
+        assert!(outputs[1].lock().unwrap().mute);
+        drop(guard);
+        assert!(!outputs[1].lock().unwrap().mute);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn keeps_partial_mute_that_cannot_be_undone() {
+        let (journal, path) = journal("keeps_partial_mute_that_cannot_be_undone");
+        let (backends, outputs) = fakes(&["first", "second"]);
+        {
+            let mut first = outputs[0].lock().unwrap();
+            first.fail_mute = true;
+            first.fail_restore = true;
+        }
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert!(guard.is_active());
+        // Falling back would overwrite the journal of the first.
+        assert!(outputs[1].lock().unwrap().calls.is_empty());
+        drop(guard);
+        // Restoring failed again, so the journal stays for next time.
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn recovers_from_journal() {
+        let (journal, path) = journal("recovers_from_journal");
+        let (backends, outputs) = fakes(&["first"]);
+        let guard = AudioGuard::with_backends(backends, journal.clone());
+        // The process dies without dropping the guard.
+        std::mem::forget(guard);
+        assert!(outputs[0].lock().unwrap().mute);
+        assert!(path.exists());
+
+        // On the next launch the output is still muted.
+        let (backends, outputs) = fakes(&["first"]);
+        outputs[0].lock().unwrap().mute = true;
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert_eq!(
+            outputs[0].lock().unwrap().calls,
+            ["restore", "snapshot", "mute"]
+        );
+        drop(guard);
+        assert!(!outputs[0].lock().unwrap().mute);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn leaves_output_muted_by_a_live_process_alone() {
+        let (journal, path) = journal("leaves_output_muted_by_a_live_process_alone");
+        let mut other = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let record = format!("pid={}\nbackend=first\nvolume=100\nmute=0\n", other.id());
+        std::fs::write(&path, record).unwrap();
+
+        let (backends, outputs) = fakes(&["first"]);
+        outputs[0].lock().unwrap().mute = true;
+        let guard = AudioGuard::with_backends(backends, journal.clone());
+        assert!(!guard.is_active());
+        drop(guard);
+        assert!(outputs[0].lock().unwrap().calls.is_empty());
+        assert!(path.exists());
+
+        // Once it has died without restoring, its journal is stale.
+        other.kill().unwrap();
+        other.wait().unwrap();
+        let (backends, outputs) = fakes(&["first"]);
+        outputs[0].lock().unwrap().mute = true;
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert_eq!(
+            outputs[0].lock().unwrap().calls,
+            ["restore", "snapshot", "mute"]
+        );
+        drop(guard);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn ignores_journal_of_unknown_backend() {
+        let (journal, path) = journal("ignores_journal_of_unknown_backend");
+        std::fs::write(&path, "backend=gone\nmute=0\n").unwrap();
+        let (backends, outputs) = fakes(&["first"]);
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert_eq!(outputs[0].lock().unwrap().calls, ["snapshot", "mute"]);
+        drop(guard);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn discards_malformed_journal() {
+        let (journal, path) = journal("discards_malformed_journal");
+        std::fs::write(&path, "garbage").unwrap();
+        let (backends, outputs) = fakes(&["first"]);
+        let guard = AudioGuard::with_backends(backends, journal);
+        assert!(guard.is_active());
+        assert_eq!(outputs[0].lock().unwrap().calls, ["snapshot", "mute"]);
+        drop(guard);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! The ways of muting the output.
+//!
+//! Each [`AudioBackend`] reads the output's state into a [`Snapshot`],
+//! mutes it, and puts a snapshot back. The guard tries them in order of
+//! preference and restores with whichever one muted. Command-line tools
+//! are run through a [`Runner`], so tests can answer with recorded
+//! output instead.
+
+use std::io;
+use std::process::Command;
+
+use crate::journal::Snapshot;
+
+mod cli;
+mod fake;
+#[cfg(target_os = "linux")]
+mod native;
+
+#[cfg(target_os = "macos")]
+pub use cli::Osascript;
+pub use cli::{Amixer, Pactl, Wpctl};
+pub use fake::{FakeBackend, FakeOutput};
+#[cfg(target_os = "linux")]
+pub use native::{EventSounds, PulseSink};
+
+/// One way of muting the output.
+pub trait AudioBackend: Send {
+    /// Name recorded in the journal, so a later run restores with the
+    /// same backend.
+    fn name(&self) -> &'static str;
+
+    /// Current state of the output, or `None` when it is already
+    /// silent. Fails when the backend is not available.
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>>;
+
+    /// Silence the output whose state `snapshot` describes.
+    fn mute(&mut self, snapshot: &Snapshot) -> io::Result<()>;
+
+    /// Put the output back to `snapshot`.
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()>;
+}
+
+/// The backends for this platform, most targeted first.
+pub fn default_backends() -> Vec<Box<dyn AudioBackend>> {
+    #[allow(unused_mut)]
+    let mut backends: Vec<Box<dyn AudioBackend>> = Vec::new();
+    #[cfg(target_os = "linux")]
+    {
+        backends.push(Box::new(EventSounds));
+        backends.push(Box::new(PulseSink));
+        backends.push(Box::new(Pactl::default()));
+        backends.push(Box::new(Wpctl::default()));
+        backends.push(Box::new(Amixer::default()));
+    }
+    #[cfg(target_os = "macos")]
+    backends.push(Box::new(Osascript::default()));
+    backends
+}
+
+/// Runs command-line tools.
+pub trait Runner: Send {
+    /// Run `program` with `args` and return what it printed. Fails when
+    /// it cannot be started or exits unsuccessfully.
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<String>;
+}
+
+impl<F> Runner for F
+where
+    F: FnMut(&str, &[&str]) -> io::Result<String> + Send,
+{
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<String> {
+        self(program, args)
+    }
+}
+
+/// Runs the real tools, in the C locale so their output parses the
+/// same everywhere.
+#[derive(Debug, Default)]
+pub struct SystemRunner;
+
+impl Runner for SystemRunner {
+    fn run(&mut self, program: &str, args: &[&str]) -> io::Result<String> {
+        let output = Command::new(program)
+            .args(args)
+            .env("LC_ALL", "C")
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "{program} exited with {}",
+                output.status
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Comma-separated list, as stored in snapshots.
+pub(crate) fn join<T: ToString>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(T::to_string).collect();
+    values.join(",")
+}
+
+pub(crate) fn split<T: std::str::FromStr>(list: &str) -> io::Result<Vec<T>> {
+    list.split(',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().map_err(|_| malformed()))
+        .collect()
+}
+
+pub(crate) fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed audio snapshot")
+}
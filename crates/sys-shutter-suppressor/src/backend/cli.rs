@@ -0,0 +1,602 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Backends that drive the sound server's command-line tools.
+//!
+//! The tools are run in the C locale, and their output is parsed
+//! strictly: text that does not look like the C locale's, such as a
+//! translated `pactl` that ignores it, fails the snapshot so the next
+//! backend is tried, rather than being misread as unmuted.
+
+use std::io;
+
+use super::{join, malformed, AudioBackend, Runner, SystemRunner};
+use crate::journal::Snapshot;
+
+macro_rules! cli_backend {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        pub struct $name {
+            runner: Box<dyn Runner>,
+        }
+
+        impl Default for $name {
+            /// Runs the installed tool.
+            fn default() -> Self {
+                Self::with_runner(SystemRunner)
+            }
+        }
+
+        impl $name {
+            /// Runs the tool through `runner` instead.
+            pub fn with_runner(runner: impl Runner + 'static) -> Self {
+                Self {
+                    runner: Box::new(runner),
+                }
+            }
+        }
+    };
+}
+
+cli_backend!(
+    /// PulseAudio's `pactl`, which pipewire-pulse also answers.
+    Pactl
+);
+
+cli_backend!(
+    /// WirePlumber's `wpctl`, for PipeWire without the PulseAudio
+    /// compatibility layer.
+    Wpctl
+);
+
+cli_backend!(
+    /// ALSA's `amixer`, driving the `Master` control of the default
+    /// card.
+    Amixer
+);
+
+#[cfg(target_os = "macos")]
+cli_backend!(
+    /// `osascript`, toggling the system output mute.
+    Osascript
+);
+
+const PACTL_SINK: &str = "@DEFAULT_SINK@";
+const WPCTL_SINK: &str = "@DEFAULT_AUDIO_SINK@";
+const AMIXER_CONTROL: &str = "Master";
+
+impl AudioBackend for Pactl {
+    fn name(&self) -> &'static str {
+        "pactl"
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        // By name, so the same sink is restored even if the default
+        // changes meanwhile. pactl before 15.0 has no get-default-sink
+        // and keeps addressing whichever sink is the default.
+        let name = (self.runner.run("pactl", &["get-default-sink"]).ok())
+            .map(|out| out.trim().to_string())
+            .filter(|name| !name.is_empty());
+        let sink = name.as_deref().unwrap_or(PACTL_SINK);
+
+        let mute = self.runner.run("pactl", &["get-sink-mute", sink])?;
+        if parse_pactl_mute(&mute).ok_or_else(malformed)? {
+            return Ok(None);
+        }
+        // Kept in case muting also zeroes the volume, as some
+        // ALSA-backed sinks do.
+        let volume = self.runner.run("pactl", &["get-sink-volume", sink])?;
+        let channels = parse_pactl_volume(&volume).ok_or_else(malformed)?;
+        let (names, volumes): (Vec<&str>, Vec<u32>) = channels.into_iter().unzip();
+        Ok(Some(Snapshot {
+            backend: self.name().into(),
+            sink: name,
+            volume: Some(join(&volumes)),
+            channel_map: Some(names.join(",")),
+            ..Snapshot::default()
+        }))
+    }
+
+    fn mute(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let sink = snapshot.sink.as_deref().unwrap_or(PACTL_SINK);
+        self.runner
+            .run("pactl", &["set-sink-mute", sink, "1"])
+            .map(drop)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let sink = snapshot.sink.as_deref().unwrap_or(PACTL_SINK);
+        if let Some(volume) = &snapshot.volume {
+            // One value per channel, in the sink's channel order.
+            let mut args = vec!["set-sink-volume", sink];
+            args.extend(volume.split(','));
+            self.runner.run("pactl", &args)?;
+        }
+        let mute = if snapshot.mute { "1" } else { "0" };
+        self.runner
+            .run("pactl", &["set-sink-mute", sink, mute])
+            .map(drop)
+    }
+}
+
+impl AudioBackend for Wpctl {
+    fn name(&self) -> &'static str {
+        "wpctl"
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        let out = self.runner.run("wpctl", &["get-volume", WPCTL_SINK])?;
+        let (volume, mute) = parse_wpctl_volume(&out).ok_or_else(malformed)?;
+        if mute {
+            return Ok(None);
+        }
+        Ok(Some(Snapshot {
+            backend: self.name().into(),
+            volume: Some(volume.to_string()),
+            ..Snapshot::default()
+        }))
+    }
+
+    fn mute(&mut self, _: &Snapshot) -> io::Result<()> {
+        self.runner
+            .run("wpctl", &["set-mute", WPCTL_SINK, "1"])
+            .map(drop)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        // wpctl only reports the average over channels, so per-channel
+        // balance is not restored.
+        if let Some(volume) = &snapshot.volume {
+            self.runner
+                .run("wpctl", &["set-volume", WPCTL_SINK, volume])?;
+        }
+        let mute = if snapshot.mute { "1" } else { "0" };
+        self.runner
+            .run("wpctl", &["set-mute", WPCTL_SINK, mute])
+            .map(drop)
+    }
+}
+
+impl AudioBackend for Amixer {
+    fn name(&self) -> &'static str {
+        "amixer"
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        let out = self.runner.run("amixer", &["get", AMIXER_CONTROL])?;
+        let channels = parse_amixer(&out).ok_or_else(malformed)?;
+        if channels.iter().all(|c| !c.on) {
+            return Ok(None);
+        }
+        let names: Vec<&str> = channels.iter().map(|c| c.name).collect();
+        let volumes: Vec<u32> = channels.iter().map(|c| c.volume).collect();
+        Ok(Some(Snapshot {
+            backend: self.name().into(),
+            volume: Some(join(&volumes)),
+            channel_map: Some(names.join(",")),
+            ..Snapshot::default()
+        }))
+    }
+
+    fn mute(&mut self, _: &Snapshot) -> io::Result<()> {
+        self.runner
+            .run("amixer", &["-q", "sset", AMIXER_CONTROL, "mute"])
+            .map(drop)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        if let Some(volume) = &snapshot.volume {
+            // amixer takes comma-separated values for the channels in
+            // the order it lists them.
+            self.runner
+                .run("amixer", &["-q", "sset", AMIXER_CONTROL, volume])?;
+        }
+        let mute = if snapshot.mute { "mute" } else { "unmute" };
+        self.runner
+            .run("amixer", &["-q", "sset", AMIXER_CONTROL, mute])
+            .map(drop)
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl AudioBackend for Osascript {
+    fn name(&self) -> &'static str {
+        "osascript"
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        let out = self.runner.run(
+            "osascript",
+            &["-e", "output muted of (get volume settings)"],
+        )?;
+        // "missing value" when the output device has no mute control.
+        let mute = match out.trim() {
+            "true" => true,
+            "false" => false,
+            _ => return Err(malformed()),
+        };
+        if mute {
+            return Ok(None);
+        }
+        Ok(Some(Snapshot {
+            backend: self.name().into(),
+            ..Snapshot::default()
+        }))
+    }
+
+    fn mute(&mut self, _: &Snapshot) -> io::Result<()> {
+        self.runner
+            .run("osascript", &["-e", "set volume with output muted"])
+            .map(drop)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let script = if snapshot.mute {
+            "set volume with output muted"
+        } else {
+            "set volume without output muted"
+        };
+        self.runner.run("osascript", &["-e", script]).map(drop)
+    }
+}
+
+/// Mute state from `pactl get-sink-mute`, e.g. `Mute: no`.
+fn parse_pactl_mute(out: &str) -> Option<bool> {
+    match out.trim().strip_prefix("Mute:")?.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Channel names and raw volumes from `pactl get-sink-volume`, e.g.
+///
+/// ```text
+/// Volume: front-left: 42597 /  65% / -11.23 dB,   front-right: 42597 /  65% / -11.23 dB
+///         balance 0.00
+/// ```
+///
+/// Only the channel entries are read, so translated labels and decimal
+/// commas do not matter.
+fn parse_pactl_volume(out: &str) -> Option<Vec<(&str, u32)>> {
+    let tokens: Vec<&str> = out.split_whitespace().collect();
+    let channels: Vec<(&str, u32)> = tokens
+        .windows(4)
+        .filter_map(|w| {
+            let name = w[0].strip_suffix(':')?;
+            let raw = w[1].parse().ok()?;
+            (w[2] == "/" && w[3].ends_with('%')).then_some((name, raw))
+        })
+        .collect();
+    (!channels.is_empty()).then_some(channels)
+}
+
+/// Linear volume and mute state from `wpctl get-volume`, e.g.
+/// `Volume: 0.40 [MUTED]`.
+fn parse_wpctl_volume(out: &str) -> Option<(f64, bool)> {
+    let mut tokens = out.trim().strip_prefix("Volume:")?.split_whitespace();
+    let volume = tokens.next()?.parse().ok()?;
+    let mute = match tokens.next() {
+        None => false,
+        Some("[MUTED]") => true,
+        Some(_) => return None,
+    };
+    Some((volume, mute))
+}
+
+/// One playback channel of an `amixer` control.
+#[derive(Debug, PartialEq)]
+struct AmixerChannel<'a> {
+    name: &'a str,
+    volume: u32,
+    /// Whether the channel's switch is on; true for controls without
+    /// one.
+    on: bool,
+}
+
+/// Playback channels from `amixer get`, from lines such as
+///
+/// ```text
+///   Front Left: Playback 57 [66%] [-22.50dB] [on]
+/// ```
+fn parse_amixer(out: &str) -> Option<Vec<AmixerChannel<'_>>> {
+    let channels: Vec<AmixerChannel> = out
+        .lines()
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(": Playback ")?;
+            let mut fields = rest.split_whitespace();
+            let volume = fields.next()?.parse().ok()?;
+            let fields: Vec<&str> = fields.collect();
+            // Rules out "Limits: Playback 0 - 87".
+            if !fields.first()?.ends_with("%]") {
+                return None;
+            }
+            Some(AmixerChannel {
+                name: name.trim(),
+                volume,
+                on: !fields.contains(&"[off]"),
+            })
+        })
+        .collect();
+    (!channels.is_empty()).then_some(channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// A runner answering from `replies` by command line, logging every
+    /// command it is given.
+    fn scripted(
+        replies: &'static [(&'static str, &'static str)],
+    ) -> (impl Runner, Arc<Mutex<Vec<String>>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&log);
+        let runner = move |program: &str, args: &[&str]| {
+            let line = format!("{program} {}", args.join(" "));
+            seen.lock().unwrap().push(line.clone());
+            replies
+                .iter()
+                .find(|(command, _)| *command == line)
+                .map(|(_, reply)| reply.to_string())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, line))
+        };
+        (runner, log)
+    }
+
+    const PACTL_STEREO: &str = "\
+Volume: front-left: 42597 /  65% / -11.23 dB,   front-right: 42597 /  65% / -11.23 dB
+        balance 0.00
+";
+
+    const PACTL_SURROUND: &str = "\
+Volume: front-left: 65536 / 100% / 0.00 dB,   front-right: 65536 / 100% / 0.00 dB,   rear-left: 32768 /  50% / -18.06 dB,   rear-right: 32768 /  50% / -18.06 dB,   front-center: 65536 / 100% / 0.00 dB,   lfe: 49152 /  75% / -7.50 dB
+        balance 0.00
+";
+
+    const PACTL_GERMAN: &str = "\
+Lautstärke: front-left: 42597 /  65% / -11,23 dB,   front-right: 39321 /  60% / -13,31 dB
+        Balance -0,08
+";
+
+    #[test]
+    fn pactl_mute() {
+        assert_eq!(parse_pactl_mute("Mute: no\n"), Some(false));
+        assert_eq!(parse_pactl_mute("Mute: yes\n"), Some(true));
+        // A translated pactl must not read as unmuted.
+        assert_eq!(parse_pactl_mute("Stumm: ja\n"), None);
+        assert_eq!(parse_pactl_mute("Silencieux : oui\n"), None);
+        assert_eq!(parse_pactl_mute(""), None);
+    }
+
+    #[test]
+    fn pactl_volume() {
+        assert_eq!(
+            parse_pactl_volume(PACTL_STEREO),
+            Some(vec![("front-left", 42597), ("front-right", 42597)])
+        );
+        assert_eq!(
+            parse_pactl_volume("Volume: mono: 65536 / 100% / 0.00 dB\n        balance 0.00\n"),
+            Some(vec![("mono", 65536)])
+        );
+        assert_eq!(parse_pactl_volume("Failure: No such entity\n"), None);
+    }
+
+    #[test]
+    fn pactl_volume_surround() {
+        let channels = parse_pactl_volume(PACTL_SURROUND).unwrap();
+        let names: Vec<&str> = channels.iter().map(|c| c.0).collect();
+        assert_eq!(
+            names,
+            [
+                "front-left",
+                "front-right",
+                "rear-left",
+                "rear-right",
+                "front-center",
+                "lfe"
+            ]
+        );
+        assert_eq!(channels[2].1, 32768);
+        assert_eq!(channels[5].1, 49152);
+    }
+
+    #[test]
+    fn pactl_volume_localised() {
+        assert_eq!(
+            parse_pactl_volume(PACTL_GERMAN),
+            Some(vec![("front-left", 42597), ("front-right", 39321)])
+        );
+    }
+
+    #[test]
+    fn pactl_round_trip() {
+        let (runner, log) = scripted(&[
+            (
+                "pactl get-default-sink",
+                "alsa_output.pci-0000_00_1f.3.analog-surround-51\n",
+            ),
+            (
+                "pactl get-sink-mute alsa_output.pci-0000_00_1f.3.analog-surround-51",
+                "Mute: no\n",
+            ),
+            (
+                "pactl get-sink-volume alsa_output.pci-0000_00_1f.3.analog-surround-51",
+                PACTL_SURROUND,
+            ),
+            (
+                "pactl set-sink-mute alsa_output.pci-0000_00_1f.3.analog-surround-51 1",
+                "",
+            ),
+            (
+                "pactl set-sink-volume alsa_output.pci-0000_00_1f.3.analog-surround-51 \
+                 65536 65536 32768 32768 65536 49152",
+                "",
+            ),
+            (
+                "pactl set-sink-mute alsa_output.pci-0000_00_1f.3.analog-surround-51 0",
+                "",
+            ),
+        ]);
+        let mut pactl = Pactl::with_runner(runner);
+        let snapshot = pactl.snapshot().unwrap().unwrap();
+        assert_eq!(
+            snapshot.sink.as_deref(),
+            Some("alsa_output.pci-0000_00_1f.3.analog-surround-51")
+        );
+        assert_eq!(
+            snapshot.channel_map.as_deref(),
+            Some("front-left,front-right,rear-left,rear-right,front-center,lfe")
+        );
+        pactl.mute(&snapshot).unwrap();
+        pactl.restore(&snapshot).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn pactl_without_get_default_sink() {
+        // pactl before 15.0 fails the unknown command.
+        let (runner, log) = scripted(&[
+            ("pactl get-sink-mute @DEFAULT_SINK@", "Mute: no\n"),
+            ("pactl get-sink-volume @DEFAULT_SINK@", PACTL_STEREO),
+            ("pactl set-sink-mute @DEFAULT_SINK@ 1", ""),
+        ]);
+        let mut pactl = Pactl::with_runner(runner);
+        let snapshot = pactl.snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.sink, None);
+        pactl.mute(&snapshot).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn pactl_already_muted() {
+        let (runner, log) = scripted(&[
+            ("pactl get-default-sink", "alsa_output.usb\n"),
+            ("pactl get-sink-mute alsa_output.usb", "Mute: yes\n"),
+        ]);
+        assert_eq!(Pactl::with_runner(runner).snapshot().unwrap(), None);
+        assert_eq!(log.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn pactl_localised_is_unavailable() {
+        let (runner, _) = scripted(&[("pactl get-sink-mute @DEFAULT_SINK@", "Stumm: nein\n")]);
+        let err = Pactl::with_runner(runner).snapshot().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn wpctl_volume() {
+        assert_eq!(parse_wpctl_volume("Volume: 0.40\n"), Some((0.4, false)));
+        assert_eq!(
+            parse_wpctl_volume("Volume: 1.00 [MUTED]\n"),
+            Some((1.0, true))
+        );
+        assert_eq!(parse_wpctl_volume("Volume: 0,40\n"), None);
+        assert_eq!(parse_wpctl_volume("Lautstärke: 0.40\n"), None);
+        assert_eq!(parse_wpctl_volume("Volume: 0.40 [STUMM]\n"), None);
+    }
+
+    #[test]
+    fn wpctl_round_trip() {
+        let (runner, log) = scripted(&[
+            ("wpctl get-volume @DEFAULT_AUDIO_SINK@", "Volume: 0.40\n"),
+            ("wpctl set-mute @DEFAULT_AUDIO_SINK@ 1", ""),
+            ("wpctl set-volume @DEFAULT_AUDIO_SINK@ 0.4", ""),
+            ("wpctl set-mute @DEFAULT_AUDIO_SINK@ 0", ""),
+        ]);
+        let mut wpctl = Wpctl::with_runner(runner);
+        let snapshot = wpctl.snapshot().unwrap().unwrap();
+        wpctl.mute(&snapshot).unwrap();
+        wpctl.restore(&snapshot).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 4);
+    }
+
+    const AMIXER_STEREO: &str = "\
+Simple mixer control 'Master',0
+  Capabilities: pvolume pswitch pswitch-joined
+  Playback channels: Front Left - Front Right
+  Limits: Playback 0 - 87
+  Mono:
+  Front Left: Playback 57 [66%] [-22.50dB] [on]
+  Front Right: Playback 52 [60%] [-26.25dB] [on]
+";
+
+    const AMIXER_MONO_MUTED: &str = "\
+Simple mixer control 'Master',0
+  Capabilities: pvolume pvolume-joined pswitch pswitch-joined
+  Playback channels: Mono
+  Limits: Playback 0 - 87
+  Mono: Playback 87 [100%] [0.00dB] [off]
+";
+
+    const AMIXER_NO_SWITCH: &str = "\
+Simple mixer control 'Master',0
+  Capabilities: pvolume
+  Playback channels: Front Left - Front Right
+  Limits: Playback 0 - 255
+  Mono:
+  Front Left: Playback 255 [100%]
+  Front Right: Playback 255 [100%]
+";
+
+    #[test]
+    fn amixer_channels() {
+        assert_eq!(
+            parse_amixer(AMIXER_STEREO),
+            Some(vec![
+                AmixerChannel {
+                    name: "Front Left",
+                    volume: 57,
+                    on: true
+                },
+                AmixerChannel {
+                    name: "Front Right",
+                    volume: 52,
+                    on: true
+                },
+            ])
+        );
+        assert_eq!(
+            parse_amixer(AMIXER_MONO_MUTED),
+            Some(vec![AmixerChannel {
+                name: "Mono",
+                volume: 87,
+                on: false
+            }])
+        );
+        let no_switch = parse_amixer(AMIXER_NO_SWITCH).unwrap();
+        assert!(no_switch.iter().all(|c| c.on && c.volume == 255));
+        assert_eq!(
+            parse_amixer("amixer: Unable to find simple control 'Master',0\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn amixer_round_trip() {
+        let (runner, log) = scripted(&[
+            ("amixer get Master", AMIXER_STEREO),
+            ("amixer -q sset Master mute", ""),
+            ("amixer -q sset Master 57,52", ""),
+            ("amixer -q sset Master unmute", ""),
+        ]);
+        let mut amixer = Amixer::with_runner(runner);
+        let snapshot = amixer.snapshot().unwrap().unwrap();
+        assert_eq!(
+            snapshot.channel_map.as_deref(),
+            Some("Front Left,Front Right")
+        );
+        amixer.mute(&snapshot).unwrap();
+        amixer.restore(&snapshot).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn amixer_already_muted() {
+        let (runner, _) = scripted(&[("amixer get Master", AMIXER_MONO_MUTED)]);
+        assert_eq!(Amixer::with_runner(runner).snapshot().unwrap(), None);
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! An in-memory backend for tests.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use super::{malformed, AudioBackend};
+use crate::journal::Snapshot;
+
+/// State of a [`FakeBackend`]'s output, and the failures to inject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeOutput {
+    pub volume: u32,
+    pub mute: bool,
+    /// Fail snapshots, as an unavailable backend does.
+    pub fail_snapshot: bool,
+    /// Fail muting after the output is already muted, as a backend does
+    /// that gets partway through.
+    pub fail_mute: bool,
+    pub fail_restore: bool,
+    /// `snapshot`, `mute` and `restore`, in the order they were called.
+    pub calls: Vec<&'static str>,
+}
+
+impl Default for FakeOutput {
+    fn default() -> Self {
+        Self {
+            volume: 100,
+            mute: false,
+            fail_snapshot: false,
+            fail_mute: false,
+            fail_restore: false,
+            calls: Vec::new(),
+        }
+    }
+}
+
+/// A backend whose output is a [`FakeOutput`] shared with the test.
+#[derive(Debug, Clone)]
+pub struct FakeBackend {
+    name: &'static str,
+    output: Arc<Mutex<FakeOutput>>,
+}
+
+impl FakeBackend {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            output: Arc::default(),
+        }
+    }
+
+    /// The output, to inspect or change while the backend is in use.
+    pub fn output(&self) -> Arc<Mutex<FakeOutput>> {
+        Arc::clone(&self.output)
+    }
+
+    fn call(&self, call: &'static str) -> std::sync::MutexGuard<'_, FakeOutput> {
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        output.calls.push(call);
+        output
+    }
+}
+
+impl AudioBackend for FakeBackend {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        let output = self.call("snapshot");
+        if output.fail_snapshot {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "fake unavailable"));
+        }
+        if output.mute {
+            return Ok(None);
+        }
+        Ok(Some(Snapshot {
+            backend: self.name.into(),
+            volume: Some(output.volume.to_string()),
+            ..Snapshot::default()
+        }))
+    }
+
+    fn mute(&mut self, _: &Snapshot) -> io::Result<()> {
+        let mut output = self.call("mute");
+        output.mute = true;
+        if output.fail_mute {
+            return Err(io::Error::other("fake mute failed"));
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let mut output = self.call("restore");
+        if output.fail_restore {
+            return Err(io::Error::other("fake restore failed"));
+        }
+        if let Some(volume) = &snapshot.volume {
+            output.volume = volume.parse().map_err(|_| malformed())?;
+        }
+        output.mute = snapshot.mute;
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Backends speaking the sound server's native protocol.
+
+use std::io;
+
+use super::{join, split, AudioBackend};
+use crate::journal::Snapshot;
+use crate::pulse::{Client, StreamRule, DEFAULT_SINK, EVENT_SOUNDS};
+
+/// Name the guard shows up as in the sound server's client list.
+const CLIENT_NAME: &str = "ocrmyimg";
+
+/// Mutes event sounds only, so music or a call keeps playing.
+///
+/// The stream-restore rule for the event role is muted, which silences
+/// event sounds that start during the capture, and so are event streams
+/// already playing.
+///
+/// module-stream-restore saves its rules to disk, so a process that dies
+/// with the rule muted leaves "System Sounds" muted for good, reboots
+/// included. The journal is kept in the state directory for the same
+/// reason, so the next launch still finds it and unmutes the rule.
+#[derive(Debug, Default)]
+pub struct EventSounds;
+
+impl AudioBackend for EventSounds {
+    fn name(&self) -> &'static str {
+        "events"
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        let mut client = Client::connect(CLIENT_NAME)?;
+        let rule = client.stream_rule(EVENT_SOUNDS)?;
+        let inputs: Vec<u32> = client
+            .sink_inputs()?
+            .into_iter()
+            .filter(|input| input.is_event_sound() && !input.mute)
+            .map(|input| input.index)
+            .collect();
+        if rule.as_ref().is_some_and(|r| r.mute) && inputs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Snapshot {
+            backend: self.name().into(),
+            sink: rule.as_ref().and_then(|r| r.device.clone()),
+            volume: rule.as_ref().map(|r| join(&r.volume)),
+            mute: rule.as_ref().is_some_and(|r| r.mute),
+            channel_map: rule.as_ref().map(|r| join(&r.channel_map)),
+            inputs,
+        }))
+    }
+
+    fn mute(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let mut client = Client::connect(CLIENT_NAME)?;
+        let muted = StreamRule {
+            mute: true,
+            ..rule(snapshot)?.unwrap_or_default()
+        };
+        client.set_stream_rule(EVENT_SOUNDS, &muted)?;
+        set_inputs_mute(&mut client, &snapshot.inputs, true)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let mut client = Client::connect(CLIENT_NAME)?;
+        match rule(snapshot)? {
+            Some(rule) => client.set_stream_rule(EVENT_SOUNDS, &rule)?,
+            // There was no rule; servers that cannot delete one get an
+            // unmuted default instead.
+            None => client
+                .delete_stream_rule(EVENT_SOUNDS)
+                .or_else(|_| client.set_stream_rule(EVENT_SOUNDS, &StreamRule::default()))?,
+        }
+        set_inputs_mute(&mut client, &snapshot.inputs, false)
+    }
+}
+
+/// The event sound rule a snapshot recorded, or `None` when there was
+/// none.
+fn rule(snapshot: &Snapshot) -> io::Result<Option<StreamRule>> {
+    let Some(channel_map) = &snapshot.channel_map else {
+        return Ok(None);
+    };
+    Ok(Some(StreamRule {
+        channel_map: split(channel_map)?,
+        volume: split(snapshot.volume.as_deref().unwrap_or_default())?,
+        device: snapshot.sink.clone(),
+        mute: snapshot.mute,
+    }))
+}
+
+fn set_inputs_mute(client: &mut Client, inputs: &[u32], mute: bool) -> io::Result<()> {
+    for &index in inputs {
+        match client.set_sink_input_mute(index, mute) {
+            // It finished playing in the meantime.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Mutes the whole default sink, putting back the exact volume of every
+/// channel afterwards.
+#[derive(Debug, Default)]
+pub struct PulseSink;
+
+impl AudioBackend for PulseSink {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn snapshot(&mut self) -> io::Result<Option<Snapshot>> {
+        let sink = Client::connect(CLIENT_NAME)?.sink(DEFAULT_SINK)?;
+        if sink.mute {
+            return Ok(None);
+        }
+        Ok(Some(Snapshot {
+            backend: self.name().into(),
+            // By name, so the same sink is restored even if the default
+            // changes meanwhile.
+            sink: Some(sink.name),
+            volume: Some(join(&sink.volume)),
+            ..Snapshot::default()
+        }))
+    }
+
+    fn mute(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let sink = snapshot.sink.as_deref().unwrap_or(DEFAULT_SINK);
+        Client::connect(CLIENT_NAME)?.set_sink_mute(sink, true)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let sink = snapshot.sink.as_deref().unwrap_or(DEFAULT_SINK);
+        let volume = snapshot.volume.as_deref().map(split::<u32>).transpose()?;
+        let mut client = Client::connect(CLIENT_NAME)?;
+        if let Some(volume) = volume {
+            client.set_sink_volume(sink, &volume)?;
+        }
+        client.set_sink_mute(sink, snapshot.mute)
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! Crash-safe record of the audio state to restore.
+//!
+//! The state is written before the output is muted and removed once it
+//! has been put back. A process killed in between, or a machine that
+//! suspends mid-capture, leaves the file behind, and the next launch
+//! restores from it. Sound servers keep mute state across reboots, so
+//! the record has to as well: it lives in the user's state directory,
+//! `$XDG_STATE_HOME/ocrmyimg`.
+//!
+//! The format is one `key=value` per line, starting with the `pid` of
+//! the process that wrote it and, where the system reports it, the
+//! time that process `started`, so a guard never restores audio that a
+//! guard in another live process has muted, nor mistakes a new process
+//! that was given the same pid for it.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "ocrmyimg-audio.journal";
+
+/// Audio state from before muting, in the terms of the backend that
+/// muted it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Snapshot {
+    /// [`AudioBackend::name`](crate::AudioBackend::name) of the backend
+    /// that took it.
+    pub backend: String,
+    /// Sink the backend muted, when it addresses sinks by name. For
+    /// `events`, the device of the event sound rule.
+    pub sink: Option<String>,
+    /// Volume in the backend's own notation: raw per-channel volumes
+    /// separated by commas for `events`, `native`, `pactl` and
+    /// `amixer`, a linear factor for `wpctl`.
+    pub volume: Option<String>,
+    pub mute: bool,
+    /// Channel positions of `volume`, separated by commas. For
+    /// `events`, absent when there was no event sound rule.
+    pub channel_map: Option<String>,
+    /// Streams muted one by one.
+    pub inputs: Vec<u32>,
+}
+
+impl Snapshot {
+    fn encode(&self) -> String {
+        let mut out = format!("backend={}\n", self.backend);
+        if let Some(sink) = &self.sink {
+            out += &format!("sink={sink}\n");
+        }
+        if let Some(volume) = &self.volume {
+            out += &format!("volume={volume}\n");
+        }
+        out += &format!("mute={}\n", u8::from(self.mute));
+        if let Some(channel_map) = &self.channel_map {
+            out += &format!("channel_map={channel_map}\n");
+        }
+        if !self.inputs.is_empty() {
+            let inputs: Vec<String> = self.inputs.iter().map(u32::to_string).collect();
+            out += &format!("inputs={}\n", inputs.join(","));
+        }
+        out
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let mut snapshot = Snapshot::default();
+        for line in text.lines() {
+            let (key, value) = line.split_once('=')?;
+            match key {
+                "backend" => snapshot.backend = value.to_string(),
+                "sink" => snapshot.sink = Some(value.to_string()),
+                "volume" => snapshot.volume = Some(value.to_string()),
+                "mute" => snapshot.mute = value == "1",
+                "channel_map" => snapshot.channel_map = Some(value.to_string()),
+                "inputs" => {
+                    snapshot.inputs = value
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .ok()?
+                }
+                _ => {}
+            }
+        }
+        (!snapshot.backend.is_empty()).then_some(snapshot)
+    }
+}
+
+/// The journal file.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Default for Journal {
+    /// The journal in `$XDG_STATE_HOME/ocrmyimg`, falling back to
+    /// `~/.local/state/ocrmyimg`, and to a directory of this user's in
+    /// the temp dir when there is no home either.
+    fn default() -> Self {
+        let state = std::env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .filter(|dir| !dir.is_empty())
+                    .map(|home| Path::new(&home).join(".local/state"))
+            });
+        let dir = match state {
+            Some(state) => state.join("ocrmyimg"),
+            None => std::env::temp_dir().join(format!("ocrmyimg-{}", user_id())),
+        };
+        Self::new(dir.join(FILE_NAME))
+    }
+}
+
+impl Journal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Record `snapshot` as this process's, replacing any earlier
+    /// record. The file is renamed into place so a crash never leaves
+    /// half a record.
+    pub(crate) fn write(&self, snapshot: &Snapshot) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            private_dir(dir)?;
+        }
+        let partial = self.path.with_extension("partial");
+        // Whatever a crash left there, never write through it.
+        match fs::remove_file(&partial) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let mut file = create_new(&partial)?;
+        let pid = std::process::id();
+        writeln!(file, "pid={pid}")?;
+        if let Some(started) = start_time(pid) {
+            writeln!(file, "started={started}")?;
+        }
+        write!(file, "{}", snapshot.encode())?;
+        fs::rename(&partial, &self.path)
+    }
+
+    /// The record left by a previous run, if any.
+    pub(crate) fn read(&self) -> io::Result<Option<Snapshot>> {
+        match self.read_text()? {
+            Some(text) => Snapshot::decode(&text).map(Some).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "malformed audio journal")
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// The process that wrote the record, while it is still running.
+    pub(crate) fn live_owner(&self) -> Option<u32> {
+        let text = self.read_text().ok()??;
+        let field = |key: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+        };
+        let pid = field("pid")?.parse().ok()?;
+        // After a crash the pid may be reused, even by this process. A
+        // process started at another time than the writer is not it.
+        let started: Option<u64> = field("started").and_then(|s| s.parse().ok());
+        let reused = started.is_some_and(|started| start_time(pid) != Some(started));
+        (pid != std::process::id() && is_running(pid) && !reused).then_some(pid)
+    }
+
+    fn read_text(&self) -> io::Result<Option<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) fn remove(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Create `dir` if needed, readable by this user only, and make sure it
+/// is not someone else's or open to everyone, as it could be in the
+/// temp dir.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let metadata = fs::metadata(dir)?;
+    if metadata.uid() != user_id() || metadata.mode() & 0o002 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not private to this user", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+/// Create `path`, failing if anything, a symlink included, is there.
+fn create_new(path: &Path) -> io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+#[cfg(unix)]
+fn user_id() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(not(unix))]
+fn user_id() -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // 0 and negative pids would address process groups.
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return false,
+    };
+    // SAFETY: signal 0 only checks that the process exists.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // It exists but belongs to someone else.
+    io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a way to tell, every record is taken to be stale.
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+/// When `pid` started, in clock ticks since boot: field 22 of
+/// `/proc/<pid>/stat`. Counted after the command name, which is in
+/// parentheses and may itself hold spaces and parentheses.
+#[cfg(target_os = "linux")]
+fn start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    // The fields after the name start with the third, the state.
+    fields.split_whitespace().nth(22 - 3)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ocrmyimg-journal-test-{}-{test}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn records_the_writer() {
+        let journal = Journal::new(dir("records_the_writer").join("state/journal"));
+        let snapshot = Snapshot {
+            backend: "native".into(),
+            sink: Some("alsa_output.usb".into()),
+            volume: Some("65536,65536".into()),
+            mute: false,
+            channel_map: Some("1,2".into()),
+            inputs: vec![3, 4],
+        };
+        journal.write(&snapshot).unwrap();
+        assert_eq!(journal.read().unwrap(), Some(snapshot));
+        // Written by this process, which has not muted anything yet.
+        assert_eq!(journal.live_owner(), None);
+        let text = fs::read_to_string(&journal.path).unwrap();
+        assert!(text.starts_with(&format!("pid={}\n", std::process::id())));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn tells_a_reused_pid_from_the_writer() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let started = start_time(pid).unwrap();
+        assert!(started >= start_time(std::process::id()).unwrap());
+
+        let journal = Journal::new(dir("tells_a_reused_pid_from_the_writer").join("journal"));
+        let owner = |record: &str| {
+            private_dir(journal.path.parent().unwrap()).unwrap();
+            fs::write(&journal.path, format!("{record}backend=native\n")).unwrap();
+            journal.live_owner()
+        };
+        assert_eq!(owner(&format!("pid={pid}\nstarted={started}\n")), Some(pid));
+        assert_eq!(
+            owner(&format!("pid={pid}\nstarted={}\n", started + 1)),
+            None
+        );
+        // Records from before start times were kept go by the pid.
+        assert_eq!(owner(&format!("pid={pid}\n")), Some(pid));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(owner(&format!("pid={pid}\nstarted={started}\n")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn never_writes_through_a_symlink() {
+        let dir = dir("never_writes_through_a_symlink");
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target");
+        fs::write(&target, "keep").unwrap();
+        let journal = Journal::new(dir.join("journal"));
+        std::os::unix::fs::symlink(&target, dir.join("journal.partial")).unwrap();
+
+        journal.write(&Snapshot::default()).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_a_shared_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = dir("refuses_a_shared_directory");
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        let journal = Journal::new(dir.join("journal"));
+        let err = journal.write(&Snapshot::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -1,17 +1,31 @@
-use anyhow::Result;
-use image::DynamicImage;
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
 
-pub fn optimize_for_ai(image_data: &[u8]) -> Result<Vec<u8>> {
-    // 1. Load image
-    let img = image::load_from_memory(image_data)?;
-    
-    // 2. Resize if too large (saving tokens/bandwidth)
-    let resized = img.resize(1024, 1024, image::imageops::FilterType::Lanczos3);
-    
-    // 3. Convert back to bytes (e.g., JPEG)
-    let mut output = Vec::new();
-    let mut cursor = std::io::Cursor::new(&mut output);
-    resized.write_to(&mut cursor, image::ImageOutputFormat::Jpeg(80))?;
-    
-    Ok(output)
-}
+//! Silencing the shutter sound of screen captures.
+//!
+//! Capture tools play a camera sound through the desktop's event
+//! sounds. [`AudioGuard`] mutes the output for the length of a capture
+//! and puts the user's audio back afterwards, even when the process
+//! dies first: a SIGINT or SIGTERM restores it on the way out, and
+//! after anything harsher the next launch finds the journal it left
+//! and restores from it.
+//!
+//! How the output is muted is up to an [`AudioBackend`]; the guard
+//! tries [`default_backends`] in order unless given its own.
+
+mod audio;
+mod backend;
+mod journal;
+#[cfg(target_os = "linux")]
+mod pulse;
+
+pub use audio::AudioGuard;
+#[cfg(target_os = "macos")]
+pub use backend::Osascript;
+pub use backend::{
+    default_backends, Amixer, AudioBackend, FakeBackend, FakeOutput, Pactl, Runner, SystemRunner,
+    Wpctl,
+};
+#[cfg(target_os = "linux")]
+pub use backend::{EventSounds, PulseSink};
+pub use journal::{Journal, Snapshot};
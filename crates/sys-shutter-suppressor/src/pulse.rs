@@ -0,0 +1,589 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! A minimal client for the PulseAudio native protocol.
+//!
+//! Speaks just enough of the protocol to read and set the volume and
+//! mute state of a sink and its streams, and the stream-restore rules
+//! that decide the state new streams start in. pipewire-pulse serves
+//! the same protocol on the same socket, so this covers both sound
+//! servers without linking libpulse.
+//!
+//! Packets are a 20-byte descriptor (length, channel, offset and flags,
+//! big-endian) followed by a tagstruct payload. Commands start with the
+//! command code and a tag that the server echoes in its reply.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod tagstruct;
+
+use tagstruct::{Reader, Writer};
+
+/// Protocol version requested; the server answers with the lower of
+/// its own and this one.
+const PROTOCOL_VERSION: u32 = 32;
+/// The low bits of the version word; the high ones carry feature flags.
+const VERSION_MASK: u32 = 0xffff;
+
+const COMMAND_ERROR: u32 = 0;
+const COMMAND_REPLY: u32 = 2;
+const COMMAND_AUTH: u32 = 8;
+const COMMAND_SET_CLIENT_NAME: u32 = 9;
+const COMMAND_GET_SINK_INFO: u32 = 21;
+const COMMAND_SET_SINK_VOLUME: u32 = 36;
+const COMMAND_GET_SINK_INPUT_INFO_LIST: u32 = 30;
+const COMMAND_SET_SINK_MUTE: u32 = 39;
+const COMMAND_SET_SINK_INPUT_MUTE: u32 = 69;
+const COMMAND_EXTENSION: u32 = 87;
+
+/// Sub-commands of the `module-stream-restore` extension.
+const STREAM_RESTORE: &str = "module-stream-restore";
+const STREAM_RESTORE_READ: u32 = 1;
+const STREAM_RESTORE_WRITE: u32 = 2;
+const STREAM_RESTORE_DELETE: u32 = 3;
+/// Write mode that replaces the given rules and keeps the rest.
+const UPDATE_REPLACE: u32 = 2;
+
+/// Channel number of packets that carry commands rather than audio.
+const CONTROL_CHANNEL: u32 = u32::MAX;
+const INVALID_INDEX: u32 = u32::MAX;
+
+const DESCRIPTOR_SIZE: usize = 20;
+const MAX_PACKET: usize = 16 * 1024 * 1024;
+const COOKIE_SIZE: usize = 256;
+
+/// Error codes the server uses for a missing object and a denied
+/// request.
+const ERROR_ACCESS: u32 = 1;
+const ERROR_NO_ENTITY: u32 = 5;
+/// Not supported, no such extension, not implemented.
+const ERRORS_UNSUPPORTED: [u32; 3] = [19, 21, 23];
+
+/// Channel position of a single-channel map.
+const CHANNEL_MONO: u8 = 0;
+const VOLUME_NORM: u32 = 0x10000;
+
+/// Longest the server gets to answer any request.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Name the server resolves to its default sink.
+pub const DEFAULT_SINK: &str = "@DEFAULT_SINK@";
+
+/// Stream-restore rule for event sounds, shown as "System Sounds" in
+/// pavucontrol.
+pub const EVENT_SOUNDS: &str = "sink-input-by-media-role:event";
+
+/// Volume and mute state of a sink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkState {
+    pub index: u32,
+    /// Stable name to address the sink by, e.g.
+    /// `alsa_output.pci-0000_00_1f.3.analog-stereo`.
+    pub name: String,
+    /// Channel positions, in the order of `volume`.
+    pub channel_map: Vec<u8>,
+    /// Volume of each channel; 0x10000 is 100%.
+    pub volume: Vec<u32>,
+    pub mute: bool,
+}
+
+/// A playback stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkInput {
+    pub index: u32,
+    pub mute: bool,
+    /// String properties, including the `application.*` ones of the
+    /// client that owns the stream.
+    pub properties: HashMap<String, String>,
+}
+
+impl SinkInput {
+    /// Whether this is an event sound: a stream with the `event` role,
+    /// or one played through libcanberra, which capture tools use for
+    /// their shutter sound.
+    pub fn is_event_sound(&self) -> bool {
+        let property = |key: &str| self.properties.get(key).map(String::as_str);
+        property("media.role") == Some("event")
+            || ["application.name", "application.process.binary"]
+                .iter()
+                .any(|key| property(key).is_some_and(|v| v.contains("canberra")))
+    }
+}
+
+/// Stream-restore rule: the state streams matching it start in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamRule {
+    /// Channel positions, in the order of `volume`. Both are empty when
+    /// the rule leaves the volume alone.
+    pub channel_map: Vec<u8>,
+    pub volume: Vec<u32>,
+    /// Sink matching streams are sent to.
+    pub device: Option<String>,
+    pub mute: bool,
+}
+
+impl Default for StreamRule {
+    /// Full volume on the default sink, as pavucontrol creates missing
+    /// rules.
+    fn default() -> Self {
+        Self {
+            channel_map: vec![CHANNEL_MONO],
+            volume: vec![VOLUME_NORM],
+            device: None,
+            mute: false,
+        }
+    }
+}
+
+/// A connection to the sound server.
+pub struct Client {
+    stream: UnixStream,
+    next_tag: u32,
+    /// Negotiated protocol version, which decides the reply layouts.
+    version: u32,
+}
+
+impl Client {
+    /// Connect and authenticate as `app_name`.
+    pub fn connect(app_name: &str) -> io::Result<Self> {
+        let path = socket_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no sound server socket"))?;
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut client = Self {
+            stream,
+            next_tag: 0,
+            version: PROTOCOL_VERSION,
+        };
+
+        // Without a cookie the server still admits clients of the same
+        // user, from the credentials the kernel attaches to the socket.
+        let cookie = read_cookie().unwrap_or_else(|| vec![0; COOKIE_SIZE]);
+        let reply = client.request(COMMAND_AUTH, |w| {
+            w.u32(PROTOCOL_VERSION).arbitrary(&cookie);
+        })?;
+        let server_version = Reader::new(&reply).u32()? & VERSION_MASK;
+        if server_version < 14 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("protocol version {server_version} is too old"),
+            ));
+        }
+        client.version = server_version.min(PROTOCOL_VERSION);
+
+        client.request(COMMAND_SET_CLIENT_NAME, |w| {
+            w.proplist(&[("application.name", app_name)]);
+        })?;
+        Ok(client)
+    }
+
+    /// State of the sink called `name`, which may be [`DEFAULT_SINK`].
+    pub fn sink(&mut self, name: &str) -> io::Result<SinkState> {
+        let reply = self.request(COMMAND_GET_SINK_INFO, |w| {
+            w.u32(INVALID_INDEX).string(Some(name));
+        })?;
+        parse_sink(&reply)
+    }
+
+    /// Set the volume of each channel of the sink called `name`.
+    pub fn set_sink_volume(&mut self, name: &str, volume: &[u32]) -> io::Result<()> {
+        self.request(COMMAND_SET_SINK_VOLUME, |w| {
+            w.u32(INVALID_INDEX).string(Some(name)).cvolume(volume);
+        })
+        .map(drop)
+    }
+
+    pub fn set_sink_mute(&mut self, name: &str, mute: bool) -> io::Result<()> {
+        self.request(COMMAND_SET_SINK_MUTE, |w| {
+            w.u32(INVALID_INDEX).string(Some(name)).bool(mute);
+        })
+        .map(drop)
+    }
+
+    /// Every playback stream.
+    pub fn sink_inputs(&mut self) -> io::Result<Vec<SinkInput>> {
+        let reply = self.request(COMMAND_GET_SINK_INPUT_INFO_LIST, |_| {})?;
+        parse_sink_inputs(&reply, self.version)
+    }
+
+    pub fn set_sink_input_mute(&mut self, index: u32, mute: bool) -> io::Result<()> {
+        self.request(COMMAND_SET_SINK_INPUT_MUTE, |w| {
+            w.u32(index).bool(mute);
+        })
+        .map(drop)
+    }
+
+    /// The stream-restore rule called `name`, if there is one.
+    pub fn stream_rule(&mut self, name: &str) -> io::Result<Option<StreamRule>> {
+        let reply = self.stream_restore(STREAM_RESTORE_READ, |_| {})?;
+        parse_stream_rule(&reply, name)
+    }
+
+    /// Create or replace the stream-restore rule called `name`. Streams
+    /// it matches pick up the change straight away.
+    pub fn set_stream_rule(&mut self, name: &str, rule: &StreamRule) -> io::Result<()> {
+        self.stream_restore(STREAM_RESTORE_WRITE, |w| {
+            w.u32(UPDATE_REPLACE)
+                .bool(true)
+                .string(Some(name))
+                .channel_map(&rule.channel_map)
+                .cvolume(&rule.volume)
+                .string(rule.device.as_deref())
+                .bool(rule.mute);
+        })
+        .map(drop)
+    }
+
+    pub fn delete_stream_rule(&mut self, name: &str) -> io::Result<()> {
+        self.stream_restore(STREAM_RESTORE_DELETE, |w| {
+            w.string(Some(name));
+        })
+        .map(drop)
+    }
+
+    fn stream_restore(
+        &mut self,
+        subcommand: u32,
+        build: impl FnOnce(&mut Writer),
+    ) -> io::Result<Vec<u8>> {
+        self.request(COMMAND_EXTENSION, |w| {
+            w.u32(INVALID_INDEX)
+                .string(Some(STREAM_RESTORE))
+                .u32(subcommand);
+            build(w);
+        })
+    }
+
+    /// Send a command and wait for its reply, returning the reply's
+    /// payload after the command and tag.
+    fn request(&mut self, command: u32, build: impl FnOnce(&mut Writer)) -> io::Result<Vec<u8>> {
+        let tag = self.next_tag;
+        self.next_tag = self.next_tag.wrapping_add(1);
+
+        let mut payload = Writer::default();
+        payload.u32(command).u32(tag);
+        build(&mut payload);
+        let payload = payload.into_bytes();
+
+        let mut packet = Vec::with_capacity(DESCRIPTOR_SIZE + payload.len());
+        for word in [payload.len() as u32, CONTROL_CHANNEL, 0, 0, 0] {
+            packet.extend_from_slice(&word.to_be_bytes());
+        }
+        packet.extend_from_slice(&payload);
+        self.stream.write_all(&packet)?;
+
+        loop {
+            let Some(packet) = self.read_packet()? else {
+                continue;
+            };
+            let mut r = Reader::new(&packet);
+            let (reply, reply_tag) = (r.u32()?, r.u32()?);
+            // Anything else is an event nobody asked for.
+            if reply_tag != tag {
+                continue;
+            }
+            return match reply {
+                COMMAND_REPLY => Ok(r.rest().to_vec()),
+                COMMAND_ERROR => Err(server_error(r.u32()?)),
+                _ => continue,
+            };
+        }
+    }
+
+    /// Read the next packet, or `None` for audio data.
+    fn read_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut descriptor = [0u8; DESCRIPTOR_SIZE];
+        self.stream.read_exact(&mut descriptor)?;
+        let word = |i: usize| u32::from_be_bytes(descriptor[i * 4..i * 4 + 4].try_into().unwrap());
+        let (len, channel) = (word(0) as usize, word(1));
+        if len > MAX_PACKET {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "packet too large",
+            ));
+        }
+        let mut packet = vec![0u8; len];
+        self.stream.read_exact(&mut packet)?;
+        Ok((channel == CONTROL_CHANNEL).then_some(packet))
+    }
+}
+
+/// A `GET_SINK_INFO` reply. Fields after the mute flag differ between
+/// protocol versions and are not read.
+fn parse_sink(reply: &[u8]) -> io::Result<SinkState> {
+    let mut r = Reader::new(reply);
+    let index = r.u32()?;
+    let name = r.string()?.unwrap_or_default();
+    // Description and sample spec.
+    r.skip_n(2)?;
+    let channel_map = r.channel_map()?;
+    // Owner module.
+    r.skip()?;
+    let volume = r.cvolume()?;
+    let mute = r.bool()?;
+    Ok(SinkState {
+        index,
+        name,
+        channel_map,
+        volume,
+        mute,
+    })
+}
+
+/// A `GET_SINK_INPUT_INFO_LIST` reply from a server speaking protocol
+/// `version`, which decides how many fields each entry has.
+fn parse_sink_inputs(reply: &[u8], version: u32) -> io::Result<Vec<SinkInput>> {
+    let mut r = Reader::new(reply);
+    let mut inputs = Vec::new();
+    while !r.is_empty() {
+        let index = r.u32()?;
+        // Name, owner module, client, sink, sample spec, channel map,
+        // volume, buffer and sink latency, resample method, driver.
+        r.skip_n(11)?;
+        let mute = r.bool()?;
+        let properties = r.proplist()?;
+        // Corked; has volume and volume writable; format.
+        let later = [(19, 1), (20, 2), (21, 1)];
+        for (since, count) in later {
+            if version >= since {
+                r.skip_n(count)?;
+            }
+        }
+        inputs.push(SinkInput {
+            index,
+            mute,
+            properties,
+        });
+    }
+    Ok(inputs)
+}
+
+/// The rule called `name` in a stream-restore `READ` reply.
+fn parse_stream_rule(reply: &[u8], name: &str) -> io::Result<Option<StreamRule>> {
+    let mut r = Reader::new(reply);
+    while !r.is_empty() {
+        let rule_name = r.string()?;
+        let rule = StreamRule {
+            channel_map: r.channel_map()?,
+            volume: r.cvolume()?,
+            device: r.string()?,
+            mute: r.bool()?,
+        };
+        if rule_name.as_deref() == Some(name) {
+            return Ok(Some(rule));
+        }
+    }
+    Ok(None)
+}
+
+fn server_error(code: u32) -> io::Error {
+    let kind = match code {
+        ERROR_ACCESS => io::ErrorKind::PermissionDenied,
+        ERROR_NO_ENTITY => io::ErrorKind::NotFound,
+        code if ERRORS_UNSUPPORTED.contains(&code) => io::ErrorKind::Unsupported,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, format!("sound server error {code}"))
+}
+
+/// Where the server listens: the first local socket in `$PULSE_SERVER`,
+/// or `native` in the per-user runtime directory.
+fn socket_path() -> Option<PathBuf> {
+    if let Ok(servers) = std::env::var("PULSE_SERVER") {
+        // Entries may be prefixed with `{machine-id}`; only local
+        // sockets are supported.
+        return servers.split_whitespace().find_map(|server| {
+            let server = server.rsplit_once('}').map_or(server, |(_, s)| s);
+            let path = server.strip_prefix("unix:").unwrap_or(server);
+            path.starts_with('/').then(|| PathBuf::from(path))
+        });
+    }
+    let runtime = std::env::var_os("PULSE_RUNTIME_PATH")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("XDG_RUNTIME_DIR").map(|dir| Path::new(&dir).join("pulse")))?;
+    Some(runtime.join("native"))
+}
+
+/// The auth cookie from the places libpulse looks for it.
+fn read_cookie() -> Option<Vec<u8>> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".config")));
+    let candidates = [
+        std::env::var_os("PULSE_COOKIE").map(PathBuf::from),
+        config.map(|c| c.join("pulse/cookie")),
+        home.map(|h| h.join(".pulse-cookie")),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .find_map(|path| fs::read(path).ok())
+        .filter(|cookie| cookie.len() == COOKIE_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Versions whose replies differ: the oldest accepted, each one that
+    /// adds sink input fields, and the one requested.
+    const VERSIONS: [u32; 5] = [14, 19, 20, 21, PROTOCOL_VERSION];
+
+    /// A `GET_SINK_INFO` reply laid out as the server writes it for
+    /// `version`.
+    fn sink_reply(version: u32) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.u32(1)
+            .string(Some("alsa_output.pci-0000_00_1f.3.analog-stereo"))
+            .string(Some("Built-in Audio Analog Stereo"))
+            .sample_spec(3, 2, 48_000)
+            .channel_map(&[1, 2])
+            .u32(7)
+            .cvolume(&[0x8000, 0x9000])
+            .bool(true)
+            .u32(2)
+            .string(Some("alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"))
+            .usec(0)
+            .string(Some("module-alsa-card.c"))
+            .u32(0x3f);
+        // Properties and configured latency.
+        w.proplist(&[("device.class", "sound")]).usec(0);
+        if version >= 15 {
+            // Base volume, state, volume steps and card.
+            w.volume(0x10000).u32(1).u32(65537).u32(0);
+        }
+        if version >= 16 {
+            // One port, then the active one.
+            w.u32(1)
+                .string(Some("analog-output-speaker"))
+                .string(Some("Speakers"))
+                .u32(10_000)
+                .string(Some("analog-output-speaker"));
+        }
+        if version >= 21 {
+            w.u8(1).format_info(1, &[]);
+        }
+        w.into_bytes()
+    }
+
+    /// One entry of a `GET_SINK_INPUT_INFO_LIST` reply for `version`.
+    fn sink_input(w: &mut Writer, version: u32, index: u32, mute: bool, role: &str) {
+        w.u32(index)
+            .string(Some("playback"))
+            .u32(INVALID_INDEX)
+            .u32(12)
+            .u32(1)
+            .sample_spec(3, 2, 44_100)
+            .channel_map(&[1, 2])
+            .cvolume(&[0x10000, 0x10000])
+            .usec(40_000)
+            .usec(25_000)
+            .string(Some("speex-float-1"))
+            .string(Some("protocol-native.c"))
+            .bool(mute)
+            .proplist(&[("media.role", role), ("application.name", "Firefox")]);
+        if version >= 19 {
+            w.bool(false);
+        }
+        if version >= 20 {
+            w.bool(true).bool(true);
+        }
+        if version >= 21 {
+            w.format_info(1, &[("format.rate", "44100")]);
+        }
+    }
+
+    #[test]
+    fn reads_sinks_of_every_version() {
+        for version in VERSIONS {
+            let sink = parse_sink(&sink_reply(version)).unwrap();
+            assert_eq!(
+                sink,
+                SinkState {
+                    index: 1,
+                    name: "alsa_output.pci-0000_00_1f.3.analog-stereo".into(),
+                    channel_map: vec![1, 2],
+                    volume: vec![0x8000, 0x9000],
+                    mute: true,
+                },
+                "version {version}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_sink_inputs_of_every_version() {
+        for version in VERSIONS {
+            let mut w = Writer::default();
+            sink_input(&mut w, version, 40, false, "music");
+            sink_input(&mut w, version, 41, true, "event");
+            let inputs = parse_sink_inputs(&w.into_bytes(), version).unwrap();
+
+            let found: Vec<(u32, bool, bool)> = inputs
+                .iter()
+                .map(|i| (i.index, i.mute, i.is_event_sound()))
+                .collect();
+            assert_eq!(
+                found,
+                [(40, false, false), (41, true, true)],
+                "version {version}"
+            );
+            assert_eq!(inputs[0].properties["application.name"], "Firefox");
+        }
+    }
+
+    #[test]
+    fn sink_inputs_of_a_newer_layout_do_not_parse_as_older() {
+        // Reading a version 21 reply as version 14 leaves the later
+        // fields where the next entry should start.
+        let mut w = Writer::default();
+        sink_input(&mut w, 21, 40, false, "music");
+        sink_input(&mut w, 21, 41, false, "music");
+        assert!(parse_sink_inputs(&w.into_bytes(), 14).is_err());
+    }
+
+    #[test]
+    fn empty_sink_input_list() {
+        assert_eq!(parse_sink_inputs(&[], PROTOCOL_VERSION).unwrap(), []);
+    }
+
+    #[test]
+    fn reads_stream_rules() {
+        let mut w = Writer::default();
+        w.string(Some("sink-input-by-application-name:Firefox"))
+            .channel_map(&[1, 2])
+            .cvolume(&[0x8000, 0x8000])
+            .string(Some("alsa_output.usb"))
+            .bool(false)
+            // Rules that only set a device have no volume.
+            .string(Some(EVENT_SOUNDS))
+            .channel_map(&[])
+            .cvolume(&[])
+            .string(None)
+            .bool(true);
+        let reply = w.into_bytes();
+
+        assert_eq!(
+            parse_stream_rule(&reply, EVENT_SOUNDS).unwrap(),
+            Some(StreamRule {
+                channel_map: vec![],
+                volume: vec![],
+                device: None,
+                mute: true,
+            })
+        );
+        let firefox = parse_stream_rule(&reply, "sink-input-by-application-name:Firefox")
+            .unwrap()
+            .unwrap();
+        assert_eq!(firefox.device.as_deref(), Some("alsa_output.usb"));
+        assert_eq!(firefox.volume, [0x8000, 0x8000]);
+        assert_eq!(parse_stream_rule(&reply, "missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,384 @@
+// Copyright 2025 a7mddra
+// SPDX-License-Identifier: Apache-2.0
+
+//! The tagged serialization of native protocol packets.
+//!
+//! Every value is a one-byte type tag followed by its big-endian
+//! encoding, so a reader can check and skip values it does not need.
+
+use std::collections::HashMap;
+use std::io;
+
+const STRING: u8 = b't';
+const STRING_NULL: u8 = b'N';
+const U32: u8 = b'L';
+const U8: u8 = b'B';
+const U64: u8 = b'R';
+const S64: u8 = b'r';
+const SAMPLE_SPEC: u8 = b'a';
+const ARBITRARY: u8 = b'x';
+const BOOLEAN_TRUE: u8 = b'1';
+const BOOLEAN_FALSE: u8 = b'0';
+const TIMEVAL: u8 = b'T';
+const USEC: u8 = b'U';
+const CHANNEL_MAP: u8 = b'm';
+const CVOLUME: u8 = b'v';
+const PROPLIST: u8 = b'P';
+const VOLUME: u8 = b'V';
+const FORMAT_INFO: u8 = b'f';
+
+/// Builds a packet payload.
+#[derive(Default)]
+pub(crate) struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.data.push(U32);
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn string(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(s) => {
+                self.data.push(STRING);
+                self.data.extend_from_slice(s.as_bytes());
+                self.data.push(0);
+            }
+            None => self.data.push(STRING_NULL),
+        }
+        self
+    }
+
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.data
+            .push(if value { BOOLEAN_TRUE } else { BOOLEAN_FALSE });
+        self
+    }
+
+    pub fn arbitrary(&mut self, bytes: &[u8]) -> &mut Self {
+        self.data.push(ARBITRARY);
+        self.data
+            .extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn channel_map(&mut self, positions: &[u8]) -> &mut Self {
+        self.data.push(CHANNEL_MAP);
+        self.data.push(positions.len() as u8);
+        self.data.extend_from_slice(positions);
+        self
+    }
+
+    pub fn cvolume(&mut self, volumes: &[u32]) -> &mut Self {
+        self.data.push(CVOLUME);
+        self.data.push(volumes.len() as u8);
+        for v in volumes {
+            self.data.extend_from_slice(&v.to_be_bytes());
+        }
+        self
+    }
+
+    /// String properties, stored NUL-terminated as the server expects.
+    pub fn proplist(&mut self, properties: &[(&str, &str)]) -> &mut Self {
+        self.data.push(PROPLIST);
+        for (key, value) in properties {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            self.string(Some(key))
+                .u32(bytes.len() as u32)
+                .arbitrary(&bytes);
+        }
+        self.string(None)
+    }
+}
+
+/// Reads values back out of a packet payload.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed packet: {what}"),
+    )
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Everything not read yet.
+    pub fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(invalid("truncated"));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn raw_u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn raw_u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn tag(&mut self, expected: u8) -> io::Result<()> {
+        if self.raw_u8()? != expected {
+            return Err(invalid("unexpected type"));
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        self.tag(U32)?;
+        self.raw_u32()
+    }
+
+    pub fn string(&mut self) -> io::Result<Option<String>> {
+        if self.peek() == Some(STRING_NULL) {
+            self.take(1)?;
+            return Ok(None);
+        }
+        self.tag(STRING)?;
+        let end = self
+            .data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+        let s = String::from_utf8_lossy(self.take(end)?).into_owned();
+        self.take(1)?;
+        Ok(Some(s))
+    }
+
+    pub fn bool(&mut self) -> io::Result<bool> {
+        match self.raw_u8()? {
+            BOOLEAN_TRUE => Ok(true),
+            BOOLEAN_FALSE => Ok(false),
+            _ => Err(invalid("unexpected type")),
+        }
+    }
+
+    pub fn arbitrary(&mut self) -> io::Result<&'a [u8]> {
+        self.tag(ARBITRARY)?;
+        let len = self.raw_u32()? as usize;
+        self.take(len)
+    }
+
+    /// Channel positions.
+    pub fn channel_map(&mut self) -> io::Result<Vec<u8>> {
+        self.tag(CHANNEL_MAP)?;
+        let channels = self.raw_u8()? as usize;
+        Ok(self.take(channels)?.to_vec())
+    }
+
+    /// Volume of each channel.
+    pub fn cvolume(&mut self) -> io::Result<Vec<u32>> {
+        self.tag(CVOLUME)?;
+        let channels = self.raw_u8()?;
+        (0..channels).map(|_| self.raw_u32()).collect()
+    }
+
+    /// Properties whose values are strings; binary ones are left out.
+    pub fn proplist(&mut self) -> io::Result<HashMap<String, String>> {
+        self.tag(PROPLIST)?;
+        let mut properties = HashMap::new();
+        while let Some(key) = self.string()? {
+            let len = self.u32()? as usize;
+            let value = self.arbitrary()?;
+            if value.len() != len {
+                return Err(invalid("property length"));
+            }
+            if let Some(text) = value.strip_suffix(&[0]) {
+                if let Ok(text) = std::str::from_utf8(text) {
+                    properties.insert(key, text.to_string());
+                }
+            }
+        }
+        Ok(properties)
+    }
+
+    /// Skip one value of any type.
+    pub fn skip(&mut self) -> io::Result<()> {
+        match self.peek().ok_or_else(|| invalid("truncated"))? {
+            STRING | STRING_NULL => {
+                self.string()?;
+            }
+            U32 | VOLUME => {
+                self.take(5)?;
+            }
+            U8 => {
+                self.take(2)?;
+            }
+            U64 | S64 | USEC | TIMEVAL => {
+                self.take(9)?;
+            }
+            SAMPLE_SPEC => {
+                self.take(7)?;
+            }
+            ARBITRARY => {
+                self.arbitrary()?;
+            }
+            BOOLEAN_TRUE | BOOLEAN_FALSE => {
+                self.take(1)?;
+            }
+            CHANNEL_MAP => {
+                self.channel_map()?;
+            }
+            CVOLUME => {
+                self.cvolume()?;
+            }
+            PROPLIST => {
+                self.proplist()?;
+            }
+            FORMAT_INFO => {
+                self.take(1)?;
+                self.skip()?;
+                self.proplist()?;
+            }
+            _ => return Err(invalid("unknown type")),
+        }
+        Ok(())
+    }
+
+    /// Skip `count` values.
+    pub fn skip_n(&mut self, count: usize) -> io::Result<()> {
+        (0..count).try_for_each(|_| self.skip())
+    }
+}
+
+/// Values only the server sends, for building replies in tests.
+#[cfg(test)]
+impl Writer {
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.data.extend_from_slice(&[U8, value]);
+        self
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.data.push(U64);
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn usec(&mut self, value: u64) -> &mut Self {
+        self.data.push(USEC);
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn volume(&mut self, value: u32) -> &mut Self {
+        self.data.push(VOLUME);
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Sample format, channel count and rate.
+    pub fn sample_spec(&mut self, format: u8, channels: u8, rate: u32) -> &mut Self {
+        self.data
+            .extend_from_slice(&[SAMPLE_SPEC, format, channels]);
+        self.data.extend_from_slice(&rate.to_be_bytes());
+        self
+    }
+
+    pub fn format_info(&mut self, encoding: u8, properties: &[(&str, &str)]) -> &mut Self {
+        self.data.push(FORMAT_INFO);
+        self.u8(encoding).proplist(properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_every_type() {
+        let mut w = Writer::default();
+        w.string(Some("name"))
+            .string(None)
+            .u32(7)
+            .volume(0x10000)
+            .u8(3)
+            .u64(1 << 40)
+            .usec(25_000)
+            .sample_spec(3, 2, 48_000)
+            .arbitrary(&[1, 2, 3])
+            .bool(true)
+            .bool(false)
+            .channel_map(&[1, 2])
+            .cvolume(&[0x8000, 0x9000])
+            .proplist(&[("media.role", "event")])
+            .format_info(1, &[("format.rate", "48000")]);
+        let mut data = w.into_bytes();
+        // Timevals are two 32-bit words.
+        data.push(TIMEVAL);
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&[U32, 0, 0, 0, 42]);
+
+        let mut r = Reader::new(&data);
+        r.skip_n(16).unwrap();
+        assert_eq!(r.u32().unwrap(), 42);
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn reads_values_back() {
+        let mut w = Writer::default();
+        w.u32(5)
+            .string(Some("sink"))
+            .string(None)
+            .bool(true)
+            .arbitrary(b"cookie")
+            .channel_map(&[1, 2])
+            .cvolume(&[0x10000, 0x8000])
+            .proplist(&[("application.name", "ocrmyimg")]);
+        let data = w.into_bytes();
+
+        let mut r = Reader::new(&data);
+        assert_eq!(r.u32().unwrap(), 5);
+        assert_eq!(r.string().unwrap().as_deref(), Some("sink"));
+        assert_eq!(r.string().unwrap(), None);
+        assert!(r.bool().unwrap());
+        assert_eq!(r.arbitrary().unwrap(), b"cookie");
+        assert_eq!(r.channel_map().unwrap(), [1, 2]);
+        assert_eq!(r.cvolume().unwrap(), [0x10000, 0x8000]);
+        let properties = r.proplist().unwrap();
+        assert_eq!(properties["application.name"], "ocrmyimg");
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_data() {
+        let invalid = |data: &[u8]| Reader::new(data).skip().unwrap_err().kind();
+        assert_eq!(invalid(b"?"), io::ErrorKind::InvalidData);
+        assert_eq!(invalid(&[U32, 0, 0]), io::ErrorKind::InvalidData);
+        assert_eq!(invalid(&[STRING, b'a']), io::ErrorKind::InvalidData);
+        assert_eq!(invalid(&[]), io::ErrorKind::InvalidData);
+        assert!(Reader::new(&[STRING_NULL]).u32().is_err());
+    }
+}
@@ -20,14 +20,18 @@ text and bounding boxes from images.
         print(f"{result.text} at {result.box.center}")
 """
 
-from .engine import OCREngine
+from .engine import OCREngine, EngineInitError
 from .config import EngineConfig
-from .models import OCRResult, BoundingBox, NumpyEncoder
+from .models import OCRResult, OCRPage, Word, EngineInfo, BoundingBox, NumpyEncoder
 
 __all__ = [
     "OCREngine",
+    "EngineInitError",
     "EngineConfig", 
     "OCRResult",
+    "OCRPage",
+    "Word",
+    "EngineInfo",
     "BoundingBox",
     "NumpyEncoder",
 ]
@@ -17,12 +17,23 @@ import logging
 from pathlib import Path
 from typing import List, Optional
 
+import paddleocr
 from paddleocr import PaddleOCR
+from PIL import Image
 
-from .models import OCRResult, BoundingBox
+from .models import OCRResult, OCRPage, EngineInfo, BoundingBox
 from .config import EngineConfig
 
 
+class EngineInitError(RuntimeError):
+    """
+    Raised when PaddleOCR cannot be initialized.
+    
+    Separates model loading failures from per-image processing
+    failures so callers can report them differently.
+    """
+
+
 class OCREngine:
     """
     Main OCR engine class for text extraction from images.
@@ -64,7 +75,7 @@ class OCREngine:
         Lazily initialize and return the PaddleOCR instance.
         
         @return Configured PaddleOCR instance.
-        @raises RuntimeError If initialization fails.
+        @raises EngineInitError If initialization fails.
         """
         if self._ocr is None:
             try:
@@ -77,9 +88,17 @@ class OCREngine:
                     cls_model_dir=str(self.config.cls_model_dir),
                 )
             except Exception as e:
-                raise RuntimeError(f"Failed to initialize PaddleOCR: {e}") from e
+                raise EngineInitError(f"Failed to initialize PaddleOCR: {e}") from e
         return self._ocr
     
+    def warm_up(self) -> None:
+        """
+        Initialize PaddleOCR ahead of the first request.
+        
+        @raises EngineInitError If initialization fails.
+        """
+        self._get_ocr()
+    
     def process(self, image_path: str) -> List[OCRResult]:
         """
         Process an image and extract text with bounding boxes.
@@ -87,6 +106,7 @@ class OCREngine:
         @param image_path Path to the image file.
         @return List of OCRResult objects containing text and coordinates.
         @raises FileNotFoundError If the image file doesn't exist.
+        @raises EngineInitError If PaddleOCR cannot be initialized.
         @raises RuntimeError If OCR processing fails.
         """
         if not os.path.exists(image_path):
@@ -101,6 +121,45 @@ class OCREngine:
         
         return self._parse_results(result)
     
+    def process_page(self, image_path: str) -> OCRPage:
+        """
+        Process an image and return its results with page metadata.
+        
+        @param image_path Path to the image file.
+        @return OCRPage with image size, lines and engine info.
+        @raises FileNotFoundError If the image file doesn't exist.
+        @raises EngineInitError If PaddleOCR cannot be initialized.
+        @raises RuntimeError If OCR processing fails.
+        """
+        lines = self.process(image_path)
+        
+        with Image.open(image_path) as image:
+            width, height = image.size
+        
+        return OCRPage(
+            width=width,
+            height=height,
+            lines=lines,
+            engine=self.info(),
+        )
+    
+    def info(self) -> EngineInfo:
+        """
+        Describe the engine and the models in use.
+        
+        @return EngineInfo for this configuration.
+        """
+        return EngineInfo(
+            name="paddleocr",
+            version=getattr(paddleocr, "__version__", "unknown"),
+            lang=self.config.lang,
+            models=[
+                self.config.det_model_dir.name,
+                self.config.rec_model_dir.name,
+                self.config.cls_model_dir.name,
+            ],
+        )
+    
     def _parse_results(self, raw_result) -> List[OCRResult]:
         """
         Parse raw PaddleOCR output into structured results.
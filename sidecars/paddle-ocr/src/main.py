@@ -6,18 +6,31 @@
 OCR Engine CLI - Command-line interface for text extraction.
 
 This is the main entry point for the OCR engine executable.
-It processes an image file and outputs detected text with
-bounding box coordinates as JSON.
+It processes an image file and outputs the page size, engine
+info and detected lines (with confidences and estimated word
+boxes) as JSON.
 
 @author a7mddra
 @version 1.0.0
 
 @usage
     ocr-engine <image_path>
+    ocr-engine --serve
     
 @example
     $ ocr-engine document.png
-    [{"text": "Hello World", "box": [[10,20], [100,20], [100,50], [10,50]]}]
+    {"width": 640, "height": 480,
+     "engine": {"name": "paddleocr", "version": "2.7.3", "lang": "en", "models": [...]},
+     "lines": [{"text": "Hello World", "box": [[10,20], [100,20], [100,50], [10,50]],
+                "confidence": 0.98, "words": [{"text": "Hello", "box": [...], "confidence": 0.98}, ...]}]}
+
+    On failure a single object is printed instead, with a machine
+    readable code next to the message:
+
+    {"error": "Image not found: missing.png", "code": "image_not_found"}
+
+    With --serve the engine stays running and answers line-delimited
+    JSON-RPC requests on stdin (see src/server.py).
 """
 
 import sys
@@ -27,7 +40,20 @@ from pathlib import Path
 if __name__ == "__main__":
     sys.path.insert(0, str(Path(__file__).parent.parent))
 
-from src import OCREngine, NumpyEncoder
+from src import OCREngine, EngineInitError
+from src.server import serve
+
+
+def emit_error(code: str, message: str) -> int:
+    """
+    Print an error object to stdout.
+    
+    @param code Machine readable error code.
+    @param message Human readable description.
+    @return Exit code for the process.
+    """
+    print(json.dumps({"error": message, "code": code}))
+    return 1
 
 
 def main() -> int:
@@ -41,30 +67,29 @@ def main() -> int:
     """
 
     if len(sys.argv) < 2:
-        error = {"error": "No image path provided"}
-        print(json.dumps(error))
-        return 1
+        return emit_error("invalid_arguments", "No image path provided")
+    
+    if sys.argv[1] == "--serve":
+        return serve()
     
     image_path = sys.argv[1]
     
     if not Path(image_path).exists():
-        error = {"error": f"Image not found: {image_path}"}
-        print(json.dumps(error))
-        return 1
+        return emit_error("image_not_found", f"Image not found: {image_path}")
     
     try:
         engine = OCREngine()
-        results = engine.process(image_path)
-        
-        output = [result.to_dict() for result in results]
-        print(json.dumps(output, cls=NumpyEncoder))
+        page = engine.process_page(image_path)
+        print(page.to_json())
         
         return 0
         
+    except FileNotFoundError as e:
+        return emit_error("image_not_found", str(e))
+    except EngineInitError as e:
+        return emit_error("engine_init", str(e))
     except Exception as e:
-        error = {"error": str(e)}
-        print(json.dumps(error))
-        return 1
+        return emit_error("ocr_failed", str(e))
 
 
 if __name__ == "__main__":
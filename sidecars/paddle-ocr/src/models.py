@@ -13,7 +13,7 @@ OCR results including bounding boxes and text detections.
 
 import json
 from dataclasses import dataclass, field
-from typing import List, Tuple
+from typing import List, Optional, Tuple
 import numpy as np
 
 
@@ -104,6 +104,53 @@ class BoundingBox:
         @return Height in pixels.
         """
         return abs(self.bottom_left[1] - self.top_left[1])
+    
+    def slice(self, start: float, end: float) -> "BoundingBox":
+        """
+        Get the sub-box spanning a fraction of the baseline.
+        
+        Interpolates along the top and bottom edges, so rotated
+        boxes stay rotated.
+        
+        @param start Start fraction in [0, 1].
+        @param end End fraction in [0, 1].
+        @return BoundingBox covering the given span.
+        """
+        def lerp(a, b, t):
+            return [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+        
+        return BoundingBox(
+            top_left=lerp(self.top_left, self.top_right, start),
+            top_right=lerp(self.top_left, self.top_right, end),
+            bottom_right=lerp(self.bottom_left, self.bottom_right, end),
+            bottom_left=lerp(self.bottom_left, self.bottom_right, start),
+        )
+
+
+@dataclass
+class Word:
+    """
+    Represents a single word inside a detected line.
+    
+    PaddleOCR recognizes whole lines, so word boxes are estimated
+    by splitting the line box proportionally to character counts.
+    """
+    
+    text: str
+    box: BoundingBox
+    confidence: float = 1.0
+    
+    def to_dict(self) -> dict:
+        """
+        Convert word to dictionary for JSON serialization.
+        
+        @return Dictionary with text, box and confidence fields.
+        """
+        return {
+            "text": self.text,
+            "box": self.box.to_list(),
+            "confidence": self.confidence,
+        }
 
 
 @dataclass
@@ -126,15 +173,42 @@ class OCRResult:
     box: BoundingBox
     confidence: float = 1.0
     
+    def words(self) -> List[Word]:
+        """
+        Split the line into words with estimated boxes.
+        
+        Each word gets the span of the box matching its character
+        offsets in the line text, and the line confidence.
+        
+        @return List of Word objects in text order.
+        """
+        total = len(self.text)
+        if total == 0:
+            return []
+        
+        words = []
+        offset = 0
+        for token in self.text.split():
+            start = self.text.index(token, offset)
+            offset = start + len(token)
+            words.append(Word(
+                text=token,
+                box=self.box.slice(start / total, offset / total),
+                confidence=self.confidence,
+            ))
+        return words
+    
     def to_dict(self) -> dict:
         """
         Convert result to dictionary for JSON serialization.
         
-        @return Dictionary with text and box fields.
+        @return Dictionary with text, box, confidence and words fields.
         """
         return {
             "text": self.text,
             "box": self.box.to_list(),
+            "confidence": self.confidence,
+            "words": [word.to_dict() for word in self.words()],
         }
     
     def to_json(self) -> str:
@@ -144,3 +218,70 @@ class OCRResult:
         @return JSON string representation.
         """
         return json.dumps(self.to_dict(), cls=NumpyEncoder)
+
+
+@dataclass
+class EngineInfo:
+    """
+    Describes the engine and models that produced a result.
+    """
+    
+    name: str
+    version: str
+    lang: str
+    models: List[str] = field(default_factory=list)
+    
+    def to_dict(self) -> dict:
+        """
+        Convert engine info to dictionary for JSON serialization.
+        
+        @return Dictionary with name, version, lang and models fields.
+        """
+        return {
+            "name": self.name,
+            "version": self.version,
+            "lang": self.lang,
+            "models": self.models,
+        }
+
+
+@dataclass
+class OCRPage:
+    """
+    Represents the OCR results for one image.
+    
+    Carries the image size and engine metadata next to the
+    detected lines so consumers can lay out and attribute them.
+    
+    @example
+        page = engine.process_page("image.png")
+        print(page.width, page.height, len(page.lines))
+    """
+    
+    width: int
+    height: int
+    lines: List[OCRResult]
+    engine: Optional[EngineInfo] = None
+    
+    def to_dict(self) -> dict:
+        """
+        Convert page to dictionary for JSON serialization.
+        
+        @return Dictionary with width, height, lines and engine fields.
+                An unknown engine is written with empty fields, never
+                as null, to match the Rust `Page` type.
+        """
+        return {
+            "width": self.width,
+            "height": self.height,
+            "lines": [line.to_dict() for line in self.lines],
+            "engine": (self.engine or EngineInfo("", "", "")).to_dict(),
+        }
+    
+    def to_json(self) -> str:
+        """
+        Convert page to JSON string.
+        
+        @return JSON string representation.
+        """
+        return json.dumps(self.to_dict(), cls=NumpyEncoder)
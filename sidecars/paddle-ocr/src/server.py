@@ -0,0 +1,176 @@
+# Copyright 2025 a7mddra
+# SPDX-License-Identifier: Apache-2.0
+
+"""
+Persistent OCR server speaking line-delimited JSON-RPC 2.0.
+
+Keeps a single OCREngine warm for the lifetime of the process so
+PaddleOCR is only initialized once. Each request and response is a
+single JSON object on its own line.
+
+@author a7mddra
+@version 1.0.0
+
+@example
+    -> {"jsonrpc": "2.0", "id": 1, "method": "ocr", "params": {"path": "a.png"}}
+    <- {"jsonrpc": "2.0", "id": 1, "result": {"width": 640, "height": 480, "lines": [...], ...}}
+
+    -> {"jsonrpc": "2.0", "id": 2, "method": "shutdown"}
+    <- {"jsonrpc": "2.0", "id": 2, "result": null}
+"""
+
+import sys
+import json
+from pathlib import Path
+from typing import Any, Optional, TextIO
+
+from .engine import OCREngine, EngineInitError
+from .models import NumpyEncoder
+
+
+PARSE_ERROR = -32700
+INVALID_REQUEST = -32600
+METHOD_NOT_FOUND = -32601
+INVALID_PARAMS = -32602
+
+IMAGE_NOT_FOUND = -32001
+ENGINE_INIT = -32002
+OCR_FAILED = -32003
+
+
+class OCRServer:
+    """
+    JSON-RPC server wrapping a warm OCREngine.
+
+    Supported methods:
+        ocr       {"path": str} -> page (see OCRPage.to_dict)
+        ping      -> "pong"
+        shutdown  -> null, then the server stops reading
+    """
+
+    def __init__(self, out: TextIO, engine: Optional[OCREngine] = None):
+        """
+        Initialize the server.
+
+        @param out Stream responses are written to.
+        @param engine Engine instance. Created with defaults if not provided.
+        """
+        self.out = out
+        self.engine = engine or OCREngine()
+        self.running = True
+
+    def serve(self, stream: TextIO) -> int:
+        """
+        Read requests from a stream until EOF or shutdown.
+
+        @param stream Input stream with one request per line.
+        @return Exit code (always 0).
+        """
+        try:
+            self.engine.warm_up()
+            self._notify("ready")
+        except EngineInitError as e:
+            self._notify("init_failed", {"message": str(e)})
+
+        for raw in stream:
+            line = raw.strip()
+            if line:
+                self._handle_line(line)
+            if not self.running:
+                break
+
+        return 0
+
+    def _handle_line(self, line: str) -> None:
+        """
+        Decode and dispatch a single request line.
+
+        @param line Raw JSON text.
+        """
+        try:
+            request = json.loads(line)
+        except json.JSONDecodeError as e:
+            self._error(None, PARSE_ERROR, f"Parse error: {e}")
+            return
+
+        if not isinstance(request, dict) or "method" not in request:
+            self._error(None, INVALID_REQUEST, "Invalid request")
+            return
+
+        request_id = request.get("id")
+        method = request["method"]
+        params = request.get("params") or {}
+
+        if method == "ocr":
+            self._handle_ocr(request_id, params)
+        elif method == "ping":
+            self._result(request_id, "pong")
+        elif method == "shutdown":
+            self.running = False
+            self._result(request_id, None)
+        else:
+            self._error(request_id, METHOD_NOT_FOUND, f"Unknown method: {method}")
+
+    def _handle_ocr(self, request_id: Any, params: dict) -> None:
+        """
+        Run OCR for an `ocr` request.
+
+        @param request_id Request id to answer.
+        @param params Request parameters containing `path`.
+        """
+        image_path = params.get("path") if isinstance(params, dict) else None
+        if not isinstance(image_path, str):
+            self._error(request_id, INVALID_PARAMS, "Missing image path")
+            return
+
+        if not Path(image_path).exists():
+            self._error(request_id, IMAGE_NOT_FOUND, f"Image not found: {image_path}")
+            return
+
+        try:
+            page = self.engine.process_page(image_path)
+            self._result(request_id, page.to_dict())
+        except FileNotFoundError as e:
+            self._error(request_id, IMAGE_NOT_FOUND, str(e))
+        except EngineInitError as e:
+            self._error(request_id, ENGINE_INIT, str(e))
+        except Exception as e:
+            self._error(request_id, OCR_FAILED, str(e))
+
+    def _result(self, request_id: Any, result: Any) -> None:
+        """Write a success response."""
+        self._write({"jsonrpc": "2.0", "id": request_id, "result": result})
+
+    def _error(self, request_id: Any, code: int, message: str) -> None:
+        """Write an error response."""
+        self._write({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "error": {"code": code, "message": message},
+        })
+
+    def _notify(self, method: str, params: Optional[dict] = None) -> None:
+        """Write a notification (a message without an id)."""
+        message = {"jsonrpc": "2.0", "method": method}
+        if params is not None:
+            message["params"] = params
+        self._write(message)
+
+    def _write(self, message: dict) -> None:
+        """Write one message as a single line and flush."""
+        self.out.write(json.dumps(message, cls=NumpyEncoder) + "\n")
+        self.out.flush()
+
+
+def serve() -> int:
+    """
+    Run the server on stdin/stdout.
+
+    Anything the OCR libraries print goes to stderr so stdout only
+    ever carries protocol messages.
+
+    @return Exit code.
+    """
+    out = sys.stdout
+    sys.stdout = sys.stderr
+    return OCRServer(out).serve(sys.stdin)
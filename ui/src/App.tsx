@@ -5,8 +5,8 @@
  */
 
 import { useState, useRef, useEffect, useCallback } from "react";
-import { Command, open } from "@tauri-apps/plugin-shell";
-import { convertFileSrc } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./index.css";
 
 // Components
@@ -24,20 +24,70 @@ import {
   useLens,
 } from "./features/google";
 
+interface OCRWord {
+  text: string;
+  box: number[][];
+  confidence: number;
+}
+
 interface OCRBox {
   text: string;
   box: number[][];
+  confidence: number;
+  words: OCRWord[];
+}
+
+interface OCRPage {
+  width: number;
+  height: number;
+  lines: OCRBox[];
+  engine: { name: string; version: string; lang: string; models: string[] };
+}
+
+interface OCRDocumentPage {
+  // Image the page was read from (extracted for TIFF and PDF input).
+  image: string;
+  // EXIF orientation applied before OCR, e.g. "rotate90".
+  orientation: string;
+  page: OCRPage;
+  // Skew corrected before OCR, in degrees.
+  skew: number;
+  // Whole page in reading order.
+  text: string;
+}
+
+interface OCRDocument {
+  pages: OCRDocumentPage[];
+}
+
+// Preprocessing presets understood by `run_ocr`.
+const PRESETS = [
+  { value: "original", label: "Original" },
+  { value: "grayscale", label: "Grayscale" },
+  { value: "scan", label: "Scan" },
+  { value: "photo", label: "Photo" },
+];
+
+interface OCRError {
+  kind: string;
+  message: string;
 }
 
 function App() {
   const [path, setPath] = useState("");
+  const [preset, setPreset] = useState("original");
   const [src, setSrc] = useState("");
-  const [data, setData] = useState<OCRBox[]>([]);
+  const [pages, setPages] = useState<OCRDocumentPage[]>([]);
+  const [pageIndex, setPageIndex] = useState(0);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState("");
   const [size, setSize] = useState({ w: 0, h: 0 });
   const [showViewer, setShowViewer] = useState(false);
 
+  const current = pages[pageIndex];
+  const data = current?.page.lines ?? [];
+  const pageText = current?.text ?? "";
+
   // Menu State
   const [menuActive, setMenuActive] = useState(false);
   const [isSelectAllMode, setIsSelectAllMode] = useState(false);
@@ -71,28 +121,58 @@ function App() {
     },
   });
 
-  const scan = async () => {
-    if (!path.trim()) return;
+  const scan = async (target: string = path) => {
+    if (!target.trim()) return;
     setLoading(true);
     setError("");
     setShowViewer(true);
     hideMenu();
-    setSrc(convertFileSrc(path));
+    setPages([]);
+    setPageIndex(0);
+    // TIFFs and PDFs can't be shown until their pages are extracted.
+    setSrc(/\.(tiff?|pdf)$/i.test(target) ? "" : convertFileSrc(target));
 
     try {
-      const cmd = Command.sidecar("binaries/ocr-engine", [path]);
-      const out = await cmd.execute();
-      if (out.code !== 0) throw new Error(out.stderr || "Failed");
-      const result = JSON.parse(out.stdout.trim());
-      if (result.error) throw new Error(result.error);
-      setData(result);
+      const doc = await invoke<OCRDocument>("run_ocr", { path: target, preset });
+      setPages(doc.pages);
+      if (doc.pages.length > 0) setSrc(convertFileSrc(doc.pages[0].image));
     } catch (e) {
-      setError(e instanceof Error ? e.message : "Error");
+      setError((e as OCRError)?.message || String(e) || "Error");
     } finally {
       setLoading(false);
     }
   };
 
+  // Images from the command line: this launch's, then any forwarded by
+  // later launches while the app is open.
+  const scanRef = useRef(scan);
+  scanRef.current = scan;
+  useEffect(() => {
+    const open = (target: string) => {
+      setPath(target);
+      scanRef.current(target);
+    };
+    // Listen first: the backend holds forwarded images until the
+    // launch image is taken, then only emits them.
+    const unlisten = listen<string>("open-image", (e) => open(e.payload));
+    unlisten.then(() =>
+      invoke<string | null>("take_launch_image").then((target) => {
+        if (target) open(target);
+      }),
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  const goToPage = (index: number) => {
+    if (index < 0 || index >= pages.length) return;
+    hideMenu();
+    window.getSelection()?.removeAllRanges();
+    setPageIndex(index);
+    setSrc(convertFileSrc(pages[index].image));
+  };
+
   const onLoad = () => {
     if (imgRef.current) {
       setSize({
@@ -388,18 +468,21 @@ function App() {
       if (action === "selectAll") {
         triggerSelectAll();
       } else {
-        const text = getSelectedText();
+        // The DOM selection follows line order but has no line breaks,
+        // so select-all uses the text laid out by the backend.
+        const text = isSelectAllMode ? pageText : getSelectedText();
         if (action === "copy") {
           if (text) navigator.clipboard.writeText(text);
         } else if (action === "search") {
-          if (text) open(generateSearchUrl(text));
+          if (text) invoke("open_external_url", { url: generateSearchUrl(text) });
         } else if (action === "translate") {
-          if (text) open(generateTranslateUrl(text));
+          if (text)
+            invoke("open_external_url", { url: generateTranslateUrl(text) });
         }
         hideMenu();
       }
     },
-    [triggerSelectAll, hideMenu]
+    [triggerSelectAll, hideMenu, isSelectAllMode, pageText]
   );
 
   const handleSelection = useCallback(() => {
@@ -505,14 +588,44 @@ function App() {
       <div className="input-bar">
         <input
           type="text"
-          placeholder="Image path"
+          placeholder="Image, TIFF or PDF path"
           value={path}
           onChange={(e) => setPath(e.target.value)}
           onKeyDown={(e) => e.key === "Enter" && scan()}
         />
-        <button onClick={scan} disabled={loading || !path.trim()}>
+        <select
+          value={preset}
+          onChange={(e) => setPreset(e.target.value)}
+          title="Preprocessing"
+        >
+          {PRESETS.map((p) => (
+            <option key={p.value} value={p.value}>
+              {p.label}
+            </option>
+          ))}
+        </select>
+        <button onClick={() => scan()} disabled={loading || !path.trim()}>
           {loading ? "..." : "Scan"}
         </button>
+        {pages.length > 1 && (
+          <div className="pager">
+            <button
+              onClick={() => goToPage(pageIndex - 1)}
+              disabled={pageIndex === 0}
+            >
+              ‹
+            </button>
+            <span>
+              {pageIndex + 1} / {pages.length}
+            </span>
+            <button
+              onClick={() => goToPage(pageIndex + 1)}
+              disabled={pageIndex === pages.length - 1}
+            >
+              ›
+            </button>
+          </div>
+        )}
       </div>
 
       {showViewer ? (